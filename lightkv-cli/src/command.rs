@@ -0,0 +1,194 @@
+use bytes::Bytes;
+use clap::Subcommand;
+use lightkv::engine::Engine;
+use lightkv::options::IteratorOptions;
+use lightkv::Errors;
+use lightkv_client::{Client, ClientError};
+use std::path::PathBuf;
+
+/// Commands of both the command line and the interactive shell
+#[derive(Subcommand, Debug, PartialEq)]
+pub enum Command {
+    /// Print value of a key
+    Get { key: String },
+
+    /// Set value of a key
+    Put { key: String, value: String },
+
+    /// Delete a key
+    Delete { key: String },
+
+    /// List keys starting with a prefix
+    Scan {
+        /// Prefix of listed keys, all keys if empty
+        #[arg(long, default_value = "")]
+        prefix: String,
+
+        /// Max number of listed keys
+        #[arg(long, default_value_t = 100)]
+        limit: usize,
+    },
+
+    /// Print status of a local database
+    Stat,
+
+    /// Compact data files of a local database
+    Compact,
+
+    /// Copy a local database into another directory
+    Backup { dest: PathBuf },
+}
+
+/// Database the commands run against
+pub enum Backend {
+    Local(Box<Engine>),
+    Remote(Client),
+}
+
+impl Backend {
+    /// Run command and return its output
+    pub async fn execute(&self, command: Command) -> Result<String, String> {
+        match self {
+            Backend::Local(engine) => execute_local(engine, command).map_err(|e| e.to_string()),
+            Backend::Remote(client) => execute_remote(client, command)
+                .await
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn close(&self) -> Result<(), String> {
+        match self {
+            Backend::Local(engine) => engine.close().map_err(|e| e.to_string()),
+            Backend::Remote(client) => {
+                client.close();
+                Ok(())
+            }
+        }
+    }
+}
+
+fn execute_local(engine: &Engine, command: Command) -> lightkv::Result<String> {
+    match command {
+        Command::Get { key } => match engine.get(Bytes::from(key)) {
+            Ok(value) => Ok(String::from_utf8_lossy(&value).into_owned()),
+            Err(Errors::KeyNotFound) => Ok("(nil)".to_string()),
+            Err(e) => Err(e),
+        },
+        Command::Put { key, value } => {
+            engine.put(Bytes::from(key), Bytes::from(value))?;
+            Ok("OK".to_string())
+        }
+        Command::Delete { key } => {
+            let key = Bytes::from(key);
+            if !engine.exists(key.clone())? {
+                return Ok("(nil)".to_string());
+            }
+            engine.remove(key)?;
+            Ok("OK".to_string())
+        }
+        Command::Scan { prefix, limit } => {
+            let iter = engine.iter(IteratorOptions {
+                prefix: prefix.into_bytes(),
+                reverse: false,
+            });
+            let mut keys = Vec::new();
+            while keys.len() < limit {
+                match iter.next() {
+                    Some((key, _)) => keys.push(key),
+                    None => break,
+                }
+            }
+            Ok(format_keys(&keys))
+        }
+        Command::Stat => {
+            let stat = engine.stat()?;
+            Ok(format!(
+                "key_counts: {}\nfile_counts: {}\ndisk_size: {}\nreclaimable_size: {}\nactive_file_id: {}\ndisk_full: {}",
+                stat.key_counts,
+                stat.file_counts,
+                stat.disk_size,
+                stat.reclaimable_size,
+                stat.active_file_id,
+                stat.disk_full
+            ))
+        }
+        Command::Compact => {
+            engine.compact()?;
+            Ok("OK, applied when the database is reopened".to_string())
+        }
+        Command::Backup { dest } => {
+            engine.backup(&dest)?;
+            Ok(format!("OK, backup written to {}", dest.display()))
+        }
+    }
+}
+
+async fn execute_remote(client: &Client, command: Command) -> Result<String, ClientError> {
+    match command {
+        Command::Get { key } => match client.get(Bytes::from(key)).await {
+            Ok(value) => Ok(String::from_utf8_lossy(&value).into_owned()),
+            Err(ClientError::Engine(Errors::KeyNotFound)) => Ok("(nil)".to_string()),
+            Err(e) => Err(e),
+        },
+        Command::Put { key, value } => {
+            client.set(Bytes::from(key), Bytes::from(value)).await?;
+            Ok("OK".to_string())
+        }
+        Command::Delete { key } => match client.del(Bytes::from(key)).await? {
+            true => Ok("OK".to_string()),
+            false => Ok("(nil)".to_string()),
+        },
+        Command::Scan { prefix, limit } => {
+            let pattern = format!("{}*", escape_glob(&prefix));
+            let mut keys = Vec::new();
+            let mut cursor = Bytes::from("0");
+            loop {
+                let (next_cursor, matched) =
+                    client.scan(cursor, Some(&pattern), Some(limit)).await?;
+                keys.extend(matched);
+                if next_cursor == "0" || keys.len() >= limit {
+                    break;
+                }
+                cursor = next_cursor;
+            }
+            keys.truncate(limit);
+            Ok(format_keys(&keys))
+        }
+        Command::Stat | Command::Compact | Command::Backup { .. } => Err(ClientError::Server(
+            "command is only available on a local database opened with --dir".to_string(),
+        )),
+    }
+}
+
+fn format_keys(keys: &[Bytes]) -> String {
+    if keys.is_empty() {
+        return "(empty)".to_string();
+    }
+    keys.iter()
+        .map(|key| String::from_utf8_lossy(key).into_owned())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// match prefix literally in a glob pattern
+fn escape_glob(prefix: &str) -> String {
+    let mut escaped = String::with_capacity(prefix.len());
+    for c in prefix.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command::escape_glob;
+
+    #[test]
+    fn test_escape_glob() {
+        assert_eq!(escape_glob("user:"), "user:");
+        assert_eq!(escape_glob("a*b?[c]\\"), "a\\*b\\?\\[c\\]\\\\");
+    }
+}
@@ -0,0 +1,179 @@
+mod command;
+
+use crate::command::{Backend, Command};
+use clap::Parser;
+use lightkv::engine::Engine;
+use lightkv::options::{ClientConfig, NetworkType, Options};
+use lightkv_client::Client;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::path::PathBuf;
+use std::process::exit;
+
+/// Command line shell of lightkv, runs one command and exits if given,
+/// otherwise starts an interactive shell
+#[derive(Parser)]
+#[command(name = "lightkv-cli", version)]
+struct Args {
+    /// Open a local database directory instead of connecting to a server
+    #[arg(long, conflicts_with_all = ["config", "addr", "network", "tls_ca"])]
+    dir: Option<PathBuf>,
+
+    /// TOML client config, `LIGHTKV_CLIENT_*` environment variables override it
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Address of lightkv server [default: 127.0.0.1:6380]
+    #[arg(long)]
+    addr: Option<String>,
+
+    /// Transport to server: tcp or quic [default: tcp]
+    #[arg(long)]
+    network: Option<NetworkType>,
+
+    /// Certificate trusted by QUIC transport in PEM or DER
+    #[arg(long)]
+    tls_ca: Option<PathBuf>,
+
+    /// History file of the interactive shell, defaults to ~/.lightkv_history
+    #[arg(long)]
+    history: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+// a line of the interactive shell
+#[derive(Parser)]
+#[command(no_binary_name = true, disable_version_flag = true)]
+struct Line {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    let backend = match open_backend(&args).await {
+        Ok(backend) => backend,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            exit(1);
+        }
+    };
+
+    let success = match args.command {
+        Some(command) => print_result(backend.execute(command).await),
+        None => {
+            let history = args.history.or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".lightkv_history"))
+            });
+            run_shell(&backend, history).await;
+            true
+        }
+    };
+    if let Err(e) = backend.close() {
+        eprintln!("error: {}", e);
+        exit(1);
+    }
+    if !success {
+        exit(1);
+    }
+}
+
+async fn open_backend(args: &Args) -> Result<Backend, String> {
+    if let Some(dir) = args.dir.as_ref() {
+        let options = Options {
+            path: dir.clone(),
+            ..Default::default()
+        };
+        return Engine::open(options)
+            .map(|engine| Backend::Local(Box::new(engine)))
+            .map_err(|e| e.to_string());
+    }
+    let mut config = ClientConfig::load(args.config.as_deref()).map_err(|e| e.to_string())?;
+    if let Some(addr) = args.addr.as_ref() {
+        config.general_config.addr = addr.clone();
+    }
+    if let Some(network) = args.network.as_ref() {
+        config.general_config.network = network.clone();
+    }
+    if args.tls_ca.is_some() {
+        config.tls_ca = args.tls_ca.clone();
+    }
+    config.validate().map_err(|e| e.to_string())?;
+    Client::connect(config)
+        .await
+        .map(Backend::Remote)
+        .map_err(|e| e.to_string())
+}
+
+async fn run_shell(backend: &Backend, history: Option<PathBuf>) {
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return;
+        }
+    };
+    if let Some(history) = history.as_ref() {
+        // missing history file on first run is fine
+        let _ = editor.load_history(history);
+    }
+
+    loop {
+        let line = match editor.readline("lightkv> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                break;
+            }
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line);
+        if matches!(line, "exit" | "quit") {
+            break;
+        }
+
+        let words = match shlex::split(line) {
+            Some(words) => words,
+            None => {
+                eprintln!("error: unbalanced quotes");
+                continue;
+            }
+        };
+        match Line::try_parse_from(words) {
+            Ok(line) => {
+                print_result(backend.execute(line.command).await);
+            }
+            // help and usage errors of clap
+            Err(e) => {
+                let _ = e.print();
+            }
+        }
+    }
+
+    if let Some(history) = history.as_ref() {
+        if let Err(e) = editor.save_history(history) {
+            eprintln!("save history {} err: {}", history.display(), e);
+        }
+    }
+}
+
+fn print_result(result: Result<String, String>) -> bool {
+    match result {
+        Ok(output) => {
+            println!("{}", output);
+            true
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            false
+        }
+    }
+}
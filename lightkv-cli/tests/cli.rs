@@ -0,0 +1,118 @@
+use lightkv::options::{GeneralConfig, NetworkType, ServerConfig, StorageConfig};
+use lightkv::server::Server;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::process::Command;
+
+// run one non-interactive command, return success with stdout or stderr
+async fn run(args: &[&str]) -> (bool, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_lightkv-cli"))
+        .args(args)
+        .output()
+        .await
+        .unwrap();
+    let text = match output.status.success() {
+        true => output.stdout,
+        false => output.stderr,
+    };
+    (output.status.success(), String::from_utf8(text).unwrap())
+}
+
+async fn run_ok(args: &[&str], expected: &str) {
+    assert_eq!(
+        run(args).await,
+        (true, format!("{}\n", expected)),
+        "{:?}",
+        args
+    );
+}
+
+#[tokio::test]
+async fn test_local_commands() {
+    let path = std::env::temp_dir().join("lightkv-cli-local");
+    let backup_path = std::env::temp_dir().join("lightkv-cli-local-backup");
+    let _ = std::fs::remove_dir_all(&path);
+    let _ = std::fs::remove_dir_all(&backup_path);
+    let dir = path.to_str().unwrap();
+
+    run_ok(&["--dir", dir, "put", "user:1", "alice"], "OK").await;
+    run_ok(&["--dir", dir, "put", "user:2", "bob"], "OK").await;
+    run_ok(&["--dir", dir, "put", "order:1", "book"], "OK").await;
+    run_ok(&["--dir", dir, "get", "user:1"], "alice").await;
+    run_ok(&["--dir", dir, "get", "user:3"], "(nil)").await;
+    run_ok(
+        &["--dir", dir, "scan", "--prefix", "user:"],
+        "user:1\nuser:2",
+    )
+    .await;
+    run_ok(&["--dir", dir, "scan", "--limit", "1"], "order:1").await;
+    run_ok(&["--dir", dir, "delete", "user:2"], "OK").await;
+    run_ok(&["--dir", dir, "delete", "user:2"], "(nil)").await;
+
+    let (success, stat) = run(&["--dir", dir, "stat"]).await;
+    assert!(success);
+    assert!(stat.starts_with("key_counts: 2\n"), "{}", stat);
+
+    let backup = backup_path.to_str().unwrap();
+    run_ok(
+        &["--dir", dir, "backup", backup],
+        &format!("OK, backup written to {}", backup),
+    )
+    .await;
+    run_ok(&["--dir", backup, "get", "order:1"], "book").await;
+
+    // engine errors fail the command
+    let (success, error) = run(&["--dir", dir, "put", "", "value"]).await;
+    assert!(!success);
+    assert_eq!(error, "error: key is empty\n");
+
+    std::fs::remove_dir_all(&path).unwrap();
+    std::fs::remove_dir_all(&backup_path).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_remote_commands() {
+    let path = std::env::temp_dir().join("lightkv-cli-remote");
+    let _ = std::fs::remove_dir_all(&path);
+    let config = ServerConfig {
+        general_config: GeneralConfig {
+            addr: "127.0.0.1:0".to_string(),
+            network: NetworkType::Tcp,
+        },
+        storage: StorageConfig {
+            path: path.clone(),
+            ..Default::default()
+        },
+        metrics_addr: None,
+        ..Default::default()
+    };
+    let server = Arc::new(Server::bind(&config).await.unwrap());
+    let serving = tokio::spawn({
+        let server = server.clone();
+        async move { server.serve().await }
+    });
+    let addr = server.local_addr().to_string();
+
+    run_ok(&["--addr", &addr, "put", "user:*", "alice"], "OK").await;
+    run_ok(&["--addr", &addr, "put", "user:2", "bob"], "OK").await;
+    run_ok(&["--addr", &addr, "get", "user:*"], "alice").await;
+    // prefix is matched literally
+    run_ok(&["--addr", &addr, "scan", "--prefix", "user:*"], "user:*").await;
+    run_ok(&["--addr", &addr, "delete", "user:2"], "OK").await;
+    run_ok(&["--addr", &addr, "get", "user:2"], "(nil)").await;
+
+    let (success, error) = run(&["--addr", &addr, "stat"]).await;
+    assert!(!success);
+    assert!(
+        error.contains("only available on a local database"),
+        "{}",
+        error
+    );
+    assert!(!run(&["--dir", "db", "--addr", &addr, "stat"]).await.0);
+
+    serving.abort();
+    let _ = serving.await;
+    server.close().unwrap();
+    drop(server);
+    std::fs::remove_dir_all(Path::new(&path)).unwrap();
+}
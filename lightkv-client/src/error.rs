@@ -0,0 +1,79 @@
+use lightkv::server::resp::Frame;
+use lightkv::Errors;
+use std::result;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum ClientError {
+    /// Engine error reported by server, such as `Errors::KeyNotFound`
+    #[error("{0}")]
+    Engine(Errors),
+
+    #[error("server error: {0}")]
+    Server(String),
+
+    #[error("failed to connect server: {0}")]
+    Connect(String),
+
+    #[error("connection io error: {0}")]
+    Io(String),
+
+    #[error("invalid server reply")]
+    Protocol,
+
+    #[error("request timed out")]
+    Timeout,
+
+    #[error("failed to load tls certificate: {0}")]
+    TlsConfig(String),
+}
+
+pub type Result<T> = result::Result<T, ClientError>;
+
+impl From<std::io::Error> for ClientError {
+    fn from(e: std::io::Error) -> Self {
+        ClientError::Io(e.to_string())
+    }
+}
+
+// engine errors are replied as `ERR <message>`, other errors are kept as they are
+pub(crate) fn reply_error(frame: &Frame) -> Option<ClientError> {
+    let message = match frame {
+        Frame::Error(message) => message,
+        _ => return None,
+    };
+    if message.starts_with("WRONGTYPE ") {
+        return Some(ClientError::Engine(Errors::WrongType));
+    }
+    let engine_error = message
+        .strip_prefix("ERR ")
+        .and_then(Errors::from_message)
+        .map(ClientError::Engine);
+    Some(engine_error.unwrap_or_else(|| ClientError::Server(message.clone())))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{reply_error, ClientError};
+    use lightkv::server::resp::Frame;
+    use lightkv::Errors;
+
+    #[test]
+    fn test_reply_error() {
+        assert_eq!(reply_error(&Frame::Simple("OK".to_string())), None);
+        assert_eq!(
+            reply_error(&Frame::Error(format!("ERR {}", Errors::KeyIsEmpty))),
+            Some(ClientError::Engine(Errors::KeyIsEmpty))
+        );
+        assert_eq!(
+            reply_error(&Frame::Error(
+                "WRONGTYPE Operation against a key holding the wrong kind of value".to_string()
+            )),
+            Some(ClientError::Engine(Errors::WrongType))
+        );
+        assert_eq!(
+            reply_error(&Frame::Error("ERR syntax error".to_string())),
+            Some(ClientError::Server("ERR syntax error".to_string()))
+        );
+    }
+}
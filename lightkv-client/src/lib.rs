@@ -0,0 +1,301 @@
+//! Async client of lightkv server, requests are sent over TCP or QUIC
+//! according to `ClientConfig::general_config.network`
+
+mod error;
+mod quic;
+mod tcp;
+
+pub use error::{ClientError, Result};
+
+use crate::error::reply_error;
+use crate::quic::QuicTransport;
+use crate::tcp::TcpPool;
+use bytes::{Bytes, BytesMut};
+use lightkv::options::{ClientConfig, NetworkType};
+use lightkv::server::resp::{Frame, ProtocolVersion};
+use lightkv::Errors;
+use log::warn;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Client of a lightkv server, cheap to clone and share between tasks
+#[derive(Clone)]
+pub struct Client {
+    inner: Arc<ClientInner>,
+}
+
+struct ClientInner {
+    transport: Transport,
+    request_timeout: Duration,
+}
+
+enum Transport {
+    Tcp(TcpPool),
+    Quic(QuicTransport),
+}
+
+/// Reply of a command in a batch
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reply {
+    /// Value of `get`
+    Value(Bytes),
+    /// `set` succeeded
+    Ok,
+    /// Whether `del` removed the key
+    Deleted(bool),
+}
+
+#[derive(Clone, Copy)]
+enum ReplyKind {
+    Value,
+    Ok,
+    Deleted,
+}
+
+impl Client {
+    /// Connect to server, fails if it is not reachable after all retries
+    pub async fn connect(config: ClientConfig) -> Result<Self> {
+        let transport = match config.general_config.network {
+            NetworkType::Tcp => Transport::Tcp(TcpPool::connect(&config).await?),
+            NetworkType::Quic => Transport::Quic(QuicTransport::connect(&config).await?),
+        };
+        Ok(Self {
+            inner: Arc::new(ClientInner {
+                transport,
+                request_timeout: Duration::from_millis(config.request_timeout_ms),
+            }),
+        })
+    }
+
+    /// Get value of key, `Errors::KeyNotFound` if it does not exist
+    pub async fn get(&self, key: Bytes) -> Result<Bytes> {
+        let reply = self.request_one(vec![Bytes::from("GET"), key]).await?;
+        into_reply(reply, ReplyKind::Value).map(|reply| match reply {
+            Reply::Value(value) => value,
+            _ => unreachable!(),
+        })
+    }
+
+    pub async fn set(&self, key: Bytes, value: Bytes) -> Result<()> {
+        let reply = self
+            .request_one(vec![Bytes::from("SET"), key, value])
+            .await?;
+        into_reply(reply, ReplyKind::Ok).map(|_| ())
+    }
+
+    /// Delete key, return whether it existed
+    pub async fn del(&self, key: Bytes) -> Result<bool> {
+        let reply = self.request_one(vec![Bytes::from("DEL"), key]).await?;
+        into_reply(reply, ReplyKind::Deleted).map(|reply| reply == Reply::Deleted(true))
+    }
+
+    /// Scan keys from `cursor`, keys are filtered by glob `pattern` after up to `count`
+    /// keys are visited, return the next cursor with matched keys, cursor `0` starts
+    /// and ends the iteration, other cursors are opaque
+    pub async fn scan(
+        &self,
+        cursor: Bytes,
+        pattern: Option<&str>,
+        count: Option<usize>,
+    ) -> Result<(Bytes, Vec<Bytes>)> {
+        let mut command = vec![Bytes::from("SCAN"), cursor];
+        if let Some(pattern) = pattern {
+            command.push(Bytes::from("MATCH"));
+            command.push(Bytes::copy_from_slice(pattern.as_bytes()));
+        }
+        if let Some(count) = count {
+            command.push(Bytes::from("COUNT"));
+            command.push(Bytes::from(count.to_string()));
+        }
+        let reply = self.request_one(command).await?;
+        if let Some(e) = reply_error(&reply) {
+            return Err(e);
+        }
+        let (next_cursor, keys) = match reply {
+            Frame::Array(mut items) if items.len() == 2 => {
+                let keys = items.pop().unwrap();
+                (items.pop().unwrap(), keys)
+            }
+            _ => return Err(ClientError::Protocol),
+        };
+        let next_cursor = match next_cursor {
+            Frame::Bulk(cursor) => cursor,
+            _ => return Err(ClientError::Protocol),
+        };
+        let keys = match keys {
+            Frame::Array(keys) => keys
+                .into_iter()
+                .map(|key| match key {
+                    Frame::Bulk(key) => Ok(key),
+                    _ => Err(ClientError::Protocol),
+                })
+                .collect::<Result<Vec<Bytes>>>()?,
+            _ => return Err(ClientError::Protocol),
+        };
+        Ok((next_cursor, keys))
+    }
+
+    /// Batch of commands sent together in one round trip
+    pub fn batch(&self) -> Batch<'_> {
+        Batch {
+            client: self,
+            commands: Vec::new(),
+            kinds: Vec::new(),
+        }
+    }
+
+    /// Close QUIC connection, pooled TCP connections are closed once client is dropped
+    pub fn close(&self) {
+        if let Transport::Quic(quic) = &self.inner.transport {
+            quic.close();
+        }
+    }
+
+    async fn request_one(&self, command: Vec<Bytes>) -> Result<Frame> {
+        let mut replies = self.request(vec![command]).await?;
+        replies.pop().ok_or(ClientError::Protocol)
+    }
+
+    async fn request(&self, commands: Vec<Vec<Bytes>>) -> Result<Vec<Frame>> {
+        let mut buf = BytesMut::new();
+        for command in commands.iter() {
+            let args = command.iter().cloned().map(Frame::Bulk).collect();
+            Frame::Array(args).encode(&mut buf, ProtocolVersion::Resp2);
+        }
+        // reads are safe to resend on a new connection, writes may be applied twice
+        let idempotent = commands.iter().all(|command| is_read_only(&command[0]));
+        match &self.inner.transport {
+            Transport::Tcp(tcp) => {
+                self.timeout(tcp.request(&buf, commands.len(), idempotent))
+                    .await
+            }
+            Transport::Quic(quic) => self.timeout(quic.request(&buf, commands.len())).await,
+        }
+    }
+
+    async fn timeout<T>(&self, request: impl Future<Output = Result<T>>) -> Result<T> {
+        match tokio::time::timeout(self.inner.request_timeout, request).await {
+            Ok(result) => result,
+            Err(_) => Err(ClientError::Timeout),
+        }
+    }
+}
+
+/// Commands pipelined in one request, each command gets its own reply
+pub struct Batch<'a> {
+    client: &'a Client,
+    commands: Vec<Vec<Bytes>>,
+    kinds: Vec<ReplyKind>,
+}
+
+impl Batch<'_> {
+    pub fn get(&mut self, key: Bytes) -> &mut Self {
+        self.push(vec![Bytes::from("GET"), key], ReplyKind::Value)
+    }
+
+    pub fn set(&mut self, key: Bytes, value: Bytes) -> &mut Self {
+        self.push(vec![Bytes::from("SET"), key, value], ReplyKind::Ok)
+    }
+
+    pub fn del(&mut self, key: Bytes) -> &mut Self {
+        self.push(vec![Bytes::from("DEL"), key], ReplyKind::Deleted)
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Send all commands, the outer error means the request failed as a whole,
+    /// commands are not atomic so some of them may still be applied
+    pub async fn execute(self) -> Result<Vec<Result<Reply>>> {
+        if self.commands.is_empty() {
+            return Ok(Vec::new());
+        }
+        let replies = self.client.request(self.commands).await?;
+        Ok(replies
+            .into_iter()
+            .zip(self.kinds)
+            .map(|(reply, kind)| into_reply(reply, kind))
+            .collect())
+    }
+
+    fn push(&mut self, command: Vec<Bytes>, kind: ReplyKind) -> &mut Self {
+        self.commands.push(command);
+        self.kinds.push(kind);
+        self
+    }
+}
+
+fn is_read_only(command: &[u8]) -> bool {
+    command.eq_ignore_ascii_case(b"GET") || command.eq_ignore_ascii_case(b"SCAN")
+}
+
+fn into_reply(frame: Frame, kind: ReplyKind) -> Result<Reply> {
+    if let Some(e) = reply_error(&frame) {
+        return Err(e);
+    }
+    match (kind, frame) {
+        (ReplyKind::Value, Frame::Bulk(value)) => Ok(Reply::Value(value)),
+        (ReplyKind::Value, Frame::Null) => Err(ClientError::Engine(Errors::KeyNotFound)),
+        (ReplyKind::Ok, Frame::Simple(_)) => Ok(Reply::Ok),
+        (ReplyKind::Deleted, Frame::Integer(count)) => Ok(Reply::Deleted(count > 0)),
+        _ => Err(ClientError::Protocol),
+    }
+}
+
+// read reply frames until `count` of them are complete
+pub(crate) async fn read_frames<R>(
+    reader: &mut R,
+    buf: &mut BytesMut,
+    count: usize,
+) -> Result<Vec<Frame>>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut frames = Vec::with_capacity(count);
+    while frames.len() < count {
+        match Frame::parse(buf) {
+            Ok(Some(frame)) => {
+                frames.push(frame);
+                continue;
+            }
+            Ok(None) => {}
+            Err(_) => return Err(ClientError::Protocol),
+        }
+        if reader.read_buf(buf).await? == 0 {
+            return Err(ClientError::Io("connection closed by server".to_string()));
+        }
+    }
+    Ok(frames)
+}
+
+// retry `connect` up to `max_retries` times, the delay doubles after each failure
+pub(crate) async fn connect_with_backoff<T, F, Fut>(config: &ClientConfig, connect: F) -> Result<T>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut backoff = Duration::from_millis(config.retry_backoff_ms);
+    let mut attempt = 0;
+    loop {
+        match connect().await {
+            Ok(conn) => return Ok(conn),
+            Err(e) if attempt >= config.max_retries => return Err(e),
+            Err(e) => {
+                warn!(
+                    "connect {} err: {}, retry in {:?}",
+                    config.general_config.addr, e, backoff
+                );
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+        }
+    }
+}
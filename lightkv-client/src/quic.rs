@@ -0,0 +1,143 @@
+use crate::error::{ClientError, Result};
+use crate::{connect_with_backoff, read_frames};
+use bytes::BytesMut;
+use lightkv::options::ClientConfig;
+use lightkv::server::resp::Frame;
+use quinn::rustls::pki_types::pem::PemObject;
+use quinn::rustls::pki_types::CertificateDer;
+use quinn::rustls::RootCertStore;
+use quinn::{Connection, Endpoint};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// QUIC transport, requests share one connection and each runs on its own
+/// bidirectional stream, the connection is rebuilt once it is lost
+pub(crate) struct QuicTransport {
+    config: ClientConfig,
+    endpoint: Endpoint,
+    connection: Mutex<Option<Connection>>,
+}
+
+impl QuicTransport {
+    pub(crate) async fn connect(config: &ClientConfig) -> Result<Self> {
+        let roots = match config.tls_ca.as_ref() {
+            Some(path) => load_roots(path)?,
+            None => {
+                return Err(ClientError::TlsConfig(
+                    "quic transport requires tls_ca".to_string(),
+                ))
+            }
+        };
+        let client_config = quinn::ClientConfig::with_root_certificates(Arc::new(roots))
+            .map_err(|e| ClientError::TlsConfig(e.to_string()))?;
+        let bind_addr: SocketAddr = match config.general_config.addr.contains('[') {
+            true => "[::]:0".parse().unwrap(),
+            false => "0.0.0.0:0".parse().unwrap(),
+        };
+        let mut endpoint = Endpoint::client(bind_addr)?;
+        endpoint.set_default_client_config(client_config);
+
+        let transport = Self {
+            config: config.clone(),
+            endpoint,
+            connection: Mutex::new(None),
+        };
+        // fail early if server is not reachable
+        transport.connection().await?;
+        Ok(transport)
+    }
+
+    /// Send pipelined request on a new stream and read `count` reply frames
+    pub(crate) async fn request(&self, request: &[u8], count: usize) -> Result<Vec<Frame>> {
+        let connection = self.connection().await?;
+        let (mut send, mut recv) = match connection.open_bi().await {
+            Ok(stream) => stream,
+            // connection is lost since last request, reconnect once
+            Err(_) => self
+                .reconnect(&connection)
+                .await?
+                .open_bi()
+                .await
+                .map_err(io_error)?,
+        };
+        send.write_all(request).await.map_err(io_error)?;
+        send.finish().map_err(io_error)?;
+        read_frames(&mut recv, &mut BytesMut::new(), count).await
+    }
+
+    pub(crate) fn close(&self) {
+        self.endpoint.close(0u32.into(), b"");
+    }
+
+    async fn connection(&self) -> Result<Connection> {
+        let mut connection = self.connection.lock().await;
+        match connection.as_ref() {
+            Some(conn) if conn.close_reason().is_none() => Ok(conn.clone()),
+            _ => {
+                let conn = self.new_connection().await?;
+                *connection = Some(conn.clone());
+                Ok(conn)
+            }
+        }
+    }
+
+    async fn reconnect(&self, lost: &Connection) -> Result<Connection> {
+        let mut connection = self.connection.lock().await;
+        // another request may have reconnected already
+        if let Some(conn) = connection.as_ref() {
+            if conn.stable_id() != lost.stable_id() && conn.close_reason().is_none() {
+                return Ok(conn.clone());
+            }
+        }
+        let conn = self.new_connection().await?;
+        *connection = Some(conn.clone());
+        Ok(conn)
+    }
+
+    async fn new_connection(&self) -> Result<Connection> {
+        let addr = self.config.general_config.addr.clone();
+        let timeout = Duration::from_millis(self.config.connect_timeout_ms);
+        connect_with_backoff(&self.config, || async {
+            let server_addr = match tokio::net::lookup_host(&addr).await?.next() {
+                Some(server_addr) => server_addr,
+                None => return Err(ClientError::Connect(format!("{}: no address", addr))),
+            };
+            let connecting = self
+                .endpoint
+                .connect(server_addr, &self.config.server_name)
+                .map_err(|e| ClientError::Connect(format!("{}: {}", addr, e)))?;
+            match tokio::time::timeout(timeout, connecting).await {
+                Ok(Ok(conn)) => Ok(conn),
+                Ok(Err(e)) => Err(ClientError::Connect(format!("{}: {}", addr, e))),
+                Err(_) => Err(ClientError::Connect(format!("{}: timed out", addr))),
+            }
+        })
+        .await
+    }
+}
+
+fn io_error(e: impl std::fmt::Display) -> ClientError {
+    ClientError::Io(e.to_string())
+}
+
+// trusted certificates in pem, or a single der certificate
+fn load_roots(path: &Path) -> Result<RootCertStore> {
+    let data = std::fs::read(path)
+        .map_err(|e| ClientError::TlsConfig(format!("{}: {}", path.display(), e)))?;
+    let certs = match data.starts_with(b"-----") {
+        true => CertificateDer::pem_slice_iter(&data)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| ClientError::TlsConfig(format!("{}: {}", path.display(), e)))?,
+        false => vec![CertificateDer::from(data)],
+    };
+    let mut roots = RootCertStore::empty();
+    for cert in certs {
+        roots
+            .add(cert)
+            .map_err(|e| ClientError::TlsConfig(format!("{}: {}", path.display(), e)))?;
+    }
+    Ok(roots)
+}
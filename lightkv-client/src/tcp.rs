@@ -0,0 +1,111 @@
+use crate::error::{ClientError, Result};
+use crate::{connect_with_backoff, read_frames};
+use bytes::BytesMut;
+use lightkv::options::ClientConfig;
+use lightkv::server::resp::Frame;
+use parking_lot::Mutex;
+use std::io;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::sync::Semaphore;
+
+struct PooledConnection {
+    stream: TcpStream,
+    // bytes read past the last reply
+    read_buf: BytesMut,
+}
+
+/// Pool of TCP connections, at most `pool_size` requests run at the same time
+/// and idle connections are reused by later requests
+pub(crate) struct TcpPool {
+    config: ClientConfig,
+    idle: Mutex<Vec<PooledConnection>>,
+    permits: Semaphore,
+}
+
+impl TcpPool {
+    pub(crate) async fn connect(config: &ClientConfig) -> Result<Self> {
+        let pool = Self {
+            config: config.clone(),
+            idle: Mutex::new(Vec::new()),
+            permits: Semaphore::new(config.pool_size.max(1)),
+        };
+        // fail early if server is not reachable
+        let conn = pool.new_connection().await?;
+        pool.idle.lock().push(conn);
+        Ok(pool)
+    }
+
+    /// Send pipelined request and read `count` reply frames, a request which
+    /// is not `idempotent` is only resent if none of it reached the server
+    pub(crate) async fn request(
+        &self,
+        request: &[u8],
+        count: usize,
+        idempotent: bool,
+    ) -> Result<Vec<Frame>> {
+        let _permit = self.permits.acquire().await.expect("pool semaphore closed");
+        let idle = self.idle.lock().pop();
+        let reused = idle.is_some();
+        let mut conn = match idle {
+            Some(conn) => conn,
+            None => self.new_connection().await?,
+        };
+        let result = match Self::send(&mut conn, request, count).await {
+            // idle connection may be closed by server meanwhile, retry once on a new one
+            // unless the server may have applied part of the request
+            (Err(ClientError::Io(_)), written) if reused && (!written || idempotent) => {
+                conn = self.new_connection().await?;
+                Self::send(&mut conn, request, count).await.0
+            }
+            (result, _) => result,
+        };
+        // connection in unknown state is dropped instead of returned
+        if result.is_ok() {
+            self.idle.lock().push(conn);
+        }
+        result
+    }
+
+    // write request and read its replies, also return whether any byte of
+    // the request was written
+    async fn send(
+        conn: &mut PooledConnection,
+        request: &[u8],
+        count: usize,
+    ) -> (Result<Vec<Frame>>, bool) {
+        let mut written = 0;
+        while written < request.len() {
+            let error = match conn.stream.write(&request[written..]).await {
+                Ok(0) => io::Error::from(io::ErrorKind::WriteZero),
+                Ok(size) => {
+                    written += size;
+                    continue;
+                }
+                Err(e) => e,
+            };
+            return (Err(error.into()), written > 0);
+        }
+        let result = read_frames(&mut conn.stream, &mut conn.read_buf, count).await;
+        (result, true)
+    }
+
+    async fn new_connection(&self) -> Result<PooledConnection> {
+        let addr = self.config.general_config.addr.clone();
+        let timeout = Duration::from_millis(self.config.connect_timeout_ms);
+        let stream = connect_with_backoff(&self.config, || async {
+            match tokio::time::timeout(timeout, TcpStream::connect(&addr)).await {
+                Ok(Ok(stream)) => Ok(stream),
+                Ok(Err(e)) => Err(ClientError::Connect(format!("{}: {}", addr, e))),
+                Err(_) => Err(ClientError::Connect(format!("{}: timed out", addr))),
+            }
+        })
+        .await?;
+        stream.set_nodelay(true)?;
+        Ok(PooledConnection {
+            stream,
+            read_buf: BytesMut::new(),
+        })
+    }
+}
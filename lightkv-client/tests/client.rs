@@ -0,0 +1,247 @@
+use bytes::Bytes;
+use lightkv::options::{ClientConfig, GeneralConfig, NetworkType, ServerConfig, StorageConfig};
+use lightkv::server::Server;
+use lightkv::Errors;
+use lightkv_client::{Client, ClientError, Reply};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+struct TestServer {
+    server: Arc<Server>,
+    serving: JoinHandle<lightkv::Result<()>>,
+    path: PathBuf,
+}
+
+impl TestServer {
+    async fn start(name: &str, addr: &str, network: NetworkType) -> Self {
+        let path = std::env::temp_dir().join(format!("lightkv-client-{}", name));
+        let _ = std::fs::remove_dir_all(&path);
+        let config = ServerConfig {
+            general_config: GeneralConfig {
+                addr: addr.to_string(),
+                network,
+            },
+            storage: StorageConfig {
+                path: path.clone(),
+                ..Default::default()
+            },
+            metrics_addr: None,
+            ..Default::default()
+        };
+        let server = Arc::new(Server::bind(&config).await.unwrap());
+        let serving = tokio::spawn({
+            let server = server.clone();
+            async move { server.serve().await }
+        });
+        Self {
+            server,
+            serving,
+            path,
+        }
+    }
+
+    fn client_config(&self, network: NetworkType) -> ClientConfig {
+        let tls_ca = self.server.certificate().map(|cert| {
+            let path = self.path.with_extension("der");
+            std::fs::write(&path, cert).unwrap();
+            path
+        });
+        ClientConfig {
+            general_config: GeneralConfig {
+                addr: self.server.local_addr().to_string(),
+                network,
+            },
+            pool_size: 2,
+            max_retries: 2,
+            retry_backoff_ms: 10,
+            tls_ca,
+            ..Default::default()
+        }
+    }
+
+    // stop serving and release the address and database directory
+    async fn stop(self) {
+        self.serving.abort();
+        let _ = self.serving.await;
+        self.server.close().unwrap();
+        drop(self.server);
+        let _ = std::fs::remove_file(self.path.with_extension("der"));
+        std::fs::remove_dir_all(&self.path).unwrap();
+    }
+}
+
+async fn check_commands(client: &Client) {
+    client
+        .set(Bytes::from("foo"), Bytes::from("bar"))
+        .await
+        .unwrap();
+    assert_eq!(
+        client.get(Bytes::from("foo")).await.unwrap(),
+        Bytes::from("bar")
+    );
+    assert_eq!(
+        client.get(Bytes::from("missing")).await,
+        Err(ClientError::Engine(Errors::KeyNotFound))
+    );
+    // engine errors reported by server are typed again
+    assert_eq!(
+        client.set(Bytes::new(), Bytes::from("bar")).await,
+        Err(ClientError::Engine(Errors::KeyIsEmpty))
+    );
+    assert!(client.del(Bytes::from("foo")).await.unwrap());
+    assert!(!client.del(Bytes::from("foo")).await.unwrap());
+
+    let mut batch = client.batch();
+    for i in 0..5 {
+        batch.set(
+            Bytes::from(format!("key-{}", i)),
+            Bytes::from(format!("value-{}", i)),
+        );
+    }
+    batch
+        .get(Bytes::from("key-3"))
+        .get(Bytes::from("missing"))
+        .del(Bytes::from("key-4"));
+    assert_eq!(batch.len(), 8);
+    let replies = batch.execute().await.unwrap();
+    assert_eq!(replies[..5], vec![Ok(Reply::Ok); 5]);
+    assert_eq!(replies[5], Ok(Reply::Value(Bytes::from("value-3"))));
+    assert_eq!(replies[6], Err(ClientError::Engine(Errors::KeyNotFound)));
+    assert_eq!(replies[7], Ok(Reply::Deleted(true)));
+
+    let (cursor, keys) = client
+        .scan(Bytes::from("0"), Some("key-*"), Some(2))
+        .await
+        .unwrap();
+    assert_eq!(keys, vec![Bytes::from("key-0"), Bytes::from("key-1")]);
+    let (cursor, keys) = client.scan(cursor, Some("key-*"), None).await.unwrap();
+    assert_eq!(cursor, "0");
+    assert_eq!(keys.len(), 2);
+
+    // concurrent requests share the pool or the quic connection
+    let mut handles = Vec::new();
+    for i in 0..16 {
+        let client = client.clone();
+        handles.push(tokio::spawn(async move {
+            let key = Bytes::from(format!("concurrent-{}", i));
+            client
+                .set(key.clone(), Bytes::from(i.to_string()))
+                .await
+                .unwrap();
+            client.get(key).await.unwrap()
+        }));
+    }
+    for (i, handle) in handles.into_iter().enumerate() {
+        assert_eq!(handle.await.unwrap(), Bytes::from(i.to_string()));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_tcp_client() {
+    let server = TestServer::start("tcp", "127.0.0.1:0", NetworkType::Tcp).await;
+    let client = Client::connect(server.client_config(NetworkType::Tcp))
+        .await
+        .unwrap();
+    check_commands(&client).await;
+    server.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_quic_client() {
+    let server = TestServer::start("quic", "127.0.0.1:0", NetworkType::Quic).await;
+    let client = Client::connect(server.client_config(NetworkType::Quic))
+        .await
+        .unwrap();
+    check_commands(&client).await;
+    client.close();
+    server.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_reconnect() {
+    let server = TestServer::start("reconnect-1", "127.0.0.1:0", NetworkType::Tcp).await;
+    let config = server.client_config(NetworkType::Tcp);
+    let client = Client::connect(config.clone()).await.unwrap();
+    client
+        .set(Bytes::from("foo"), Bytes::from("bar"))
+        .await
+        .unwrap();
+
+    // pooled connection is closed by server restart, request is retried on a new one
+    let addr = config.general_config.addr.clone();
+    server.stop().await;
+    let server = TestServer::start("reconnect-2", &addr, NetworkType::Tcp).await;
+    assert_eq!(
+        client.get(Bytes::from("foo")).await,
+        Err(ClientError::Engine(Errors::KeyNotFound))
+    );
+    client
+        .set(Bytes::from("foo"), Bytes::from("baz"))
+        .await
+        .unwrap();
+
+    server.stop().await;
+    assert!(matches!(
+        client.get(Bytes::from("foo")).await,
+        Err(ClientError::Connect(_))
+    ));
+    assert!(matches!(
+        Client::connect(config).await,
+        Err(ClientError::Connect(_))
+    ));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_resend_only_reads() {
+    use tokio::io::AsyncReadExt;
+
+    // server reading each request and closing the connection without a reply
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let requests = Arc::new(parking_lot::Mutex::new(Vec::new()));
+    tokio::spawn({
+        let requests = requests.clone();
+        async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let requests = requests.clone();
+                tokio::spawn(async move {
+                    let mut buf = vec![0; 1024];
+                    if let Ok(size) = stream.read(&mut buf).await {
+                        if size > 0 {
+                            requests.lock().push(buf[..size].to_vec());
+                        }
+                    }
+                });
+            }
+        }
+    });
+    let config = ClientConfig {
+        general_config: GeneralConfig {
+            addr,
+            network: NetworkType::Tcp,
+        },
+        ..Default::default()
+    };
+
+    // a write which may have been applied is never sent again
+    let client = Client::connect(config.clone()).await.unwrap();
+    assert!(matches!(
+        client.del(Bytes::from("foo")).await,
+        Err(ClientError::Io(_))
+    ));
+    assert_eq!(requests.lock().len(), 1);
+
+    // a read is resent once on a new connection
+    let client = Client::connect(config).await.unwrap();
+    assert!(matches!(
+        client.get(Bytes::from("foo")).await,
+        Err(ClientError::Io(_))
+    ));
+    let requests = requests.lock();
+    assert_eq!(requests.len(), 3);
+    assert!(requests[1..]
+        .iter()
+        .all(|request| request.ends_with(b"GET\r\n$3\r\nfoo\r\n")));
+}
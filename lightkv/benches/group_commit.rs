@@ -0,0 +1,53 @@
+use bytes::Bytes;
+use lightkv::engine::Engine;
+use lightkv::options::Options;
+use std::sync::Arc;
+use std::time::Instant;
+
+const WRITES_PER_WRITER: usize = 200;
+
+// measure sync put throughput with different numbers of concurrent writers
+fn bench_sync_writers(writers: usize) {
+    let options = Options {
+        path: std::env::temp_dir().join(format!("lightkv-bench-group-commit-{}", writers)),
+        sync_write: true,
+        ..Default::default()
+    };
+    let _ = std::fs::remove_dir_all(&options.path);
+    let engine = Arc::new(Engine::open(options.clone()).unwrap());
+
+    let start = Instant::now();
+    let handles: Vec<_> = (0..writers)
+        .map(|w| {
+            let engine = engine.clone();
+            std::thread::spawn(move || {
+                for i in 0..WRITES_PER_WRITER {
+                    let key = Bytes::from(format!("writer-{}-key-{}", w, i));
+                    engine.put(key, Bytes::from(vec![b'v'; 128])).unwrap();
+                }
+            })
+        })
+        .collect();
+    for handle in handles.into_iter() {
+        handle.join().unwrap();
+    }
+    let elapsed = start.elapsed();
+
+    let total = writers * WRITES_PER_WRITER;
+    println!(
+        "{:>3} sync writers: {:>6} puts in {:>8.2?}, {:>10.0} puts/sec",
+        writers,
+        total,
+        elapsed,
+        total as f64 / elapsed.as_secs_f64()
+    );
+
+    drop(engine);
+    let _ = std::fs::remove_dir_all(&options.path);
+}
+
+fn main() {
+    for writers in [1, 8, 64] {
+        bench_sync_writers(writers);
+    }
+}
@@ -0,0 +1,224 @@
+use crate::data::log_record::{LogRecord, LogRecordPos};
+use crate::errors::Errors;
+use crate::Result;
+use parking_lot::{Condvar, Mutex};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+// upper bound of bytes a leader writes for one group
+const MAX_GROUP_BYTES: usize = 4 * 1024 * 1024;
+
+/// Key and record applied to the memory index once the record is written
+pub(crate) type IndexUpdate = (Vec<u8>, LogRecord);
+
+/// WriteRequest holds encoded records which must be written together
+pub(crate) struct WriteRequest {
+    pub(crate) records: Vec<Vec<u8>>,
+    // one for each record, empty if records are kept out of the memory index
+    pub(crate) updates: Vec<IndexUpdate>,
+    pub(crate) sync: bool,
+    result: Mutex<Option<Result<Vec<LogRecordPos>>>>,
+}
+
+impl WriteRequest {
+    fn size(&self) -> usize {
+        self.records.iter().map(|record| record.len()).sum()
+    }
+}
+
+/// GroupCommit coalesces concurrent writes,
+/// the first queued writer becomes leader and writes records of the whole
+/// group with a single write and sync, followers wait until leader finished
+pub(crate) struct GroupCommit {
+    queue: Mutex<VecDeque<Arc<WriteRequest>>>,
+    cond: Condvar,
+}
+
+impl GroupCommit {
+    pub(crate) fn new() -> Self {
+        Self {
+            queue: Mutex::new(VecDeque::new()),
+            cond: Condvar::new(),
+        }
+    }
+
+    // submit records and return their positions once they are written,
+    // `write_group` is called by the leader with every request of the group
+    pub(crate) fn commit<F>(
+        &self,
+        records: Vec<Vec<u8>>,
+        updates: Vec<IndexUpdate>,
+        sync: bool,
+        write_group: F,
+    ) -> Result<Vec<LogRecordPos>>
+    where
+        F: FnOnce(&[Arc<WriteRequest>]) -> Vec<Result<Vec<LogRecordPos>>>,
+    {
+        let request = Arc::new(WriteRequest {
+            records,
+            updates,
+            sync,
+            result: Mutex::new(None),
+        });
+
+        let mut queue = self.queue.lock();
+        queue.push_back(request.clone());
+        loop {
+            if let Some(result) = request.result.lock().take() {
+                return result;
+            }
+            if Arc::ptr_eq(queue.front().unwrap(), &request) {
+                break;
+            }
+            self.cond.wait(&mut queue);
+        }
+
+        // become leader, collect following requests into the group
+        let mut group_bytes = 0;
+        let mut group = Vec::new();
+        for item in queue.iter() {
+            let size = item.size();
+            if !group.is_empty() && group_bytes + size > MAX_GROUP_BYTES {
+                break;
+            }
+            group_bytes += size;
+            group.push(item.clone());
+        }
+        // new writers queue up while leader is writing
+        drop(queue);
+
+        let mut guard = GroupGuard {
+            group_commit: self,
+            group: &group,
+            finished: false,
+        };
+        let results = write_group(&group);
+        self.finish(&group, results);
+        guard.finished = true;
+
+        let result = request.result.lock().take().unwrap();
+        result
+    }
+
+    // hand results to requests of the group, wake followers and the next leader
+    fn finish<I>(&self, group: &[Arc<WriteRequest>], results: I)
+    where
+        I: IntoIterator<Item = Result<Vec<LogRecordPos>>>,
+    {
+        let mut queue = self.queue.lock();
+        for (item, result) in group.iter().zip(results) {
+            queue.pop_front();
+            *item.result.lock() = Some(result);
+        }
+        self.cond.notify_all();
+    }
+}
+
+// fails requests of a group whose leader panicked while writing,
+// followers would wait forever otherwise
+struct GroupGuard<'a> {
+    group_commit: &'a GroupCommit,
+    group: &'a [Arc<WriteRequest>],
+    finished: bool,
+}
+
+impl Drop for GroupGuard<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let results = self.group.iter().map(|_| Err(Errors::WriteGroupAborted));
+            self.group_commit.finish(self.group, results);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::batch::GroupCommit;
+    use crate::data::log_record::LogRecordPos;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_group_commit() {
+        let group_commit = Arc::new(GroupCommit::new());
+        let offset = Arc::new(AtomicUsize::new(0));
+        let groups = Arc::new(AtomicUsize::new(0));
+
+        let mut handles = Vec::new();
+        for i in 0..32 {
+            let group_commit = group_commit.clone();
+            let offset = offset.clone();
+            let groups = groups.clone();
+            handles.push(std::thread::spawn(move || {
+                let records = vec![vec![i as u8; 10], vec![i as u8; 20]];
+                group_commit
+                    .commit(records, Vec::new(), true, |group| {
+                        groups.fetch_add(1, Ordering::SeqCst);
+                        std::thread::sleep(std::time::Duration::from_millis(5));
+                        let mut results = Vec::new();
+                        for request in group.iter() {
+                            let mut positions = Vec::new();
+                            for record in request.records.iter() {
+                                let pos = offset.fetch_add(record.len(), Ordering::SeqCst);
+                                positions.push(LogRecordPos { file_id: 0, offset: pos as u64, size: record.len() as u64 });
+                            }
+                            results.push(Ok(positions));
+                        }
+                        results
+                    })
+                    .unwrap()
+            }));
+        }
+
+        let mut positions: Vec<LogRecordPos> = Vec::new();
+        for handle in handles.into_iter() {
+            let result = handle.join().unwrap();
+            // records of one request are contiguous
+            assert_eq!(result.len(), 2);
+            assert_eq!(result[0].offset + 10, result[1].offset);
+            positions.extend(result);
+        }
+        assert_eq!(offset.load(Ordering::SeqCst), 32 * 30);
+        positions.sort_by_key(|pos| pos.offset);
+        positions.dedup_by_key(|pos| pos.offset);
+        assert_eq!(positions.len(), 64);
+        // waiting writers are coalesced into fewer groups
+        assert!(groups.load(Ordering::SeqCst) < 32);
+    }
+
+    #[test]
+    fn test_group_commit_leader_panic() {
+        let group_commit = Arc::new(GroupCommit::new());
+        let (started_tx, started_rx) = std::sync::mpsc::channel();
+
+        let leader = {
+            let group_commit = group_commit.clone();
+            std::thread::spawn(move || {
+                group_commit.commit(vec![vec![0; 10]], Vec::new(), false, |_| {
+                    started_tx.send(()).unwrap();
+                    std::thread::sleep(std::time::Duration::from_millis(50));
+                    panic!("leader failed");
+                })
+            })
+        };
+        started_rx.recv().unwrap();
+        // follower queued behind the panicking leader
+        let follower = {
+            let group_commit = group_commit.clone();
+            std::thread::spawn(move || {
+                group_commit.commit(vec![vec![1; 10]], Vec::new(), false, |group| {
+                    group.iter().map(|_| Ok(Vec::new())).collect()
+                })
+            })
+        };
+        assert!(leader.join().is_err());
+        // follower becomes the next leader instead of waiting forever
+        assert!(follower.join().unwrap().is_ok());
+
+        // queue is released for later writers
+        let result = group_commit.commit(vec![vec![2; 10]], Vec::new(), false, |group| {
+            group.iter().map(|_| Ok(Vec::new())).collect()
+        });
+        assert!(result.is_ok());
+    }
+}
@@ -0,0 +1,123 @@
+use clap::Parser;
+use lightkv::log::init_logger;
+use lightkv::options::{NetworkType, ServerConfig};
+use lightkv::server::Server;
+use lightkv::types::LogLevel;
+use log::{error, info};
+use std::path::PathBuf;
+use std::process::exit;
+
+/// Redis compatible server of lightkv, flags override the config file
+/// and `LIGHTKV_*` environment variables
+#[derive(Parser)]
+#[command(name = "lightkv-server", version)]
+struct Args {
+    /// TOML config file
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Address to listen on [default: 127.0.0.1:6380]
+    #[arg(long)]
+    addr: Option<String>,
+
+    /// Transport of RESP connections: tcp or quic [default: tcp]
+    #[arg(long)]
+    network: Option<NetworkType>,
+
+    /// PEM certificate chain of QUIC transport, self-signed if not set
+    #[arg(long, requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+
+    /// PEM private key of QUIC transport
+    #[arg(long, requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+
+    /// Database directory [default: lightkv-data]
+    #[arg(long)]
+    dir: Option<PathBuf>,
+
+    /// Address serving Prometheus metrics at /metrics
+    #[arg(long)]
+    metrics_addr: Option<String>,
+
+    /// Minimum level of logs: info, warn or error [default: info]
+    #[arg(long)]
+    log_level: Option<LogLevel>,
+
+    /// Log file rotated by size, logs go to stderr if not set
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+}
+
+impl Args {
+    fn apply(self, config: &mut ServerConfig) {
+        if let Some(addr) = self.addr {
+            config.general_config.addr = addr;
+        }
+        if let Some(network) = self.network {
+            config.general_config.network = network;
+        }
+        if self.tls_cert.is_some() {
+            config.tls_cert = self.tls_cert;
+            config.tls_key = self.tls_key;
+        }
+        if let Some(dir) = self.dir {
+            config.storage.path = dir;
+        }
+        if self.metrics_addr.is_some() {
+            config.metrics_addr = self.metrics_addr;
+        }
+        if let Some(log_level) = self.log_level {
+            config.log_config.level = log_level;
+        }
+        if self.log_file.is_some() {
+            config.log_config.file = self.log_file;
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    let mut config = match ServerConfig::load(args.config.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("load config err: {}", e);
+            exit(1);
+        }
+    };
+    args.apply(&mut config);
+    if let Err(e) = config.validate() {
+        eprintln!("load config err: {}", e);
+        exit(1);
+    }
+    if let Err(e) = init_logger(&config.log_config) {
+        eprintln!("init logger err: {}", e);
+        exit(1);
+    }
+
+    let server = match Server::bind(&config).await {
+        Ok(server) => server,
+        Err(e) => {
+            error!("start server err: {}", e);
+            exit(1);
+        }
+    };
+    info!("lightkv server listening on {}", server.local_addr());
+    if let Some(metrics_addr) = server.metrics_addr() {
+        info!("metrics served on http://{}/metrics", metrics_addr);
+    }
+
+    tokio::select! {
+        res = server.serve() => {
+            if let Err(e) = res {
+                error!("serve err: {}", e);
+            }
+        }
+        _ = tokio::signal::ctrl_c() => info!("shutting down"),
+    }
+    if let Err(e) = server.close() {
+        error!("close engine err: {}", e);
+        exit(1);
+    }
+}
@@ -0,0 +1,391 @@
+use crate::data::data_file::{DataFile, BLOB_FILE_NAME_SUFFIX};
+use crate::data::log_record::{LogRecord, LogRecordPos, RecordType};
+use crate::errors::Errors;
+use crate::options::{IOType, Options};
+use crate::Result;
+use log::warn;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Live and total bytes of a blob file
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct BlobFileStat {
+    // bytes still referenced by keys
+    pub live_size: u64,
+
+    // blob file size on disk
+    pub total_size: u64,
+}
+
+impl BlobFileStat {
+    pub fn live_ratio(&self) -> f32 {
+        if self.total_size == 0 {
+            return 1.0;
+        }
+        self.live_size as f32 / self.total_size as f32
+    }
+}
+
+/// BlobStore keeps large values in separate blob files,
+/// data files only store a reference to the blob record
+pub(crate) struct BlobStore {
+    options: Arc<Options>,
+
+    // created on first blob write
+    active_file: RwLock<Option<DataFile>>,
+    inactive_files: RwLock<HashMap<u64, DataFile>>,
+    next_file_id: AtomicU64,
+    // active file has writes which are not synced yet
+    dirty: AtomicBool,
+
+    // blob position of every key whose value lives in a blob file
+    refs: RwLock<HashMap<Vec<u8>, LogRecordPos>>,
+    // referenced bytes of each blob file
+    live_sizes: RwLock<HashMap<u64, u64>>,
+
+    // blob writes hold read lock until their reference is appended,
+    // compaction holds write lock while choosing blob files to collect
+    pub(crate) rotate_lock: RwLock<()>,
+}
+
+impl BlobStore {
+    pub(crate) fn open(options: Arc<Options>) -> Result<Self> {
+        let file_ids = load_blob_file_ids(&options.path)?;
+        let next_file_id = file_ids.last().map(|file_id| file_id + 1).unwrap_or_default();
+
+        // existing blob files are immutable, new blobs go into a new file
+        let io_type = match options.read_only {
+            true => IOType::MmapIO,
+            false => IOType::StdIO,
+        };
+        let mut inactive_files = HashMap::new();
+        for file_id in file_ids.into_iter() {
+            inactive_files.insert(file_id, DataFile::new_blob_file(options.path.clone(), file_id, io_type)?);
+        }
+
+        Ok(Self {
+            options,
+            active_file: RwLock::new(None),
+            inactive_files: RwLock::new(inactive_files),
+            next_file_id: AtomicU64::new(next_file_id),
+            dirty: AtomicBool::new(false),
+            refs: RwLock::new(HashMap::new()),
+            live_sizes: RwLock::new(HashMap::new()),
+            rotate_lock: RwLock::new(()),
+        })
+    }
+
+    // whether the value should be separated from data file
+    pub(crate) fn is_blob_value(&self, value_size: usize) -> bool {
+        self.options.blob_threshold > 0 && value_size >= self.options.blob_threshold
+    }
+
+    pub(crate) fn allocate_file_id(&self) -> u64 {
+        self.next_file_id.fetch_add(1, Ordering::SeqCst)
+    }
+
+    // write value into active blob file, return its blob position
+    pub(crate) fn write(&self, key: &[u8], value: &[u8]) -> Result<LogRecordPos> {
+        let encoded_record = encode_blob_record(&self.options, key, value)?;
+
+        let mut active_file = self.active_file.write();
+        let rotate = match active_file.as_ref() {
+            Some(data_file) => {
+                data_file.get_offset() + encoded_record.len() as u64 > self.options.blob_file_size
+            }
+            None => true,
+        };
+        if rotate {
+            let file_id = self.allocate_file_id();
+            let new_active_file = DataFile::new_blob_file(self.options.path.clone(), file_id, IOType::StdIO)?;
+            if let Some(old_file) = active_file.replace(new_active_file) {
+                self.seal_file(old_file)?;
+            }
+        }
+
+        let data_file = active_file.as_ref().unwrap();
+        let offset = data_file.get_offset();
+        let write_size = data_file.write(&encoded_record)?;
+        if self.options.sync_write {
+            data_file.sync()?;
+        } else {
+            self.dirty.store(true, Ordering::SeqCst);
+        }
+        Ok(LogRecordPos {
+            file_id: data_file.get_file_id(),
+            offset,
+            size: write_size as u64,
+        })
+    }
+
+    pub(crate) fn read(&self, pos: LogRecordPos) -> Result<Vec<u8>> {
+        {
+            let active_file = self.active_file.read();
+            if let Some(data_file) = active_file.as_ref() {
+                if data_file.get_file_id() == pos.file_id {
+                    return Ok(data_file.read_log_record(pos.offset)?.log_record.value);
+                }
+            }
+        }
+        let inactive_files = self.inactive_files.read();
+        match inactive_files.get(&pos.file_id) {
+            Some(data_file) => Ok(data_file.read_log_record(pos.offset)?.log_record.value),
+            None => Err(Errors::DataFileNotFound),
+        }
+    }
+
+    // record that key now points to blob position
+    pub(crate) fn set_ref(&self, key: Vec<u8>, pos: LogRecordPos) {
+        let mut refs = self.refs.write();
+        let mut live_sizes = self.live_sizes.write();
+        if let Some(old_pos) = refs.insert(key, pos) {
+            release_live_size(&mut live_sizes, old_pos);
+        }
+        *live_sizes.entry(pos.file_id).or_default() += pos.size;
+    }
+
+    // key was overwritten by an inline value or deleted
+    pub(crate) fn remove_ref(&self, key: &[u8]) {
+        let mut refs = self.refs.write();
+        if let Some(old_pos) = refs.remove(key) {
+            release_live_size(&mut self.live_sizes.write(), old_pos);
+        }
+    }
+
+    // seal active blob file so it can be collected
+    pub(crate) fn rotate(&self) -> Result<()> {
+        let mut active_file = self.active_file.write();
+        if let Some(old_file) = active_file.take() {
+            self.seal_file(old_file)?;
+        }
+        Ok(())
+    }
+
+    fn seal_file(&self, data_file: DataFile) -> Result<()> {
+        data_file.sync()?;
+        let file_id = data_file.get_file_id();
+        self.inactive_files
+            .write()
+            .insert(file_id, DataFile::new_blob_file(self.options.path.clone(), file_id, IOType::StdIO)?);
+        Ok(())
+    }
+
+    // cheap when nothing was written since the last sync,
+    // so it is called before every data file sync
+    pub(crate) fn sync(&self) -> Result<()> {
+        let active_file = self.active_file.read();
+        if !self.dirty.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let res = match active_file.as_ref() {
+            Some(data_file) => data_file.sync(),
+            None => Ok(()),
+        };
+        if res.is_err() {
+            self.dirty.store(true, Ordering::SeqCst);
+        }
+        res
+    }
+
+    #[cfg(test)]
+    pub(crate) fn is_dirty(&self) -> bool {
+        self.dirty.load(Ordering::SeqCst)
+    }
+
+    // live ratio of every blob file
+    pub(crate) fn stats(&self) -> HashMap<u64, BlobFileStat> {
+        let live_sizes = self.live_sizes.read();
+        let mut stats = HashMap::new();
+        let mut collect = |data_file: &DataFile| {
+            let file_id = data_file.get_file_id();
+            stats.insert(
+                file_id,
+                BlobFileStat {
+                    live_size: live_sizes.get(&file_id).copied().unwrap_or_default(),
+                    total_size: data_file.get_data_file_size(),
+                },
+            );
+        };
+        if let Some(data_file) = self.active_file.read().as_ref() {
+            collect(data_file);
+        }
+        for data_file in self.inactive_files.read().values() {
+            collect(data_file);
+        }
+        stats
+    }
+
+    // sealed blob files whose live ratio dropped below gc ratio
+    pub(crate) fn gc_candidates(&self) -> Vec<u64> {
+        let active_file_id = self.active_file.read().as_ref().map(|data_file| data_file.get_file_id());
+        let mut file_ids: Vec<u64> = self
+            .stats()
+            .into_iter()
+            .filter(|(file_id, stat)| {
+                Some(*file_id) != active_file_id && stat.live_ratio() < self.options.blob_gc_ratio
+            })
+            .map(|(file_id, _)| file_id)
+            .collect();
+        file_ids.sort();
+        file_ids
+    }
+
+    pub(crate) fn new_writer(&self, path: PathBuf) -> BlobWriter<'_> {
+        BlobWriter {
+            store: self,
+            path,
+            active_file: None,
+        }
+    }
+}
+
+/// BlobWriter rewrites live blobs into new blob files during compaction
+pub(crate) struct BlobWriter<'a> {
+    store: &'a BlobStore,
+    path: PathBuf,
+    active_file: Option<DataFile>,
+}
+
+impl BlobWriter<'_> {
+    pub(crate) fn write(&mut self, key: &[u8], value: &[u8]) -> Result<LogRecordPos> {
+        let encoded_record = encode_blob_record(&self.store.options, key, value)?;
+        let rotate = match self.active_file.as_ref() {
+            Some(data_file) => {
+                data_file.get_offset() + encoded_record.len() as u64 > self.store.options.blob_file_size
+            }
+            None => true,
+        };
+        if rotate {
+            if let Some(old_file) = self.active_file.take() {
+                old_file.sync()?;
+            }
+            // file id comes from the engine so it never collides after moving
+            let file_id = self.store.allocate_file_id();
+            self.active_file = Some(DataFile::new_blob_file(self.path.clone(), file_id, IOType::StdIO)?);
+        }
+
+        let data_file = self.active_file.as_ref().unwrap();
+        let offset = data_file.get_offset();
+        let write_size = data_file.write(&encoded_record)?;
+        Ok(LogRecordPos {
+            file_id: data_file.get_file_id(),
+            offset,
+            size: write_size as u64,
+        })
+    }
+
+    pub(crate) fn sync(&self) -> Result<()> {
+        match self.active_file.as_ref() {
+            Some(data_file) => data_file.sync(),
+            None => Ok(()),
+        }
+    }
+}
+
+fn encode_blob_record(options: &Options, key: &[u8], value: &[u8]) -> Result<Vec<u8>> {
+    let log_record = LogRecord {
+        key: key.to_vec(),
+        value: value.to_vec(),
+        record_type: RecordType::NORMAL,
+    };
+    log_record.encode_with_compression(options.compression, options.compression_threshold)
+}
+
+fn release_live_size(live_sizes: &mut HashMap<u64, u64>, pos: LogRecordPos) {
+    if let Some(live_size) = live_sizes.get_mut(&pos.file_id) {
+        *live_size = live_size.saturating_sub(pos.size);
+    }
+}
+
+// blob file ids in directory, sorted
+pub(crate) fn load_blob_file_ids(dir_path: &Path) -> Result<Vec<u64>> {
+    let dir = match fs::read_dir(dir_path) {
+        Ok(dir) => dir,
+        Err(e) => {
+            warn!("read database directory err: {}", e);
+            return Err(Errors::ReadDirError);
+        }
+    };
+
+    let mut file_ids = Vec::new();
+    for entry in dir.flatten() {
+        let file_name = entry.file_name();
+        let file_name = file_name.to_string_lossy();
+        if let Some(file_id) = file_name.strip_suffix(BLOB_FILE_NAME_SUFFIX) {
+            match file_id.parse::<u64>() {
+                Ok(file_id) => file_ids.push(file_id),
+                Err(_) => return Err(Errors::DataDirCorrupted),
+            }
+        }
+    }
+    file_ids.sort();
+    Ok(file_ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blob::{BlobFileStat, BlobStore};
+    use crate::options::Options;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_write_and_read() {
+        let options = create_options("write_read");
+        let blob_store = BlobStore::open(options.clone()).unwrap();
+
+        let pos1 = blob_store.write(b"key-1", &[1u8; 200]).unwrap();
+        let pos2 = blob_store.write(b"key-2", &[2u8; 200]).unwrap();
+        // small blob file size leads to rotation
+        assert_ne!(pos1.file_id, pos2.file_id);
+        assert_eq!(blob_store.read(pos1).unwrap(), vec![1u8; 200]);
+        assert_eq!(blob_store.read(pos2).unwrap(), vec![2u8; 200]);
+        drop(blob_store);
+
+        let blob_store = BlobStore::open(options.clone()).unwrap();
+        assert_eq!(blob_store.read(pos1).unwrap(), vec![1u8; 200]);
+        let pos3 = blob_store.write(b"key-3", &[3u8; 200]).unwrap();
+        assert!(pos3.file_id > pos2.file_id);
+        std::fs::remove_dir_all(&options.path).unwrap();
+    }
+
+    #[test]
+    fn test_live_ratio() {
+        let options = create_options("live_ratio");
+        let blob_store = BlobStore::open(options.clone()).unwrap();
+
+        let pos1 = blob_store.write(b"key-1", &[1u8; 100]).unwrap();
+        blob_store.set_ref(b"key-1".to_vec(), pos1);
+        let pos2 = blob_store.write(b"key-2", &[2u8; 100]).unwrap();
+        blob_store.set_ref(b"key-2".to_vec(), pos2);
+        assert_eq!(pos1.file_id, pos2.file_id);
+
+        let stat = blob_store.stats()[&pos1.file_id];
+        assert_eq!(stat, BlobFileStat { live_size: pos1.size + pos2.size, total_size: pos1.size + pos2.size });
+        // active blob file is never collected
+        assert!(blob_store.gc_candidates().is_empty());
+
+        blob_store.remove_ref(b"key-1");
+        blob_store.rotate().unwrap();
+        let stat = blob_store.stats()[&pos1.file_id];
+        assert_eq!(stat.live_size, pos2.size);
+        assert_eq!(blob_store.gc_candidates(), vec![pos1.file_id]);
+        std::fs::remove_dir_all(&options.path).unwrap();
+    }
+
+    fn create_options(name: &str) -> Arc<Options> {
+        let options = Options {
+            path: std::env::temp_dir().join(format!("lightkv-blob-{}", name)),
+            blob_threshold: 64,
+            blob_file_size: 300,
+            blob_gc_ratio: 0.6,
+            ..Default::default()
+        };
+        let _ = std::fs::remove_dir_all(&options.path);
+        std::fs::create_dir_all(&options.path).unwrap();
+        Arc::new(options)
+    }
+}
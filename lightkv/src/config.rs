@@ -0,0 +1,413 @@
+//! Loading `ServerConfig` and `ClientConfig` from toml files,
+//! environment variables override keys of the file
+
+use crate::options::{ClientConfig, NetworkType, ServerConfig};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+use thiserror::Error;
+use toml::{Table, Value};
+
+/// Prefix of environment variables overriding server config, such as
+/// `LIGHTKV_STORAGE_DATA_FILE_SIZE` for `data_file_size` of `[storage]`
+pub const SERVER_ENV_PREFIX: &str = "LIGHTKV_";
+/// Prefix of environment variables overriding client config, such as `LIGHTKV_CLIENT_POOL_SIZE`
+pub const CLIENT_ENV_PREFIX: &str = "LIGHTKV_CLIENT_";
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ConfigError {
+    #[error("failed to read config file {path}: {reason}")]
+    ReadFile { path: String, reason: String },
+
+    #[error("invalid toml in config file: {0}")]
+    Parse(String),
+
+    /// `key` is the dotted path of the offending key, such as `storage.data_file_size`
+    #[error("invalid config `{key}`: {reason}")]
+    InvalidKey { key: String, reason: String },
+}
+
+impl ServerConfig {
+    /// Load config from a toml file, or defaults if none, then apply
+    /// `LIGHTKV_*` environment variables and validate the result
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let source = read_source(path)?;
+        let env = std::env::vars().filter(|(name, _)| !name.starts_with(CLIENT_ENV_PREFIX));
+        load_config(&source, SERVER_ENV_PREFIX, env)
+    }
+
+    /// Check values the engine or server would reject
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        validate_addr(&self.general_config.addr)?;
+        match (&self.tls_cert, &self.tls_key) {
+            (Some(_), None) => {
+                return Err(invalid("tls_key", "must be set together with tls_cert"))
+            }
+            (None, Some(_)) => {
+                return Err(invalid("tls_cert", "must be set together with tls_key"))
+            }
+            _ => {}
+        }
+
+        let storage = &self.storage;
+        if storage.path.as_os_str().is_empty() {
+            return Err(invalid("storage.path", "must not be empty"));
+        }
+        if storage.data_file_size == 0 {
+            return Err(invalid("storage.data_file_size", "must be greater than 0"));
+        }
+        if storage.sync_write && (storage.sync_bytes_write > 0 || storage.sync_interval_ms > 0) {
+            return Err(invalid(
+                "storage.sync_write",
+                "conflicts with sync_bytes_write and sync_interval_ms",
+            ));
+        }
+        if !storage.compression.is_available() {
+            return Err(invalid(
+                "storage.compression",
+                "codec is not enabled by cargo features",
+            ));
+        }
+        if storage.blob_threshold > 0 && storage.blob_file_size == 0 {
+            return Err(invalid(
+                "storage.blob_file_size",
+                "must be greater than 0 when blob_threshold is set",
+            ));
+        }
+        if !(0.0..=1.0).contains(&storage.blob_gc_ratio) {
+            return Err(invalid("storage.blob_gc_ratio", "must be between 0 and 1"));
+        }
+        if !(0.0..=1.0).contains(&storage.compact_ratio) {
+            return Err(invalid("storage.compact_ratio", "must be between 0 and 1"));
+        }
+        if storage.compact_ratio > 0.0 && storage.compact_interval_ms == 0 {
+            return Err(invalid(
+                "storage.compact_interval_ms",
+                "must be greater than 0 when compact_ratio is set",
+            ));
+        }
+
+        if self.log_config.file.is_some()
+            && self.log_config.max_file_size > 0
+            && self.log_config.max_files == 0
+        {
+            return Err(invalid(
+                "log.max_files",
+                "must be greater than 0 when log file is rotated",
+            ));
+        }
+        if self.limits.max_request_size == 0 {
+            return Err(invalid("limits.max_request_size", "must be greater than 0"));
+        }
+        Ok(())
+    }
+}
+
+impl ClientConfig {
+    /// Load config from a toml file, or defaults if none, then apply
+    /// `LIGHTKV_CLIENT_*` environment variables and validate the result
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let source = read_source(path)?;
+        load_config(&source, CLIENT_ENV_PREFIX, std::env::vars())
+    }
+
+    /// Check values the client would reject
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        validate_addr(&self.general_config.addr)?;
+        if self.general_config.network == NetworkType::Quic && self.tls_ca.is_none() {
+            return Err(invalid("tls_ca", "is required by quic network"));
+        }
+        if self.pool_size == 0 {
+            return Err(invalid("pool_size", "must be greater than 0"));
+        }
+        if self.connect_timeout_ms == 0 {
+            return Err(invalid("connect_timeout_ms", "must be greater than 0"));
+        }
+        if self.request_timeout_ms == 0 {
+            return Err(invalid("request_timeout_ms", "must be greater than 0"));
+        }
+        Ok(())
+    }
+}
+
+// configs with defaults for missing keys and their own validation
+trait Config: Default + Serialize + DeserializeOwned {
+    fn validate(&self) -> Result<(), ConfigError>;
+}
+
+impl Config for ServerConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        ServerConfig::validate(self)
+    }
+}
+
+impl Config for ClientConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        ClientConfig::validate(self)
+    }
+}
+
+fn read_source(path: Option<&Path>) -> Result<String, ConfigError> {
+    match path {
+        Some(path) => std::fs::read_to_string(path).map_err(|e| ConfigError::ReadFile {
+            path: path.display().to_string(),
+            reason: e.to_string(),
+        }),
+        None => Ok(String::new()),
+    }
+}
+
+fn load_config<T: Config>(
+    source: &str,
+    env_prefix: &str,
+    env: impl Iterator<Item = (String, String)>,
+) -> Result<T, ConfigError> {
+    let mut table: Table = toml::from_str(source).map_err(|e| ConfigError::Parse(e.to_string()))?;
+    // defaults tell sections apart from keys and the type of overridden values
+    let defaults = Table::try_from(T::default()).expect("serialize default config");
+    for (name, value) in env {
+        if let Some(key) = name.strip_prefix(env_prefix) {
+            apply_env(
+                &mut table,
+                &defaults,
+                &key.to_ascii_lowercase(),
+                &name,
+                &value,
+            )?;
+        }
+    }
+
+    let config: T = serde_path_to_error::deserialize(Value::Table(table)).map_err(|e| {
+        ConfigError::InvalidKey {
+            key: e.path().to_string(),
+            reason: e.inner().to_string(),
+        }
+    })?;
+    config.validate()?;
+    Ok(config)
+}
+
+// `storage_data_file_size` sets `data_file_size` of `[storage]`, other keys are top level
+fn apply_env(
+    table: &mut Table,
+    defaults: &Table,
+    key: &str,
+    name: &str,
+    value: &str,
+) -> Result<(), ConfigError> {
+    let section = defaults.iter().find_map(|(section, default)| {
+        let field = key.strip_prefix(section.as_str())?.strip_prefix('_')?;
+        default
+            .as_table()
+            .map(|default| (section.clone(), field, default))
+    });
+    let (table, key, default, path) = match section {
+        Some((section, field, default)) => {
+            let path = format!("{}.{}", section, field);
+            let section_table = table
+                .entry(section)
+                .or_insert_with(|| Value::Table(Table::new()))
+                .as_table_mut()
+                .ok_or_else(|| invalid(&path, "section is not a table"))?;
+            (section_table, field, default.get(field), path)
+        }
+        None => (&mut *table, key, defaults.get(key), key.to_string()),
+    };
+
+    let parse_error =
+        |kind: &str| invalid(&path, &format!("invalid {} `{}` in {}", kind, value, name));
+    let value = match default {
+        Some(Value::Integer(_)) => {
+            Value::Integer(value.parse().map_err(|_| parse_error("integer"))?)
+        }
+        Some(Value::Float(_)) => Value::Float(value.parse().map_err(|_| parse_error("float"))?),
+        Some(Value::Boolean(_)) => {
+            Value::Boolean(value.parse().map_err(|_| parse_error("boolean"))?)
+        }
+        // strings, enums and optional paths
+        _ => Value::String(value.to_string()),
+    };
+    table.insert(key.to_string(), value);
+    Ok(())
+}
+
+fn validate_addr(addr: &str) -> Result<(), ConfigError> {
+    match addr.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => Ok(()),
+        _ => Err(invalid("network.addr", "must be host:port")),
+    }
+}
+
+fn invalid(key: &str, reason: &str) -> ConfigError {
+    ConfigError::InvalidKey {
+        key: key.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{load_config, ConfigError, CLIENT_ENV_PREFIX, SERVER_ENV_PREFIX};
+    use crate::options::{ClientConfig, IOType, IndexType, NetworkType, ServerConfig};
+    use crate::types::LogLevel;
+    use std::path::PathBuf;
+
+    fn env(vars: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn load_server(source: &str, vars: &[(&str, &str)]) -> Result<ServerConfig, ConfigError> {
+        load_config(source, SERVER_ENV_PREFIX, env(vars))
+    }
+
+    fn invalid_key(result: Result<ServerConfig, ConfigError>) -> String {
+        match result {
+            Err(ConfigError::InvalidKey { key, .. }) => key,
+            other => panic!("expect invalid key, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_load_server_config() {
+        assert_eq!(load_server("", &[]).unwrap(), ServerConfig::default());
+
+        let source = r#"
+            metrics_addr = "127.0.0.1:9100"
+
+            [network]
+            addr = "0.0.0.0:7000"
+            type = "quic"
+
+            [storage]
+            path = "/var/lib/lightkv"
+            data_file_size = 1048576
+            sync_interval_ms = 100
+            index_type = "skiplist"
+            io_type = "std"
+
+            [log]
+            level = "warn"
+            file = "/var/log/lightkv/server.log"
+
+            [limits]
+            max_connections = 100
+        "#;
+        let config = load_server(source, &[]).unwrap();
+        assert_eq!(config.general_config.addr, "0.0.0.0:7000");
+        assert_eq!(config.general_config.network, NetworkType::Quic);
+        assert_eq!(config.metrics_addr.as_deref(), Some("127.0.0.1:9100"));
+        assert_eq!(config.storage.path, PathBuf::from("/var/lib/lightkv"));
+        assert_eq!(config.storage.index_type, IndexType::SkipList);
+        assert_eq!(config.storage.io_type, IOType::StdIO);
+        assert_eq!(config.log_config.level, LogLevel::Warn);
+        assert_eq!(config.limits.max_connections, 100);
+
+        let options = config.storage.to_options();
+        assert_eq!(options.data_file_size, 1048576);
+        assert_eq!(options.sync_interval_ms, 100);
+        assert!(!options.mmap_at_startup);
+
+        // environment variables win over the file
+        let vars = [
+            ("LIGHTKV_NETWORK_ADDR", "127.0.0.1:7001"),
+            ("LIGHTKV_STORAGE_DATA_FILE_SIZE", "2048"),
+            ("LIGHTKV_STORAGE_INDEX_TYPE", "bplustree"),
+            ("LIGHTKV_STORAGE_BLOB_GC_RATIO", "0.3"),
+            ("LIGHTKV_TLS_CERT", "cert.pem"),
+            ("LIGHTKV_TLS_KEY", "key.pem"),
+            ("OTHER_VAR", "ignored"),
+        ];
+        let config = load_server(source, &vars).unwrap();
+        assert_eq!(config.general_config.addr, "127.0.0.1:7001");
+        assert_eq!(config.storage.data_file_size, 2048);
+        assert_eq!(config.storage.index_type, IndexType::BPlusTree);
+        assert_eq!(config.storage.blob_gc_ratio, 0.3);
+        assert_eq!(config.tls_cert, Some(PathBuf::from("cert.pem")));
+    }
+
+    #[test]
+    fn test_invalid_server_config() {
+        assert!(matches!(
+            load_server("[storage", &[]),
+            Err(ConfigError::Parse(_))
+        ));
+
+        // errors name the offending key
+        assert_eq!(
+            invalid_key(load_server("[storage]\npath = 1", &[])),
+            "storage.path"
+        );
+        assert_eq!(
+            invalid_key(load_server("[storage]\nindex_type = \"hash\"", &[])),
+            "storage.index_type"
+        );
+        assert_eq!(
+            invalid_key(load_server("[storage]\ndata_file_size = 0", &[])),
+            "storage.data_file_size"
+        );
+        assert_eq!(
+            invalid_key(load_server("[network]\naddr = \"localhost\"", &[])),
+            "network.addr"
+        );
+        assert_eq!(
+            invalid_key(load_server("tls_cert = \"cert.pem\"", &[])),
+            "tls_key"
+        );
+        assert_eq!(
+            invalid_key(load_server(
+                "[storage]\nsync_write = true\nsync_interval_ms = 10",
+                &[]
+            )),
+            "storage.sync_write"
+        );
+        assert_eq!(
+            invalid_key(load_server("", &[("LIGHTKV_STORAGE_COMPACT_RATIO", "2")])),
+            "storage.compact_ratio"
+        );
+        assert_eq!(
+            invalid_key(load_server(
+                "",
+                &[("LIGHTKV_STORAGE_DATA_FILE_SIZE", "big")]
+            )),
+            "storage.data_file_size"
+        );
+
+        // unknown keys are rejected instead of silently ignored
+        assert_eq!(
+            invalid_key(load_server("[storage]\ndata_file_sise = 1", &[])),
+            "storage.data_file_sise"
+        );
+        assert_eq!(
+            invalid_key(load_server("", &[("LIGHTKV_LOG_LEVLE", "warn")])),
+            "log.levle"
+        );
+    }
+
+    #[test]
+    fn test_load_client_config() {
+        let source = r#"
+            pool_size = 4
+            tls_ca = "ca.pem"
+
+            [network]
+            addr = "10.0.0.1:6380"
+            type = "quic"
+        "#;
+        let config: ClientConfig = load_config(
+            source,
+            CLIENT_ENV_PREFIX,
+            env(&[("LIGHTKV_CLIENT_REQUEST_TIMEOUT_MS", "500")]),
+        )
+        .unwrap();
+        assert_eq!(config.pool_size, 4);
+        assert_eq!(config.request_timeout_ms, 500);
+        assert_eq!(config.general_config.network, NetworkType::Quic);
+
+        let result: Result<ClientConfig, ConfigError> =
+            load_config("[network]\ntype = \"quic\"", CLIENT_ENV_PREFIX, env(&[]));
+        assert!(matches!(result, Err(ConfigError::InvalidKey { key, .. }) if key == "tls_ca"));
+    }
+}
@@ -0,0 +1,101 @@
+use crate::errors::Errors;
+use crate::options::CompressionType;
+use crate::Result;
+
+// codec id stored in the high bits of the record header type byte,
+// `0` is kept for uncompressed values so older records stay readable
+impl CompressionType {
+    pub(crate) fn to_flag(self) -> u8 {
+        match self {
+            CompressionType::None => 0,
+            CompressionType::Lz4 => 1,
+            CompressionType::Zstd => 2,
+        }
+    }
+
+    pub(crate) fn from_flag(flag: u8) -> Result<Self> {
+        match flag {
+            0 => Ok(CompressionType::None),
+            1 => Ok(CompressionType::Lz4),
+            2 => Ok(CompressionType::Zstd),
+            _ => Err(Errors::UnsupportedCompression),
+        }
+    }
+
+    // whether the codec was compiled into this build
+    pub(crate) fn is_available(self) -> bool {
+        match self {
+            CompressionType::None => true,
+            CompressionType::Lz4 => cfg!(feature = "lz4"),
+            CompressionType::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+}
+
+pub(crate) fn compress(compression: CompressionType, data: &[u8]) -> Result<Vec<u8>> {
+    match compression {
+        CompressionType::None => Ok(data.to_vec()),
+        #[cfg(feature = "lz4")]
+        CompressionType::Lz4 => Ok(lz4_flex::compress_prepend_size(data)),
+        #[cfg(feature = "zstd")]
+        CompressionType::Zstd => zstd::bulk::compress(data, 0).map_err(|e| {
+            log::error!("zstd compress err: {}", e);
+            Errors::CompressError
+        }),
+        #[allow(unreachable_patterns)]
+        _ => Err(Errors::UnsupportedCompression),
+    }
+}
+
+pub(crate) fn decompress(compression: CompressionType, data: &[u8]) -> Result<Vec<u8>> {
+    match compression {
+        CompressionType::None => Ok(data.to_vec()),
+        #[cfg(feature = "lz4")]
+        CompressionType::Lz4 => lz4_flex::decompress_size_prepended(data).map_err(|e| {
+            log::error!("lz4 decompress err: {}", e);
+            Errors::DecompressError
+        }),
+        #[cfg(feature = "zstd")]
+        CompressionType::Zstd => zstd::stream::decode_all(data).map_err(|e| {
+            log::error!("zstd decompress err: {}", e);
+            Errors::DecompressError
+        }),
+        #[allow(unreachable_patterns)]
+        _ => Err(Errors::UnsupportedCompression),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::compression::{compress, decompress};
+    use crate::options::CompressionType;
+
+    #[test]
+    fn test_compress_roundtrip() {
+        let data = "lightkv-compression-".repeat(64).into_bytes();
+
+        let mut codecs = vec![CompressionType::None];
+        if CompressionType::Lz4.is_available() {
+            codecs.push(CompressionType::Lz4);
+        }
+        if CompressionType::Zstd.is_available() {
+            codecs.push(CompressionType::Zstd);
+        }
+
+        for codec in codecs.into_iter() {
+            let compressed = compress(codec, &data).unwrap();
+            if codec != CompressionType::None {
+                assert!(compressed.len() < data.len());
+            }
+            assert_eq!(decompress(codec, &compressed).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_compression_flag() {
+        for codec in [CompressionType::None, CompressionType::Lz4, CompressionType::Zstd] {
+            assert_eq!(CompressionType::from_flag(codec.to_flag()).unwrap(), codec);
+        }
+        assert!(CompressionType::from_flag(7).is_err());
+    }
+}
@@ -3,17 +3,20 @@ use crate::Result;
 use parking_lot::RwLock;
 use std::path::PathBuf;
 use std::sync::Arc;
-use bytes::{Buf, BufMut, BytesMut};
-use crate::data::log_record::{ReadLogRecord, RecordType};
+use bytes::{Buf, BytesMut};
+use crate::data::compression::decompress;
+use crate::data::log_record::{ReadLogRecord, RecordType, COMPRESSION_FLAG_SHIFT};
 use crate::errors::Errors;
-use crate::options::IOType;
+use crate::options::{CompressionType, IOType};
 
 use super::log_record::{LogRecord, LogRecordPos};
 
-const DATA_FILE_NAME_SUFFIX: &str = ".data";
+pub(crate) const DATA_FILE_NAME_SUFFIX: &str = ".data";
+pub(crate) const BLOB_FILE_NAME_SUFFIX: &str = ".blob";
 pub const HINT_FILE_NAME_SUFFIX:&str="_hint_file";
-const MERGE_FINISHED_FILE_NAME_SUFFIX:&str="_merged_finished_file";
-const TXN_SEQ_FILE_NAME_SUFFIX:&str="_txn_seq_file";
+pub(crate) const MERGE_FINISHED_FILE_NAME_SUFFIX:&str="_merged_finished_file";
+pub(crate) const TXN_SEQ_FILE_NAME_SUFFIX:&str="_txn_seq_file";
+pub(crate) const MANIFEST_FILE_NAME_SUFFIX:&str="_manifest_file";
 
 /// DataFile use to manage a file which store log record
 pub struct DataFile {
@@ -30,7 +33,19 @@ pub struct DataFile {
 impl DataFile {
     // create a new data file
     pub fn new(path: PathBuf, file_id: u64,io_type:IOType) -> Result<Self> {
-        let file_name = new_file_name(path, file_id);
+        let file_name = get_data_file_name(path, file_id);
+        let io_manager = new_io_manager(file_name,io_type)?;
+
+        Ok(Self {
+            file_id: Arc::new(RwLock::new(file_id)),
+            offset: Arc::new(RwLock::new(0)),
+            io_manager,
+        })
+    }
+
+    // create a blob file which store large values
+    pub fn new_blob_file(path: PathBuf, file_id: u64,io_type:IOType) -> Result<Self> {
+        let file_name = get_blob_file_name(path, file_id);
         let io_manager = new_io_manager(file_name,io_type)?;
 
         Ok(Self {
@@ -41,9 +56,9 @@ impl DataFile {
     }
 
     // create a hint data file
-    pub fn new_hint_file(path:PathBuf) -> Result<Self> {
+    pub fn new_hint_file(path:PathBuf,io_type:IOType) -> Result<Self> {
         let file_name=path.join(HINT_FILE_NAME_SUFFIX);
-        let io_manager=new_io_manager(file_name,IOType::StdIO)?;
+        let io_manager=new_io_manager(file_name,io_type)?;
 
         Ok(Self {
             file_id: Arc::new(RwLock::new(0)),
@@ -53,9 +68,9 @@ impl DataFile {
     }
 
     // create a merge finished file
-    pub fn new_merge_fin_file(path:PathBuf)->Result<Self>{
+    pub fn new_merge_fin_file(path:PathBuf,io_type:IOType)->Result<Self>{
         let file_name=path.join(MERGE_FINISHED_FILE_NAME_SUFFIX);
-        let io_manager=new_io_manager(file_name,IOType::StdIO)?;
+        let io_manager=new_io_manager(file_name,io_type)?;
 
         Ok(Self{
             file_id:Arc::new(RwLock::new(0)),
@@ -65,9 +80,21 @@ impl DataFile {
     }
 
     // create a txn seq file
-    pub fn new_txn_seq_file(path:PathBuf)->Result<Self>{
+    pub fn new_txn_seq_file(path:PathBuf,io_type:IOType)->Result<Self>{
         let file_name=path.join(TXN_SEQ_FILE_NAME_SUFFIX);
-        let io_manager=new_io_manager(file_name,IOType::StdIO)?;
+        let io_manager=new_io_manager(file_name,io_type)?;
+
+        Ok(Self{
+            file_id:Arc::new(RwLock::new(0)),
+            offset:Arc::new(RwLock::new(0)),
+            io_manager,
+        })
+    }
+
+    // create a checkpoint manifest file
+    pub fn new_manifest_file(path:PathBuf,io_type:IOType)->Result<Self>{
+        let file_name=path.join(MANIFEST_FILE_NAME_SUFFIX);
+        let io_manager=new_io_manager(file_name,io_type)?;
 
         Ok(Self{
             file_id:Arc::new(RwLock::new(0)),
@@ -103,48 +130,67 @@ impl DataFile {
 impl DataFile {
     // read a log record from a data file
     pub fn read_log_record(&self,offset:u64)->Result<ReadLogRecord> {
-        // first create a max header size buffer
-        let mut buf =BytesMut::zeroed(LogRecord::max_header_size());
-        self.io_manager.read(&mut buf,offset)?;
+        let file_size=self.get_data_file_size();
+        if offset>=file_size {
+            return Err(Errors::ReadFileEOF);
+        }
 
-        // secondly get log record type
-        let record_type:RecordType=buf.get_u8().into();
+        // first create a header buffer, header may be shorter than max size at the tail of file
+        let header_len=std::cmp::min(LogRecord::max_header_size() as u64,file_size-offset) as usize;
+        let mut header_buf=BytesMut::zeroed(header_len);
+        self.io_manager.read(&mut header_buf,offset)?;
+        let mut buf=&header_buf[..];
 
-        // thirdly get key size and size, and get actual log record header size
-        let key_size=match prost::decode_length_delimiter(&mut buf) {
-            Ok(size)=>size,
-            Err(e)=>panic!("data file read log record key size err: {}",e),
-        };
-        let value_size=match prost::decode_length_delimiter(&mut buf) {
-            Ok(size)=>size,
-            Err(e)=>panic!("data file read log record value size err: {}",e),
+        // secondly get log record type byte, which also carries value compression codec
+        let type_byte=buf.get_u8();
+
+        // thirdly get key size and size, and get actual log record header size,
+        // a header cut by the end of file is a record that was not completely written
+        let truncated=match header_len<LogRecord::max_header_size() {
+            true=>Errors::ReadFileEOF,
+            false=>Errors::CrcCheckError,
         };
+        let key_size=prost::decode_length_delimiter(&mut buf).map_err(|_|truncated.clone())?;
+        let value_size=prost::decode_length_delimiter(&mut buf).map_err(|_|truncated)?;
         if key_size==0&&value_size==0 {
             return Err(Errors::ReadFileEOF);
         }
         let header_size=1+prost::length_delimiter_len(key_size)+prost::length_delimiter_len(value_size);
 
-        // fourthly set buffer for log record body and push data into it
-        let mut body_buf=BytesMut::zeroed(key_size+value_size+4);
-        match self.io_manager.read(&mut body_buf,offset+header_size as u64){
-            Ok(size)=>if size!=body_buf.len() { panic!("data file read log record body data err"); },
-            Err(e)=>panic!("data file read log record body data err: {}",e),
-        };
+        // fourthly set buffer for log record body and push data into it,
+        // a record beyond the end of file was not completely written
+        let body_size=key_size as u64+value_size as u64+4;
+        if offset+header_size as u64+body_size>file_size {
+            return Err(Errors::ReadFileEOF);
+        }
+        let mut body_buf=BytesMut::zeroed(body_size as usize);
+        let read_size=self.io_manager.read(&mut body_buf,offset+header_size as u64)?;
+        if read_size!=body_buf.len() {
+            return Err(Errors::ReadFileEOF);
+        }
 
-        // finally set log record
+        // check crc value over the stored header and body
+        let mut hasher=crc32fast::Hasher::new();
+        hasher.update(&header_buf[..header_size]);
+        hasher.update(&body_buf[..key_size+value_size]);
+        let crc=hasher.finalize();
+        let mut crc_buf=&body_buf[key_size+value_size..];
+        if crc_buf.get_u32()!=crc {
+            return Err(Errors::CrcCheckError);
+        }
+
+        // type byte is only trusted once crc matches
+        let record_type=RecordType::try_from(type_byte)?;
+        let compression=CompressionType::from_flag(type_byte>>COMPRESSION_FLAG_SHIFT)?;
+
+        // finally set log record, decompress value if needed
+        let value=&body_buf[key_size..key_size+value_size];
         let log_record=LogRecord{
             key:body_buf[0..key_size].to_vec(),
-            value:body_buf[key_size..key_size+value_size].to_vec(),
+            value:decompress(compression,value)?,
             record_type,
         };
 
-        // check crc value
-        body_buf.advance(key_size+value_size);
-        let crc_value=body_buf.get_u32();
-        if crc_value!=log_record.get_crc() {
-            return Err(Errors::CrcCheckError);
-        }
-
         Ok(ReadLogRecord{
             size: header_size+body_buf.len(),
             log_record,
@@ -168,20 +214,38 @@ impl DataFile {
         Ok(())
     }
 
+    // hint of a blob reference also keeps blob position
+    pub fn write_blob_hint_log(&self,key:Vec<u8>,pos:LogRecordPos,blob_pos:LogRecordPos)->Result<()>{
+        let mut value=pos.encode();
+        value.extend_from_slice(&blob_pos.encode());
+        let hint_log_record=LogRecord{
+            key,
+            value,
+            record_type:RecordType::BLOB,
+        };
+        self.io_manager.write(&hint_log_record.encode())?;
+        Ok(())
+    }
+
     pub fn sync(&self)->Result<()> {
         self.io_manager.sync()
     }
 
 }
 
-fn new_file_name(path:PathBuf,file_id:u64)->PathBuf {
+pub(crate) fn get_data_file_name(path:PathBuf,file_id:u64)->PathBuf {
     path.join(format!("{:09}", file_id) + DATA_FILE_NAME_SUFFIX)
 }
 
+pub(crate) fn get_blob_file_name(path:PathBuf,file_id:u64)->PathBuf {
+    path.join(format!("{:09}", file_id) + BLOB_FILE_NAME_SUFFIX)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::data::data_file::{DataFile, new_file_name};
+    use crate::data::data_file::{DataFile, get_data_file_name};
     use crate::data::log_record::{LogRecord, RecordType};
+    use crate::errors::Errors;
     use crate::options::IOType;
 
     #[test]
@@ -217,6 +281,43 @@ mod tests {
 
         let read_log_record=data_file.read_log_record(0);
         println!("{:?}",read_log_record.unwrap());
-        std::fs::remove_file(new_file_name(temp_path,0)).unwrap()
+        std::fs::remove_file(get_data_file_name(temp_path,0)).unwrap()
+    }
+
+    #[test]
+    fn test_read_corrupted_log_record(){
+        let dir=std::env::temp_dir().join("lightkv-corrupted-record");
+        let _=std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let data_file=DataFile::new(dir.clone(),0,IOType::StdIO).unwrap();
+        let log_record=LogRecord{
+            key: "key".into(),
+            value: "value".into(),
+            record_type: RecordType::NORMAL,
+        };
+        let encoded_data=log_record.encode();
+        let size=encoded_data.len() as u64;
+
+        // a damaged type byte fails crc check instead of panicking
+        let mut damaged=encoded_data.clone();
+        damaged[0]=0x0f;
+        data_file.write(&damaged).unwrap();
+        assert_eq!(data_file.read_log_record(0).err(),Some(Errors::CrcCheckError));
+
+        // an unknown type with a matching crc is corruption
+        let crc=crc32fast::hash(&damaged[..damaged.len()-4]);
+        damaged.truncate(damaged.len()-4);
+        damaged.extend_from_slice(&crc.to_be_bytes());
+        data_file.write(&damaged).unwrap();
+        assert_eq!(data_file.read_log_record(size).err(),Some(Errors::DataDirCorrupted));
+
+        // a torn tail is the end of the log
+        data_file.write(&encoded_data).unwrap();
+        data_file.write(&[1,0xff]).unwrap();
+        assert!(data_file.read_log_record(2*size).is_ok());
+        assert_eq!(data_file.read_log_record(3*size).err(),Some(Errors::ReadFileEOF));
+        data_file.write(&[0x0f,3,100,b'k']).unwrap();
+        assert_eq!(data_file.read_log_record(3*size+2).err(),Some(Errors::ReadFileEOF));
+        std::fs::remove_dir_all(dir).unwrap();
     }
 }
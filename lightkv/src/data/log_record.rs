@@ -1,4 +1,13 @@
 use bytes::{BufMut, BytesMut};
+use crate::data::compression::compress;
+use crate::errors::Errors;
+use crate::options::CompressionType;
+use crate::Result;
+
+// low bits of the header type byte keep the record type,
+// high bits keep the value compression codec
+pub(crate) const RECORD_TYPE_MASK: u8 = 0x0f;
+pub(crate) const COMPRESSION_FLAG_SHIFT: u8 = 4;
 
 /// LogRecord use to record key value data into disk
 #[derive(Debug)]
@@ -19,20 +28,29 @@ pub struct TxnRecord{
     pub(crate) position:LogRecordPos,
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(PartialEq,Copy, Clone,Debug)]
 pub enum RecordType {
     NORMAL = 1,
     DELETED = 2,
-    TXNFIN=3
+    TXNFIN=3,
+    // value is the position of a record in blob file
+    BLOB=4,
+    // value is an operand folded into the value of key on read
+    MERGE=5,
 }
 
-impl From<u8> for RecordType {
-    fn from(value: u8) -> Self {
-        match value {
-            1=>RecordType::NORMAL,
-            2=>RecordType::DELETED,
-            3=>RecordType::TXNFIN,
-            _=>panic!("wrong record type!"),
+impl TryFrom<u8> for RecordType {
+    type Error=Errors;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value & RECORD_TYPE_MASK {
+            1=>Ok(RecordType::NORMAL),
+            2=>Ok(RecordType::DELETED),
+            3=>Ok(RecordType::TXNFIN),
+            4=>Ok(RecordType::BLOB),
+            5=>Ok(RecordType::MERGE),
+            _=>Err(Errors::DataDirCorrupted),
         }
     }
 }
@@ -49,6 +67,15 @@ pub struct LogRecordPos {
 }
 
 impl LogRecordPos {
+    // whether record is written after the other one
+    pub(crate) fn is_after(&self,other:&LogRecordPos)->bool{
+        (self.file_id,self.offset)>(other.file_id,other.offset)
+    }
+
+    pub(crate) fn is_same_record(&self,other:&LogRecordPos)->bool{
+        self.file_id==other.file_id&&self.offset==other.offset
+    }
+
     pub fn encode(&self)->Vec<u8>{
         let mut buf=BytesMut::new();
         prost::encoding::encode_varint(self.file_id,&mut buf);
@@ -86,39 +113,53 @@ impl LogRecord {
         encoded_data
     }
 
-    pub fn get_crc(&self)->u32{
-        let (_,crc)=self.internal_encode();
-        crc
+    // encode log record, compressing value when it reaches the threshold
+    pub fn encode_with_compression(&self,compression:CompressionType,threshold:usize)->Result<Vec<u8>>{
+        if compression==CompressionType::None||self.value.len()<threshold {
+            return Ok(self.encode());
+        }
+        let compressed=compress(compression,&self.value)?;
+        // keep the raw value if codec does not help
+        if compressed.len()>=self.value.len() {
+            return Ok(self.encode());
+        }
+        let (encoded_data,_)=self.encode_with_flag(compression.to_flag(),&compressed);
+        Ok(encoded_data)
     }
 
     //
     //	+-------------+--------------+-------------+--------------+-------------+-------------+
     //	| record type |    key size  |  value size |     key      |    value    |  crc value  |
     //	+-------------+--------------+-------------+--------------+-------------+-------------+
-    // log record encode layout
+    // log record encode layout,
+    // high 4 bits of record type byte hold compression codec of value
     pub fn internal_encode(&self)->(Vec<u8>,u32) {
+        self.encode_with_flag(CompressionType::None.to_flag(),&self.value)
+    }
+
+    fn encode_with_flag(&self,compression_flag:u8,value:&[u8])->(Vec<u8>,u32) {
         let mut buf=BytesMut::new();
         buf.reserve(
             std::mem::size_of::<u8>()
                 +prost::length_delimiter_len(self.key.len())
-                +prost::length_delimiter_len(self.value.len())
+                +prost::length_delimiter_len(value.len())
                 +self.key.len()
-                +self.value.len()
+                +value.len()
         );
 
-        buf.put_u8(self.record_type as u8);
+        buf.put_u8(self.record_type as u8|(compression_flag<<COMPRESSION_FLAG_SHIFT));
 
         let encoded_res=prost::encode_length_delimiter(self.key.len(),&mut buf);
         if let Err(e) = encoded_res {
             panic!("{}",e);
         }
-        let encoded_res=prost::encode_length_delimiter(self.value.len(),&mut buf);
+        let encoded_res=prost::encode_length_delimiter(value.len(),&mut buf);
         if let Err(e) = encoded_res {
             panic!("{}",e);
         }
 
         buf.extend_from_slice(&self.key);
-        buf.extend_from_slice(&self.value);
+        buf.extend_from_slice(value);
 
         // get crc32 value from data (header and key&value)
         let mut hasher=crc32fast::Hasher::new();
@@ -137,9 +178,9 @@ impl LogRecord {
 #[cfg(test)]
 mod tests{
     use bytes::BytesMut;
-    use log::log;
 
-    use crate::data::log_record::{LogRecord, LogRecordPos, RecordType};
+    use crate::data::log_record::{LogRecord, LogRecordPos, RecordType, COMPRESSION_FLAG_SHIFT, RECORD_TYPE_MASK};
+    use crate::options::CompressionType;
 
     #[test]
     fn test_encode_log_record(){
@@ -215,6 +256,26 @@ mod tests{
         }
     }
 
+    #[test]
+    fn test_encode_log_record_with_compression(){
+        let log_record=LogRecord{
+            key: "key".as_bytes().to_vec(),
+            value: "value".repeat(100).into_bytes(),
+            record_type: RecordType::NORMAL,
+        };
+
+        // value shorter than threshold is kept verbatim
+        let encoded_data=log_record.encode_with_compression(CompressionType::Lz4,1024).unwrap();
+        assert_eq!(encoded_data,log_record.encode());
+
+        if CompressionType::Lz4.is_available() {
+            let encoded_data=log_record.encode_with_compression(CompressionType::Lz4,64).unwrap();
+            assert!(encoded_data.len()<log_record.encode().len());
+            assert_eq!(encoded_data[0]&RECORD_TYPE_MASK,RecordType::NORMAL as u8);
+            assert_eq!(encoded_data[0]>>COMPRESSION_FLAG_SHIFT,CompressionType::Lz4.to_flag());
+        }
+    }
+
     #[test]
     fn test_encode_log_record_pos(){
         let log_record_pos=LogRecordPos{
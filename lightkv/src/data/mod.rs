@@ -1,2 +1,3 @@
+pub mod compression;
 pub mod data_file;
 pub mod log_record;
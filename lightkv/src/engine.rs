@@ -1,15 +1,39 @@
-use crate::data::data_file::{DataFile,HINT_FILE_NAME_SUFFIX, self};
-use crate::data::log_record::{LogRecord, LogRecordPos, RecordType};
+use crate::batch::{GroupCommit, IndexUpdate, WriteRequest};
+use crate::blob::{BlobFileStat, BlobStore, BlobWriter};
+use crate::log::{init_logger, LogConfig};
+use crate::macros::event;
+use crate::manifest::{remove_manifest, Manifest};
+use crate::merge::{self, MergeChain, MergeOperator, APPEND_OPERATOR, INCR_OPERATOR};
+use crate::metrics::{self, LatencyTimer, Metrics};
+use crate::periodic::PeriodicTask;
+use crate::util::file::{available_disk_size, copy_file_prefix, dir_disk_size, is_same_file, link_or_copy};
+use crate::util::rate_limiter::RateLimiter;
+use crate::data::data_file::{get_blob_file_name, get_data_file_name, DataFile, BLOB_FILE_NAME_SUFFIX, DATA_FILE_NAME_SUFFIX, HINT_FILE_NAME_SUFFIX, MERGE_FINISHED_FILE_NAME_SUFFIX, TXN_SEQ_FILE_NAME_SUFFIX};
+use crate::fio::new_io_manager;
+use crate::data::log_record::{LogRecord, LogRecordPos, RecordType, TxnRecord};
 use crate::errors::Errors;
-use crate::index::{Index, IndexIterator};
+use crate::index::{self, Index, IndexIterator};
 use crate::options::{IteratorOptions, Options,WriteBatchOptions, IOType};
-use crate::{Result, data};
-use bytes::Bytes;
+use crate::Result;
+use bytes::{BufMut, Bytes, BytesMut};
+use fs2::FileExt;
+use log::warn;
 use parking_lot::{RwLock, Mutex};
 use std::collections::HashMap;
-use std::fs::File;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+const INITIAL_FILE_ID:u64=0;
+const LOCK_FILE_NAME:&str="lightkv_lock";
+const TXN_FIN_KEY:&[u8]="txn-fin".as_bytes();
+const TXN_SEQ_KEY:&[u8]="txn-seq".as_bytes();
+const MERGE_FIN_KEY:&[u8]="merge-fin".as_bytes();
+const BLOB_GC_KEY:&[u8]="blob-gc".as_bytes();
+const MERGE_DIR_NAME_SUFFIX:&str="-merge";
+pub(crate) const NON_TXN_ID:usize=0;
 
 /// Storage engine instance
 pub struct Engine {
@@ -20,23 +44,65 @@ pub struct Engine {
     // memory index
     pub(crate) index: Box<dyn Index>,
 
+    // large values separated from data files
+    blob_store: Arc<BlobStore>,
+
+    // data file ids, only used when loading index
+    file_ids:Vec<u64>,
+
     pub(crate) txn_id:Arc<AtomicUsize>,
 
+    // coalesce concurrent writes into one write and sync
+    group_commit:GroupCommit,
+
     compact_lock:Mutex<()>,
 
-    file_lock:File,
+    // none if a read only directory has no lock file
+    file_lock:Option<File>,
+    // set by the first close, later ones must not touch a directory which is unlocked
+    closed:AtomicBool,
+    // bytes written since last sync
     written_bytes:Arc<AtomicUsize>,
+    // bytes of overwritten and deleted records of each data file
+    reclaim_sizes:Mutex<HashMap<u64,u64>>,
+    // data files before this id are merged and wait for reopen to be replaced
+    merged_file_id:AtomicU64,
+
+    // background sync by time interval
+    syncer:Option<PeriodicTask>,
+    // background compaction triggered by dead bytes ratio
+    compact_scheduler:Mutex<Option<PeriodicTask>>,
+
+    // writes are rejected until disk space is freed
+    disk_full:AtomicBool,
+
+    // operators of merge records by name
+    merge_operators:HashMap<String,Arc<dyn MergeOperator>>,
+    // records making up values of keys with merge operands,
+    // memory index points to the last operand of them
+    merge_chains:RwLock<HashMap<Vec<u8>,MergeChain>>,
 }
 
 /// Status of engine instance
 #[derive(Debug)]
 pub struct EngineStatus{
     // engine key counts
-    key_counts:usize,
+    pub key_counts:usize,
+
+    // engine data files
+    pub file_counts:usize,
+
+    // size of files in database directory
+    pub disk_size:u64,
 
-    // engine data files 
-    file_counts:usize,
+    // estimated bytes compaction can reclaim from data files
+    pub reclaimable_size:u64,
 
+    // id of current active data file
+    pub active_file_id:u64,
+
+    // writes are rejected because of no enough disk space, reads still work
+    pub disk_full:bool,
 }
 
 impl Engine {
@@ -44,20 +110,190 @@ impl Engine {
         // open a engine can be divided into several steps
         // 1.Check engine options
         // 2.Load data files
-        // 3.Load index from hint file
+        // 3.Load index from hint file and data files
 
         if let Some(e) = Engine::check_options(&options) {
             return Err(e);
         }
 
+        // logger may be installed by application or another engine already
+        if let Some(level)=options.log_level {
+            let _=init_logger(&LogConfig{level,..Default::default()});
+        }
+
+        let dir_path=options.path.clone();
+        if !dir_path.is_dir() {
+            if options.read_only {
+                return Err(Errors::ReadDirError);
+            }
+            if let Err(e)=fs::create_dir_all(&dir_path) {
+                warn!("create database directory err: {}",e);
+                return Err(Errors::CreateDirError);
+            }
+        }
+
+        // make sure only one process writes the database directory,
+        // read only engines share the lock with each other
+        let file_lock=open_lock_file(&dir_path,options.read_only)?;
+
+        // move finished merge result into database directory,
+        // read only engine keeps using the files before merge
+        if !options.read_only {
+            load_merge_files(&dir_path)?;
+        }
+
+        // directory restored from a checkpoint must still hold its files
+        if let Some(manifest)=Manifest::load(&dir_path)? {
+            manifest.verify(&dir_path,false)?;
+        }
+
+        // read only engine never writes, all files stay mapped
+        let mut data_files=load_data_files(&dir_path,options.mmap_at_startup||options.read_only)?;
+        let file_ids:Vec<u64>=data_files.iter().map(|file|file.get_file_id()).collect();
+
+        // the newest data file is the active file, others are immutable
+        let active_file=match data_files.pop() {
+            Some(file)=>file,
+            None if options.read_only=>return Err(Errors::DataFileNotFound),
+            None=>DataFile::new(dir_path.clone(),INITIAL_FILE_ID,IOType::StdIO)?,
+        };
+        let mut inactive_files=HashMap::new();
+        for data_file in data_files.into_iter() {
+            inactive_files.insert(data_file.get_file_id(),data_file);
+        }
+
+        let index=index::new_index(options.index_type.clone());
+        let merge_operators=merge::operator_registry(&options.merge_operators);
+        let options=Arc::new(options);
+        let blob_store=Arc::new(BlobStore::open(options.clone())?);
+        let mut engine=Self{
+            options,
+            active_file: Arc::new(RwLock::new(active_file)),
+            inactive_files: Arc::new(RwLock::new(inactive_files)),
+            index,
+            blob_store,
+            file_ids,
+            txn_id: Arc::new(AtomicUsize::new(NON_TXN_ID)),
+            group_commit: GroupCommit::new(),
+            compact_lock: Mutex::new(()),
+            file_lock,
+            closed: AtomicBool::new(false),
+            written_bytes: Arc::new(AtomicUsize::new(0)),
+            reclaim_sizes: Mutex::new(HashMap::new()),
+            merged_file_id: AtomicU64::new(0),
+            syncer: None,
+            compact_scheduler: Mutex::new(None),
+            disk_full: AtomicBool::new(false),
+            merge_operators,
+            merge_chains: RwLock::new(HashMap::new()),
+        };
+
+        engine.load_index_from_hint_file()?;
+        let current_txn_id=engine.load_index_from_data_files()?;
+        let saved_txn_id=load_txn_id(&engine.options.path)?;
+        engine.txn_id.store(current_txn_id.max(saved_txn_id),Ordering::SeqCst);
+        event!(Info,"open",
+            dir=engine.options.path.display(),
+            files=engine.file_ids.len(),
+            active_file_id=engine.active_file.read().get_file_id(),
+            active_offset=engine.active_file.read().get_offset(),
+            keys=engine.index.size(),
+            read_only=engine.options.read_only,
+        );
+
+        if engine.options.read_only {
+            return Ok(engine);
+        }
+
+        // data files are written by standard io
+        if engine.options.mmap_at_startup {
+            engine.reset_io_type()?;
+        }
 
+        if engine.options.sync_interval_ms>0 {
+            let active_file=engine.active_file.clone();
+            let blob_store=engine.blob_store.clone();
+            let written_bytes=engine.written_bytes.clone();
+            let metrics=engine.options.metrics.clone();
+            engine.syncer=Some(PeriodicTask::start(Duration::from_millis(engine.options.sync_interval_ms),move||{
+                // nothing to do if no write happened since last sync
+                if written_bytes.load(Ordering::SeqCst)==0 {
+                    return;
+                }
+                if let Err(e)=flush_files(&active_file,&blob_store,&written_bytes,&*metrics) {
+                    warn!("periodic sync err: {}",e);
+                }
+            }));
+        }
 
-        Err(Errors::DataFileSizeError)
+        Ok(engine)
     }
 
     pub fn close(&self) -> Result<()> {
-        let read_record = self.active_file.read();
-        read_record.sync()
+        if self.closed.swap(true,Ordering::SeqCst) {
+            return Ok(());
+        }
+        // stop background tasks before the final flush
+        if let Some(compact_scheduler)=self.compact_scheduler.lock().take() {
+            compact_scheduler.stop();
+        }
+        if let Some(syncer)=self.syncer.as_ref() {
+            syncer.stop();
+        }
+
+        if self.options.read_only {
+            self.unlock();
+            return Ok(());
+        }
+
+        // persist txn id, records of merged transactions no longer carry it
+        write_txn_seq_file(&self.options.path,self.txn_id.load(Ordering::SeqCst))?;
+
+        self.flush()?;
+        self.unlock();
+        Ok(())
+    }
+
+    fn unlock(&self){
+        if let Some(file_lock)=self.file_lock.as_ref() {
+            if let Err(e)=file_lock.unlock() {
+                warn!("release lock file err: {}",e);
+            }
+        }
+    }
+
+    pub fn sync(&self) -> Result<()> {
+        if self.options.read_only {
+            return Ok(());
+        }
+        flush_files(&self.active_file,&self.blob_store,&self.written_bytes,&*self.options.metrics)
+    }
+
+    /// Current status of engine
+    pub fn stat(&self) -> Result<EngineStatus> {
+        let active_file_id=self.active_file.read().get_file_id();
+        let file_counts=self.inactive_files.read().len()+1;
+        Ok(EngineStatus{
+            key_counts: self.index.size(),
+            file_counts,
+            disk_size: dir_disk_size(&self.options.path),
+            reclaimable_size: self.reclaim_sizes.lock().values().sum(),
+            active_file_id,
+            disk_full: self.disk_full.load(Ordering::SeqCst)&&!self.has_free_space(0),
+        })
+    }
+
+    // whether every write is synced, batches of data types follow it
+    pub(crate) fn sync_write(&self)->bool{
+        self.options.sync_write
+    }
+
+    /// Persist all written records and blobs to disk
+    pub fn flush(&self) -> Result<()> {
+        if self.options.read_only {
+            return Ok(());
+        }
+        flush_files(&self.active_file,&self.blob_store,&self.written_bytes,&*self.options.metrics)
     }
 
     fn check_options(options: &Options) -> Option<Errors> {
@@ -68,12 +304,214 @@ impl Engine {
         if options.data_file_size == 0 {
             return Some(Errors::DataFileSizeError);
         }
+        if !options.compression.is_available() {
+            return Some(Errors::UnsupportedCompression);
+        }
+        if options.blob_threshold>0&&options.blob_file_size==0 {
+            return Some(Errors::DataFileSizeError);
+        }
+        if !(0.0..=1.0).contains(&options.blob_gc_ratio) {
+            return Some(Errors::BlobGcRatioError);
+        }
+        if !(0.0..=1.0).contains(&options.compact_ratio)||(options.compact_ratio>0.0&&options.compact_interval_ms==0) {
+            return Some(Errors::CompactRatioError);
+        }
+        // every write is synced already, other policies never take effect
+        if options.sync_write&&(options.sync_bytes_write>0||options.sync_interval_ms>0) {
+            return Some(Errors::SyncPolicyConflict);
+        }
+        if !merge::check_operator_names(&options.merge_operators) {
+            return Some(Errors::MergeOperatorConflict);
+        }
         None
     }
+
+    // load index from data files, return the latest txn id
+    fn load_index_from_data_files(&self)->Result<usize>{
+        let mut current_txn_id=NON_TXN_ID;
+        if self.file_ids.is_empty() {
+            return Ok(current_txn_id);
+        }
+
+        // stash records of unfinished transactions
+        let mut txn_records:HashMap<usize,Vec<TxnRecord>>=HashMap::new();
+
+        // records in merged files are loaded from hint file
+        let non_merge_file_id=get_non_merge_file_id(&self.options.path)?;
+
+        let active_file=self.active_file.read();
+        let inactive_files=self.inactive_files.read();
+        for (i,file_id) in self.file_ids.iter().enumerate() {
+            if let Some(non_merge_file_id)=non_merge_file_id {
+                if *file_id<non_merge_file_id {
+                    continue;
+                }
+            }
+            let mut offset=0;
+            loop {
+                let read_log_record=match *file_id==active_file.get_file_id() {
+                    true=>active_file.read_log_record(offset),
+                    false=>match inactive_files.get(file_id) {
+                        Some(data_file)=>data_file.read_log_record(offset),
+                        None=>return Err(Errors::DataFileNotFound),
+                    },
+                };
+                let (log_record,size)=match read_log_record {
+                    Ok(read_log_record)=>(read_log_record.log_record,read_log_record.size),
+                    Err(e)=>{
+                        if e==Errors::ReadFileEOF {
+                            break;
+                        }
+                        event!(Error,"corruption",file_id=file_id,offset=offset,error=e);
+                        return Err(e);
+                    }
+                };
+
+                let log_record_pos=LogRecordPos{
+                    file_id: *file_id,
+                    offset,
+                    size: size as u64,
+                };
+
+                let (real_key,txn_id)=parse_log_record_key(log_record.key.clone());
+                if txn_id==NON_TXN_ID {
+                    self.update_index(real_key,&log_record,log_record_pos);
+                } else if log_record.record_type==RecordType::TXNFIN {
+                    // transaction is committed, apply all of its records
+                    if let Some(records)=txn_records.remove(&txn_id) {
+                        for txn_record in records.into_iter() {
+                            self.update_index(txn_record.record.key.clone(),&txn_record.record,txn_record.position);
+                        }
+                    }
+                    self.add_reclaim_size(&log_record_pos);
+                } else {
+                    txn_records.entry(txn_id).or_default().push(TxnRecord{
+                        record: LogRecord{
+                            key: real_key,
+                            value: log_record.value,
+                            record_type: log_record.record_type,
+                        },
+                        position: log_record_pos,
+                    });
+                }
+
+                current_txn_id=current_txn_id.max(txn_id);
+                offset+=size as u64;
+            }
+
+            // set write offset of active file
+            if i==self.file_ids.len()-1 {
+                active_file.set_offset(offset);
+            }
+        }
+
+        // records of unfinished transactions are never applied
+        for txn_record in txn_records.values().flatten() {
+            self.add_reclaim_size(&txn_record.position);
+        }
+        event!(Info,"recovery",
+            from_file_id=non_merge_file_id.unwrap_or(INITIAL_FILE_ID),
+            files=self.file_ids.len(),
+            unfinished_txns=txn_records.len(),
+            txn_id=current_txn_id,
+        );
+
+        Ok(current_txn_id)
+    }
+
+    fn reset_io_type(&self)->Result<()>{
+        let mut active_file=self.active_file.write();
+        let file_id=active_file.get_file_id();
+        active_file.set_io_manager(new_io_manager(get_data_file_name(self.options.path.clone(),file_id),IOType::StdIO)?);
+
+        let mut inactive_files=self.inactive_files.write();
+        for (file_id,data_file) in inactive_files.iter_mut() {
+            data_file.set_io_manager(new_io_manager(get_data_file_name(self.options.path.clone(),*file_id),IOType::StdIO)?);
+        }
+        Ok(())
+    }
+
+    // apply record to memory index, overwritten and deleted records become reclaimable
+    fn update_index(&self,key:Vec<u8>,log_record:&LogRecord,pos:LogRecordPos){
+        let old_pos=match log_record.record_type {
+            RecordType::NORMAL=>{
+                self.blob_store.remove_ref(&key);
+                self.remove_merge_chain(&key);
+                self.index.put(key,pos)
+            },
+            RecordType::DELETED=>{
+                self.blob_store.remove_ref(&key);
+                self.remove_merge_chain(&key);
+                self.add_reclaim_size(&pos);
+                self.index.delete(key)
+            },
+            RecordType::BLOB=>{
+                self.blob_store.set_ref(key.clone(),LogRecordPos::decode(log_record.value.clone()));
+                self.remove_merge_chain(&key);
+                self.index.put(key,pos)
+            },
+            RecordType::TXNFIN=>{
+                self.add_reclaim_size(&pos);
+                None
+            },
+            RecordType::MERGE=>{
+                self.add_merge_operand(key,pos);
+                None
+            },
+        };
+        if let Some(old_pos)=old_pos {
+            self.add_reclaim_size(&old_pos);
+        }
+    }
+
+    // operand joins the chain of key, earlier records of the chain stay live
+    fn add_merge_operand(&self,key:Vec<u8>,pos:LogRecordPos){
+        let mut merge_chains=self.merge_chains.write();
+        let current_pos=self.index.get(key.clone());
+        let chain=match merge_chains.get_mut(&key) {
+            Some(chain) if is_chain_of(chain,current_pos)=>chain,
+            _=>{
+                // value is replaced by a record written later
+                if current_pos.is_some_and(|current_pos|current_pos.is_after(&pos)) {
+                    self.add_reclaim_size(&pos);
+                    return;
+                }
+                merge_chains.insert(key.clone(),MergeChain{base:current_pos,operands:Vec::new()});
+                merge_chains.get_mut(&key).unwrap()
+            },
+        };
+        let i=chain.operands.partition_point(|operand|pos.is_after(operand));
+        chain.operands.insert(i,pos);
+        if i==chain.operands.len()-1 {
+            self.index.put(key,pos);
+        }
+    }
+
+    // records of the chain are reclaimable once key is overwritten,
+    // except the last one which memory index points to
+    fn remove_merge_chain(&self,key:&[u8]){
+        if self.merge_chains.read().is_empty() {
+            return;
+        }
+        if let Some(mut chain)=self.merge_chains.write().remove(key) {
+            chain.operands.pop();
+            for pos in chain.base.iter().chain(chain.operands.iter()) {
+                self.add_reclaim_size(pos);
+            }
+        }
+    }
+
+    fn add_reclaim_size(&self,pos:&LogRecordPos){
+        *self.reclaim_sizes.lock().entry(pos.file_id).or_default()+=pos.size;
+    }
 }
 
 impl Engine {
     pub fn get(&self, key: Bytes) -> Result<Bytes> {
+        let _timer=LatencyTimer::new(&*self.options.metrics,metrics::GET_SECONDS);
+        if key.is_empty() {
+            return Err(Errors::KeyIsEmpty);
+        }
         let record_pos = self.index.get(key.to_vec());
         match record_pos {
             Some(pos) => self.get_value_on_offset(pos),
@@ -81,43 +519,245 @@ impl Engine {
         }
     }
 
+    /// Whether key exists, its value is not read
+    pub fn exists(&self, key: Bytes) -> Result<bool> {
+        if key.is_empty() {
+            return Err(Errors::KeyIsEmpty);
+        }
+        Ok(self.index.get(key.to_vec()).is_some())
+    }
+
     pub fn put(&self, key: Bytes, value: Bytes) -> Result<()> {
+        let _timer=LatencyTimer::new(&*self.options.metrics,metrics::PUT_SECONDS);
         if key.is_empty() {
             return Err(Errors::KeyIsEmpty);
         }
+
+        self.check_writable()?;
+
+        // large value is written into blob file, data file keeps its position
+        let _rotate_guard=self.blob_store.rotate_lock.read();
         let mut log_record=LogRecord{
-            key: key.clone().into(),
-            value: value.into(),
+            key: log_record_key_with_txn_id(key.to_vec(),NON_TXN_ID),
+            value: value.to_vec(),
             record_type: RecordType::NORMAL,
         };
-
-        let log_record_pos=self.append_log_record(&mut log_record)?;
-        match self.index.put(key.into(), log_record_pos) {
-            true=>Ok(()),
-            false=>Err(Errors::IndexUpdateError),
+        if self.blob_store.is_blob_value(value.len()) {
+            let pos=self.blob_store.write(&key,&value).map_err(|e|self.on_write_error(e))?;
+            log_record.value=pos.encode();
+            log_record.record_type=RecordType::BLOB;
         }
+
+        self.append_indexed_record(key.to_vec(),log_record)?;
+        Ok(())
     }
 
     pub fn remove(&self, key: Bytes) -> Result<()> {
+        let _timer=LatencyTimer::new(&*self.options.metrics,metrics::REMOVE_SECONDS);
         if key.is_empty() {
             return Err(Errors::KeyIsEmpty);
         }
 
-        match self.index.get(key.clone().into()) {
-            Some(log_record_pos)=>log_record_pos,
-            None => { return Ok(()); }
-        };
+        self.check_writable()?;
+        if self.index.get(key.to_vec()).is_none() {
+            return Ok(());
+        }
 
-        let mut log_record=LogRecord{
-            key: key.clone().into(),
+        let log_record=LogRecord{
+            key: log_record_key_with_txn_id(key.to_vec(),NON_TXN_ID),
             value: Default::default(),
             record_type: RecordType::DELETED,
         };
 
-        let _=self.append_log_record(&mut log_record)?;
-        match self.index.delete(key.into()) {
-            true=>Ok(()),
-            false=>Err(Errors::IndexUpdateError),
+        self.append_indexed_record(key.to_vec(),log_record)?;
+        Ok(())
+    }
+
+    /// Replace the value of key by `new` only if it holds `expected`,
+    /// none stands for a missing key on both sides, return whether it is replaced
+    pub fn compare_and_swap(&self,key:Bytes,expected:Option<Bytes>,new:Option<Bytes>)->Result<bool>{
+        if key.is_empty() {
+            return Err(Errors::KeyIsEmpty);
+        }
+        self.check_writable()?;
+
+        loop {
+            let current_pos=self.index.get(key.to_vec());
+            let current=match current_pos {
+                Some(pos)=>Some(self.get_value_on_offset(pos)?),
+                None=>None,
+            };
+            if current!=expected {
+                return Ok(false);
+            }
+            if current.is_none()&&new.is_none() {
+                return Ok(true);
+            }
+
+            // writers update the index while holding active file, so it can not
+            // change between the check and applying the new record
+            let _rotate_guard=self.blob_store.rotate_lock.read();
+            let mut active_file=self.active_file.write();
+            // value is replaced since it was read, compare again
+            if !self.is_index_unchanged(&key,current_pos) {
+                continue;
+            }
+
+            let mut log_record=LogRecord{
+                key: log_record_key_with_txn_id(key.to_vec(),NON_TXN_ID),
+                value: Default::default(),
+                record_type: RecordType::DELETED,
+            };
+            if let Some(value)=new.as_ref() {
+                log_record.value=value.to_vec();
+                log_record.record_type=RecordType::NORMAL;
+                if self.blob_store.is_blob_value(value.len()) {
+                    let pos=self.blob_store.write(&key,value).map_err(|e|self.on_write_error(e))?;
+                    log_record.value=pos.encode();
+                    log_record.record_type=RecordType::BLOB;
+                }
+            }
+            let records=[log_record.encode_with_compression(self.options.compression,self.options.compression_threshold)?];
+            let positions=self.write_records(&mut active_file,&[records.as_slice()],false).remove(0)?;
+            self.update_index(key.to_vec(),&log_record,positions[0]);
+            return Ok(true);
+        }
+    }
+
+    /// Put value only if key does not exist, return whether it is written
+    pub fn put_if_absent(&self,key:Bytes,value:Bytes)->Result<bool>{
+        self.compare_and_swap(key,None,Some(value))
+    }
+
+    // whether key is still at the position read before holding the active file
+    fn is_index_unchanged(&self,key:&[u8],pos:Option<LogRecordPos>)->bool{
+        match (self.index.get(key.to_vec()),pos) {
+            (Some(index_pos),Some(pos))=>index_pos.is_same_record(&pos),
+            (None,None)=>true,
+            _=>false,
+        }
+    }
+}
+
+impl Engine {
+    /// Append `operand` of merge operator `name` to key, operands are folded
+    /// into the value of key when it is read
+    pub fn merge(&self,key:Bytes,name:&str,operand:Bytes)->Result<()>{
+        self.append_merge_operand(&key,name,&operand).map(|_|())
+    }
+
+    /// Add `delta` to the decimal integer value of key atomically,
+    /// a missing key counts as 0, return the new value
+    pub fn incr_by(&self,key:Bytes,delta:i64)->Result<i64>{
+        if key.is_empty() {
+            return Err(Errors::KeyIsEmpty);
+        }
+        self.check_writable()?;
+
+        loop {
+            let current_pos=self.index.get(key.to_vec());
+            let current=match self.get(key.clone()) {
+                Ok(value)=>merge::parse_integer(&value).ok_or(Errors::NotInteger)?,
+                Err(Errors::KeyNotFound)=>0,
+                Err(e)=>return Err(e),
+            };
+            let value=current.checked_add(delta).ok_or(Errors::IntegerOverflow)?;
+
+            // the operand is only written if the value it is checked against is still current
+            let _rotate_guard=self.blob_store.rotate_lock.read();
+            let mut active_file=self.active_file.write();
+            if !self.is_index_unchanged(&key,current_pos) {
+                continue;
+            }
+            let log_record=LogRecord{
+                key: log_record_key_with_txn_id(key.to_vec(),NON_TXN_ID),
+                value: merge::encode_operand(INCR_OPERATOR,&delta.to_be_bytes()),
+                record_type: RecordType::MERGE,
+            };
+            let records=[log_record.encode_with_compression(self.options.compression,self.options.compression_threshold)?];
+            let positions=self.write_records(&mut active_file,&[records.as_slice()],false).remove(0)?;
+            self.update_index(key.to_vec(),&log_record,positions[0]);
+            return Ok(value);
+        }
+    }
+
+    /// Append `value` to the value of key, a missing key counts as empty,
+    /// return the length of the new value
+    pub fn append(&self,key:Bytes,value:Bytes)->Result<usize>{
+        let pos=self.append_merge_operand(&key,APPEND_OPERATOR,&value)?;
+        let (value,_)=self.fold_merge_chain(&key,pos)?;
+        Ok(value.len())
+    }
+
+    fn append_merge_operand(&self,key:&[u8],name:&str,operand:&[u8])->Result<LogRecordPos>{
+        if key.is_empty() {
+            return Err(Errors::KeyIsEmpty);
+        }
+        if !self.merge_operators.contains_key(name) {
+            return Err(Errors::MergeOperatorNotFound);
+        }
+        self.check_writable()?;
+
+        let log_record=LogRecord{
+            key: log_record_key_with_txn_id(key.to_vec(),NON_TXN_ID),
+            value: merge::encode_operand(name,operand),
+            record_type: RecordType::MERGE,
+        };
+        self.append_indexed_record(key.to_vec(),log_record)
+    }
+
+    // fold operands of key up to `until` onto their base value,
+    // return the value and whether the operand at `until` is applied
+    fn fold_merge_chain(&self,key:&[u8],until:LogRecordPos)->Result<(Bytes,bool)>{
+        let chain=self.merge_chains.read().get(key)
+            .filter(|chain|chain.operands.iter().any(|pos|pos.is_same_record(&until)))
+            .cloned();
+        let chain=match chain {
+            Some(chain)=>chain,
+            // key is overwritten meanwhile
+            None=>return match self.index.get(key.to_vec()) {
+                Some(pos) if !pos.is_same_record(&until)=>self.get_value_on_offset(pos).map(|value|(value,true)),
+                _=>Err(Errors::KeyNotFound),
+            },
+        };
+
+        let mut value=match chain.base {
+            Some(base)=>{
+                let log_record=self.read_log_record_on_offset(base)?;
+                match log_record.record_type {
+                    RecordType::NORMAL=>Some(log_record.value),
+                    RecordType::BLOB=>Some(self.blob_store.read(LogRecordPos::decode(log_record.value))?),
+                    _=>return Err(Errors::InvalidMergeOperand),
+                }
+            },
+            None=>None,
+        };
+        let mut applied=false;
+        for pos in chain.operands.iter().take_while(|pos|!pos.is_after(&until)) {
+            let log_record=self.read_log_record_on_offset(*pos)?;
+            let (name,operand)=merge::decode_operand(&log_record.value)?;
+            let operator=self.merge_operators.get(name).ok_or(Errors::MergeOperatorNotFound)?;
+            applied=match operator.merge(key,value.as_deref(),operand) {
+                Some(merged)=>{
+                    value=Some(merged);
+                    true
+                },
+                None=>{
+                    warn!("merge operand of operator {} at file {} offset {} is rejected",name,pos.file_id,pos.offset);
+                    false
+                },
+            };
+        }
+        Ok((Bytes::from(value.unwrap_or_default()),applied))
+    }
+
+    // last record of the chain of key in files before `file_id`, and whether it is the base
+    fn merge_chain_tail(&self,key:&[u8],file_id:u64)->Option<(LogRecordPos,bool)>{
+        let merge_chains=self.merge_chains.read();
+        let chain=merge_chains.get(key).filter(|chain|is_chain_of(chain,self.index.get(key.to_vec())))?;
+        match chain.operands.iter().rev().find(|pos|pos.file_id<file_id) {
+            Some(pos)=>Some((*pos,false)),
+            None=>chain.base.filter(|base|base.file_id<file_id).map(|base|(base,true)),
         }
     }
 }
@@ -125,90 +765,595 @@ impl Engine {
 impl Engine {
     // get specific value through value's position
     pub(crate) fn get_value_on_offset(&self, record_pos: LogRecordPos) -> Result<Bytes> {
+        let log_record=self.read_log_record_on_offset(record_pos)?;
+        match log_record.record_type {
+            RecordType::DELETED=>Err(Errors::KeyNotFound),
+            RecordType::BLOB=>{
+                let blob_pos=LogRecordPos::decode(log_record.value);
+                Ok(Bytes::from(self.blob_store.read(blob_pos)?))
+            },
+            RecordType::MERGE=>{
+                let (key,_)=parse_log_record_key(log_record.key);
+                self.fold_merge_chain(&key,record_pos).map(|(value,_)|value)
+            },
+            _=>Ok(Bytes::from(log_record.value)),
+        }
+    }
+
+    fn read_log_record_on_offset(&self,record_pos:LogRecordPos)->Result<LogRecord>{
         let active_file = self.active_file.read();
         let old_file = self.inactive_files.read();
-        let log_record = match active_file.get_file_id() == record_pos.file_id {
-            true => active_file.read_log_record(record_pos.offset)?,
+        let read_log_record = match active_file.get_file_id() == record_pos.file_id {
+            true => active_file.read_log_record(record_pos.offset),
             false => {
                 // get specific data file
-                let data_file = old_file.get(&record_pos.offset);
+                let data_file = old_file.get(&record_pos.file_id);
                 match data_file {
-                    Some(file) => file.read_log_record(record_pos.offset)?,
+                    Some(file) => file.read_log_record(record_pos.offset),
                     None => {
                         return Err(Errors::DataFileNotFound);
                     }
                 }
             }
         };
-        let log_record = log_record.log_record;
-        if log_record.record_type == RecordType::DELETED {
-            return Err(Errors::KeyNotFound);
+        match read_log_record {
+            Ok(read_log_record) => Ok(read_log_record.log_record),
+            Err(e) => {
+                event!(Error,"corruption",file_id=record_pos.file_id,offset=record_pos.offset,error=e);
+                Err(e)
+            }
         }
-        Ok(Bytes::from(log_record.value))
     }
 
     // append log record to active datafile
     pub(crate) fn append_log_record(&self,log_record:&mut LogRecord)->Result<LogRecordPos>{
+        let encoded_record=log_record.encode_with_compression(
+            self.options.compression,
+            self.options.compression_threshold,
+        )?;
+        let positions=self.append_encoded_records(vec![encoded_record],Vec::new(),self.options.sync_write)?;
+        Ok(positions[0])
+    }
+
+    // append log record of key and apply it to memory index
+    fn append_indexed_record(&self,key:Vec<u8>,log_record:LogRecord)->Result<LogRecordPos>{
+        let encoded_record=log_record.encode_with_compression(
+            self.options.compression,
+            self.options.compression_threshold,
+        )?;
+        let positions=self.append_encoded_records(vec![encoded_record],vec![(key,log_record)],self.options.sync_write)?;
+        Ok(positions[0])
+    }
+
+    // append encoded records through group commit, records are written contiguously
+    pub(crate) fn append_encoded_records(&self,records:Vec<Vec<u8>>,updates:Vec<IndexUpdate>,sync:bool)->Result<Vec<LogRecordPos>>{
+        self.group_commit.commit(records,updates,sync,|group|self.write_group(group))
+    }
+
+    // write records of a commit group with a single write and at most one sync,
+    // index is updated before active file is released so it follows the order of records
+    fn write_group(&self,group:&[Arc<WriteRequest>])->Vec<Result<Vec<LogRecordPos>>>{
         let mut active_file=self.active_file.write();
+        let requests:Vec<&[Vec<u8>]>=group.iter().map(|request|request.records.as_slice()).collect();
+        let sync=group.iter().any(|request|request.sync);
+        let results=self.write_records(&mut active_file,&requests,sync);
+        for (request,result) in group.iter().zip(results.iter()) {
+            if let Ok(positions)=result {
+                for ((key,log_record),pos) in request.updates.iter().zip(positions.iter()) {
+                    self.update_index(key.clone(),log_record,*pos);
+                }
+            }
+        }
+        results
+    }
 
-        let encoded_record=log_record.encode();
-        let encoded_len=encoded_record.len();
-    
-        // check if current active datafile size exceed max file size limit
-        // create new datafile if exceed
-        if active_file.get_data_file_size()+encoded_len as u64>self.options.data_file_size {
-            let active_file_id=active_file.get_file_id();
+    // write records of requests to active file locked by caller, return positions of each request,
+    // requests which are completely written before a failure still get their positions
+    fn write_records(&self,active_file:&mut DataFile,requests:&[&[Vec<u8>]],sync:bool)->Vec<Result<Vec<LogRecordPos>>>{
+        let mut positions=Vec::with_capacity(requests.len());
+        let mut written=0;
+        match self.write_requests(active_file,requests,sync,&mut positions,&mut written) {
+            Ok(())=>positions.into_iter().map(Ok).collect(),
+            Err(e)=>{
+                let mut results:Vec<Result<Vec<LogRecordPos>>>=positions.into_iter().take(written).map(Ok).collect();
+                results.resize_with(requests.len(),||Err(e.clone()));
+                results
+            },
+        }
+    }
 
-            // insert into old datafile maps
-            let mut write_guard=self.inactive_files.write();
-            write_guard.insert(active_file_id,DataFile::new(self.options.path.clone(), active_file_id, IOType::StdIO)?);
+    // `written` counts leading requests whose records are written, and synced when a file is rotated
+    fn write_requests(&self,active_file:&mut DataFile,requests:&[&[Vec<u8>]],sync:bool,positions:&mut Vec<Vec<LogRecordPos>>,written:&mut usize)->Result<()>{
+        if self.disk_full.load(Ordering::SeqCst) {
+            if !self.has_free_space(0) {
+                return Err(Errors::DiskFull);
+            }
+            // space is freed, failed writes may leave partial records at the tail of active files
+            self.blob_store.rotate()?;
+            self.rotate_active_file(active_file)?;
+            self.disk_full.store(false, Ordering::SeqCst);
+            event!(Info,"disk_space_recovered",dir=self.options.path.display(),active_file_id=active_file.get_file_id());
+        }
 
-            let new_active_file=DataFile::new(self.options.path.clone(),active_file_id,IOType::StdIO)?;
-            *active_file=new_active_file;
+        // check disk space before creating a new data file
+        let group_size:u64=requests.iter().flat_map(|records|records.iter()).map(|record|record.len() as u64).sum();
+        if active_file.get_offset()+group_size>self.options.data_file_size&&!self.has_free_space(group_size) {
+            return Err(self.on_write_error(Errors::DiskFull));
         }
 
-        let offset=active_file.get_offset();
-        let write_size=active_file.write(&encoded_record)?;
+        let mut buf=Vec::new();
+        for (i,records) in requests.iter().enumerate() {
+            let mut request_positions=Vec::with_capacity(records.len());
+            for record in records.iter() {
+                // check if current active datafile size exceed max file size limit
+                // create new datafile if exceed
+                let offset=active_file.get_offset()+buf.len() as u64;
+                if offset>0&&offset+record.len() as u64>self.options.data_file_size {
+                    if !buf.is_empty() {
+                        active_file.write(&buf).map_err(|e|self.on_write_error(e))?;
+                        self.options.metrics.incr_counter(metrics::BYTES_WRITTEN_TOTAL,buf.len() as u64);
+                        buf.clear();
+                    }
+                    self.rotate_active_file(active_file)?;
+                    *written=i;
+                }
+
+                request_positions.push(LogRecordPos{
+                    file_id: active_file.get_file_id(),
+                    offset: active_file.get_offset()+buf.len() as u64,
+                    size: record.len() as u64,
+                });
+                buf.extend_from_slice(record);
+            }
+            positions.push(request_positions);
+        }
+        let write_size=buf.len();
+        active_file.write(&buf).map_err(|e|self.on_write_error(e))?;
 
         let previous_write_bytes=self.written_bytes.fetch_add(write_size, Ordering::SeqCst);
+        self.options.metrics.incr_counter(metrics::BYTES_WRITTEN_TOTAL,write_size as u64);
+
+        // divided into 2 cases
+        // 1.Enable every sync write, sync once for the whole group
+        // 2.Disable every sync write but sync datafile depend on totoal write bytes size
+        let mut sync_write=self.options.sync_write||sync;
+        if !sync_write&&self.options.sync_bytes_write>0&&(previous_write_bytes+write_size)>=self.options.sync_bytes_write {
+            sync_write=true;
+        }
+
+        if sync_write {
+            // blobs of earlier unsynced groups may be referenced too
+            self.blob_store.sync()?;
+            sync_data_file(active_file,&*self.options.metrics)?;
+            self.written_bytes.store(0, Ordering::SeqCst);
+        }
+        *written=requests.len();
+        Ok(())
+    }
+
+    fn has_free_space(&self,size:u64)->bool{
+        available_disk_size(&self.options.path)>self.options.min_free_space.saturating_add(size)
+    }
+
+    // reject writes of read only engine, or early while disk is full,
+    // writes resume once space is freed
+    fn check_writable(&self)->Result<()>{
+        if self.options.read_only {
+            return Err(Errors::DatabaseReadOnly);
+        }
+        if self.disk_full.load(Ordering::SeqCst)&&!self.has_free_space(0) {
+            return Err(Errors::DiskFull);
+        }
+        Ok(())
+    }
+
+    // remember running out of disk space so later writes fail fast
+    fn on_write_error(&self,e:Errors)->Errors{
+        if e==Errors::DiskFull&&!self.disk_full.swap(true, Ordering::SeqCst) {
+            event!(Warn,"disk_full",dir=self.options.path.display(),min_free_space=self.options.min_free_space);
+        }
+        e
+    }
+
+    fn rotate_active_file(&self,active_file:&mut DataFile)->Result<()>{
+        // persist current active file before turning it into an old file,
+        // blob files must be durable before data files referencing them
+        self.blob_store.sync()?;
+        sync_data_file(active_file,&*self.options.metrics)?;
+        let active_file_id=active_file.get_file_id();
+
+        // insert into old datafile maps
+        let mut write_guard=self.inactive_files.write();
+        write_guard.insert(active_file_id,DataFile::new(self.options.path.clone(), active_file_id, IOType::StdIO)?);
+
+        let new_active_file=DataFile::new(self.options.path.clone(),active_file_id+1,IOType::StdIO)?;
+        event!(Info,"rotate",file_id=active_file_id,offset=active_file.get_offset(),new_file_id=active_file_id+1);
+        *active_file=new_active_file;
+        self.options.metrics.incr_counter(metrics::FILE_ROTATIONS_TOTAL,1);
+        Ok(())
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        if let Err(e)=self.close() {
+            warn!("close engine err: {}",e);
+        }
+    }
+}
+
+// backup related
+impl Engine {
+    /// Backup database into `dest` while serving traffic, `dest` can be opened directly.
+    /// Data files kept in `dest` by a previous backup are not copied again
+    pub fn backup<P:AsRef<Path>>(&self,dest:P)->Result<()>{
+        let dest=dest.as_ref();
+        if let Err(e)=fs::create_dir_all(dest) {
+            warn!("create backup directory err: {}",e);
+            return Err(Errors::CreateDirError);
+        }
+        // manifest of previous checkpoint is outdated once files change
+        remove_manifest(dest)?;
+
+        // blobs are synced before records referencing them,
+        // active file is copied up to the synced offset
+        self.blob_store.sync()?;
+        let (active_file_id,active_offset,mut file_ids)={
+            let active_file=self.active_file.read();
+            if !self.options.read_only {
+                active_file.sync()?;
+            }
+            let inactive_files=self.inactive_files.read();
+            let file_ids:Vec<u64>=inactive_files.keys().copied().collect();
+            (active_file.get_file_id(),active_file.get_offset(),file_ids)
+        };
+        let blob_stats=self.blob_store.stats();
+        let txn_id=self.txn_id.load(Ordering::SeqCst);
+
+        let backup_err=|e:std::io::Error|{
+            warn!("backup file err: {}",e);
+            Errors::BackupError
+        };
+
+        // old data files are immutable, skip the ones copied by previous backup
+        for file_id in file_ids.iter() {
+            let src=get_data_file_name(self.options.path.clone(),*file_id);
+            let dst=get_data_file_name(dest.to_path_buf(),*file_id);
+            if is_same_file(&src,&dst) {
+                continue;
+            }
+            link_or_copy(&src,&dst).map_err(backup_err)?;
+        }
+        copy_file_prefix(
+            &get_data_file_name(self.options.path.clone(),active_file_id),
+            &get_data_file_name(dest.to_path_buf(),active_file_id),
+            active_offset,
+        ).map_err(backup_err)?;
+        file_ids.push(active_file_id);
+
+        for (file_id,stat) in blob_stats.iter() {
+            let src=get_blob_file_name(self.options.path.clone(),*file_id);
+            let dst=get_blob_file_name(dest.to_path_buf(),*file_id);
+            if is_same_file(&src,&dst) {
+                continue;
+            }
+            copy_file_prefix(&src,&dst,stat.total_size).map_err(backup_err)?;
+        }
+
+        // hint and merge finished file describe merged data files
+        for file_name in [HINT_FILE_NAME_SUFFIX,MERGE_FINISHED_FILE_NAME_SUFFIX] {
+            let src=self.options.path.join(file_name);
+            let dst=dest.join(file_name);
+            if src.is_file() {
+                link_or_copy(&src,&dst).map_err(backup_err)?;
+            } else if dst.is_file() {
+                fs::remove_file(&dst).map_err(backup_err)?;
+            }
+        }
+
+        // files removed from database since previous backup
+        let dir=fs::read_dir(dest).map_err(backup_err)?;
+        for entry in dir.flatten() {
+            let file_name=entry.file_name();
+            let file_name=file_name.to_string_lossy();
+            let stale=match (file_name.strip_suffix(DATA_FILE_NAME_SUFFIX),file_name.strip_suffix(BLOB_FILE_NAME_SUFFIX)) {
+                (Some(file_id),_)=>file_id.parse::<u64>().map(|file_id|!file_ids.contains(&file_id)).unwrap_or(false),
+                (_,Some(file_id))=>file_id.parse::<u64>().map(|file_id|!blob_stats.contains_key(&file_id)).unwrap_or(false),
+                _=>false,
+            };
+            if stale {
+                fs::remove_file(entry.path()).map_err(backup_err)?;
+            }
+        }
+
+        write_txn_seq_file(dest,txn_id)
+    }
+
+    /// Backup database into `dest` with a manifest of files, sizes and checksums,
+    /// opening `dest` verifies it against the manifest
+    pub fn checkpoint<P:AsRef<Path>>(&self,dest:P)->Result<()>{
+        let dest=dest.as_ref();
+        self.backup(dest)?;
+        let manifest=Manifest::build(dest,load_txn_id(dest)?)?;
+        manifest.write(dest)
+    }
+
+    /// Rebuild database directory `target` from checkpoint directory `checkpoint`,
+    /// checksums of all files are verified before copying
+    pub fn restore<P:AsRef<Path>,Q:AsRef<Path>>(checkpoint:P,target:Q)->Result<()>{
+        let (checkpoint,target)=(checkpoint.as_ref(),target.as_ref());
+        let manifest=match Manifest::load(checkpoint)? {
+            Some(manifest)=>manifest,
+            None=>return Err(Errors::ManifestMismatch),
+        };
+        manifest.verify(checkpoint,true)?;
+
+        if fs::read_dir(target).map(|mut dir|dir.next().is_some()).unwrap_or(false) {
+            return Err(Errors::RestoreDirNotEmpty);
+        }
+        if let Err(e)=fs::create_dir_all(target) {
+            warn!("create restore directory err: {}",e);
+            return Err(Errors::CreateDirError);
+        }
+
+        // files may be appended after checkpoint, only the recorded part is restored
+        for file in manifest.files.iter() {
+            if let Err(e)=copy_file_prefix(&checkpoint.join(&file.name),&target.join(&file.name),file.size) {
+                warn!("restore file err: {}",e);
+                return Err(Errors::WriteFileError);
+            }
+        }
+        write_txn_seq_file(target,manifest.txn_id)?;
+        manifest.write(target)
+    }
+}
+
+// compaction related
+impl Engine {
+    /// Rewrite live records of all old data files into a merge directory,
+    /// merged files replace the old ones when engine is opened next time,
+    /// so disk space is only reclaimed after reopening
+    pub fn compact(&self)->Result<()> {
+
+        if self.options.read_only {
+            return Err(Errors::DatabaseReadOnly);
+        }
+
+        // check compact status
+        let lock=self.compact_lock.try_lock();
+        if lock.is_none() {
+            return Err(Errors::ProcessCompactError);
+        }
+
+        let start=Instant::now();
+        // merged files are written before old files are removed
+        let live_size=self.live_data_size();
+        if !self.has_free_space(live_size) {
+            warn!("no enough disk space for compaction, need {} bytes",live_size);
+            return Err(Errors::DiskFull);
+        }
+
+        // blob files to collect are chosen while data files rotate,
+        // so no newer record can reference them
+        let (merge_files,gc_file_ids)={
+            let _rotate_guard=self.blob_store.rotate_lock.write();
+            self.blob_store.rotate()?;
+            (self.rotate_merge_files()?,self.blob_store.gc_candidates())
+        };
+        if merge_files.is_empty() {
+            return Ok(());
+        }
+        // files before this id are merged, newer files are untouched
+        let non_merge_file_id=merge_files.last().unwrap().get_file_id()+1;
+
+        // rewrite valid records into a temporary merge directory
+        let merge_path=get_merge_path(&self.options.path);
+        if merge_path.is_dir() {
+            if let Err(e)=fs::remove_dir_all(&merge_path) {
+                warn!("remove merge directory err: {}",e);
+                return Err(Errors::CreateDirError);
+            }
+        }
+        let mut merge_options=(*self.options).clone();
+        merge_options.path=merge_path.clone();
+        // merge engine is synced once rewriting finished
+        merge_options.sync_interval_ms=0;
+        let merge_engine=Engine::open(merge_options)?;
+        let hint_file=DataFile::new_hint_file(merge_path.clone(),IOType::StdIO)?;
+        let mut blob_writer=self.blob_store.new_writer(merge_path.clone());
+        let mut rate_limiter=RateLimiter::new(self.options.compact_bytes_per_sec);
+
+        for data_file in merge_files.iter() {
+            let mut offset=0;
+            loop {
+                let (mut log_record,size)=match data_file.read_log_record(offset) {
+                    Ok(read_log_record)=>(read_log_record.log_record,read_log_record.size),
+                    Err(e)=>{
+                        if e==Errors::ReadFileEOF {
+                            break;
+                        }
+                        return Err(e);
+                    }
+                };
+                rate_limiter.consume(size as u64);
+
+                // only rewrite records still referenced by memory index
+                let (real_key,_)=parse_log_record_key(log_record.key.clone());
+                let is_record=|pos:LogRecordPos|pos.file_id==data_file.get_file_id()&&pos.offset==offset;
+                let is_live=match self.merge_chain_tail(&real_key,non_merge_file_id) {
+                    // operands in merged files are folded into one value,
+                    // operands in newer files apply to it after reopen
+                    Some((tail,false)) if is_record(tail)=>{
+                        let (value,_)=self.fold_merge_chain(&real_key,tail)?;
+                        let mut folded_record=LogRecord{
+                            key: log_record_key_with_txn_id(real_key.clone(),NON_TXN_ID),
+                            value: value.to_vec(),
+                            record_type: RecordType::NORMAL,
+                        };
+                        let pos=merge_engine.append_log_record(&mut folded_record)?;
+                        hint_file.write_hint_log(real_key.clone(),pos)?;
+                        false
+                    },
+                    Some((tail,is_base))=>is_base&&is_record(tail),
+                    None=>self.index.get(real_key.clone()).is_some_and(is_record),
+                };
+                if is_live {
+                    log_record.key=log_record_key_with_txn_id(real_key.clone(),NON_TXN_ID);
+                    match log_record.record_type {
+                        RecordType::BLOB=>{
+                            let blob_pos=self.rewrite_blob(&mut blob_writer,&gc_file_ids,&real_key,&mut log_record)?;
+                            let pos=merge_engine.append_log_record(&mut log_record)?;
+                            hint_file.write_blob_hint_log(real_key,pos,blob_pos)?;
+                        },
+                        _=>{
+                            let pos=merge_engine.append_log_record(&mut log_record)?;
+                            hint_file.write_hint_log(real_key,pos)?;
+                        },
+                    }
+                }
+                offset+=size as u64;
+            }
+        }
+
+        blob_writer.sync()?;
+        merge_engine.sync()?;
+        hint_file.sync()?;
+
+        // merge finished file marks the merge directory complete,
+        // it also records blob files to remove when merge is loaded
+        let merge_fin_file=DataFile::new_merge_fin_file(merge_path,IOType::StdIO)?;
+        let merge_fin_record=LogRecord{
+            key: MERGE_FIN_KEY.to_vec(),
+            value: non_merge_file_id.to_string().into_bytes(),
+            record_type: RecordType::NORMAL,
+        };
+        merge_fin_file.write(&merge_fin_record.encode())?;
+        let gc_file_ids:Vec<String>=gc_file_ids.iter().map(|file_id|file_id.to_string()).collect();
+        let blob_gc_record=LogRecord{
+            key: BLOB_GC_KEY.to_vec(),
+            value: gc_file_ids.join(",").into_bytes(),
+            record_type: RecordType::NORMAL,
+        };
+        merge_fin_file.write(&blob_gc_record.encode())?;
+        merge_fin_file.sync()?;
+        self.merged_file_id.store(non_merge_file_id, Ordering::SeqCst);
+
+        // merge engine only holds live records, the rest is reclaimed once merge is loaded
+        let merged_size:u64=merge_files.iter().map(|data_file|data_file.get_data_file_size()).sum();
+        let reclaimed_size=merged_size.saturating_sub(merge_engine.live_data_size());
+        self.options.metrics.incr_counter(metrics::COMPACT_RECLAIMED_BYTES_TOTAL,reclaimed_size);
+        self.options.metrics.observe_histogram(metrics::COMPACT_SECONDS,start.elapsed().as_secs_f64());
+        event!(Info,"compact",
+            files=merge_files.len(),
+            non_merge_file_id=non_merge_file_id,
+            blob_gc_files=gc_file_ids.len(),
+            reclaimed_bytes=reclaimed_size,
+            elapsed_ms=start.elapsed().as_millis(),
+        );
+
+        Ok(())
+    }
+
+    /// Start background compaction triggered by `compact_ratio`,
+    /// it is stopped when engine is closed
+    pub fn start_auto_compact(self:&Arc<Self>){
+        if self.options.compact_ratio<=0.0 {
+            return;
+        }
+        let mut compact_scheduler=self.compact_scheduler.lock();
+        if compact_scheduler.is_some() {
+            return;
+        }
+        let engine=Arc::downgrade(self);
+        *compact_scheduler=Some(PeriodicTask::start(Duration::from_millis(self.options.compact_interval_ms),move||{
+            let engine=match engine.upgrade() {
+                Some(engine)=>engine,
+                None=>return,
+            };
+            if !engine.need_compact() {
+                return;
+            }
+            match engine.compact() {
+                Ok(_)|Err(Errors::ProcessCompactError)=>{},
+                Err(e)=>warn!("auto compaction err: {}",e),
+            }
+        }));
+    }
+
+    // whether an old data file reaches dead bytes ratio,
+    // a finished merge waiting for reopen is not redone as it would merge the same files again
+    fn need_compact(&self)->bool{
+        if self.merged_file_id.load(Ordering::SeqCst)>0 {
+            return false;
+        }
+        let reclaim_sizes=self.reclaim_sizes.lock();
+        let inactive_files=self.inactive_files.read();
+        inactive_files.iter().any(|(file_id,data_file)|{
+            let total_size=data_file.get_data_file_size();
+            let dead_size=reclaim_sizes.get(file_id).copied().unwrap_or_default().min(total_size);
+            total_size>0&&dead_size as f32/total_size as f32>=self.options.compact_ratio
+        })
+    }
 
-        // divided into 2 cases
-        // 1.Enable every sync write
-        // 2.Disable every sync write but sync datafile depend on totoal write bytes size
-        let mut sync_write=self.options.sync_write;
-        if !sync_write&&(previous_write_bytes+write_size)>=self.options.sync_bytes_write {
-            sync_write=true;
+    // estimated bytes of live records, which compaction rewrites
+    fn live_data_size(&self)->u64{
+        let mut live_size=self.active_file.read().get_offset();
+        let reclaim_sizes=self.reclaim_sizes.lock();
+        let inactive_files=self.inactive_files.read();
+        for (file_id,data_file) in inactive_files.iter() {
+            let total_size=data_file.get_data_file_size();
+            live_size+=total_size-reclaim_sizes.get(file_id).copied().unwrap_or_default().min(total_size);
         }
+        live_size
+    }
 
-        if sync_write {
-            active_file.sync()?;
-            self.written_bytes.store(0, Ordering::SeqCst);
+    // move a live blob out of a blob file being collected, return its blob position
+    fn rewrite_blob(&self,blob_writer:&mut BlobWriter,gc_file_ids:&[u64],key:&[u8],log_record:&mut LogRecord)->Result<LogRecordPos>{
+        let blob_pos=LogRecordPos::decode(log_record.value.clone());
+        if !gc_file_ids.contains(&blob_pos.file_id) {
+            return Ok(blob_pos);
         }
-        Ok(LogRecordPos { file_id: active_file.get_file_id(), offset, size: write_size as u64 })
+        let value=self.blob_store.read(blob_pos)?;
+        let new_blob_pos=blob_writer.write(key,&value)?;
+        log_record.value=new_blob_pos.encode();
+        Ok(new_blob_pos)
     }
-}
 
-// compaction related
-impl Engine {
-    pub fn compact(&self)->Result<()> {
+    /// Live and total bytes of every blob file
+    pub fn blob_stats(&self)->HashMap<u64,BlobFileStat>{
+        self.blob_store.stats()
+    }
 
-        // check compact status
-        let lock=self.compact_lock.try_lock();
-        if lock.is_none() {
-            return Err(Errors::ProcessCompactError);
+    // turn active file into an old file, return all old files to merge
+    fn rotate_merge_files(&self)->Result<Vec<DataFile>>{
+        // lock active file before old files, the same order as writes rotating files
+        let mut active_file=self.active_file.write();
+        let mut inactive_files=self.inactive_files.write();
+        let mut merge_file_ids:Vec<u64>=inactive_files.keys().copied().collect();
+
+        self.blob_store.sync()?;
+        active_file.sync()?;
+        let active_file_id=active_file.get_file_id();
+        let new_active_file=DataFile::new(self.options.path.clone(),active_file_id+1,IOType::StdIO)?;
+        *active_file=new_active_file;
+        inactive_files.insert(active_file_id,DataFile::new(self.options.path.clone(),active_file_id,IOType::StdIO)?);
+        merge_file_ids.push(active_file_id);
+        merge_file_ids.sort();
+
+        let mut merge_files=Vec::with_capacity(merge_file_ids.len());
+        for file_id in merge_file_ids.into_iter() {
+            merge_files.push(DataFile::new(self.options.path.clone(),file_id,IOType::StdIO)?);
         }
-        todo!();
-        
-        Ok(())
+        Ok(merge_files)
     }
 
     pub fn load_index_from_hint_file(&self)->Result<()>{
-        let hint_file_path=self.options.path.clone().join(data_file::HINT_FILE_NAME_SUFFIX);
-        if !hint_file_path.is_file() {
+        let hint_file_path=self.options.path.clone();
+        if !hint_file_path.join(HINT_FILE_NAME_SUFFIX).is_file() {
             return Ok(());
         }
 
-        let hint_file=DataFile::new_hint_file(hint_file_path)?;
+        let hint_file=DataFile::new_hint_file(hint_file_path,IOType::MmapIO)?;
         let mut read_offset=0;
         loop {
              let (log_record,size)=match hint_file.read_log_record(read_offset) {
@@ -217,13 +1362,17 @@ impl Engine {
                  },
                  Err(e)=>{
                     if e==Errors::ReadFileEOF{
-                      break;  
+                      break;
                     }
                     return Err(e);
                  },
              };
 
-             let log_record_pos=LogRecordPos::decode(log_record.value);
+             let log_record_pos=LogRecordPos::decode(log_record.value.clone());
+             if log_record.record_type==RecordType::BLOB {
+                 let blob_pos=LogRecordPos::decode(log_record.value[log_record_pos.encode().len()..].to_vec());
+                 self.blob_store.set_ref(log_record.key.clone(),blob_pos);
+             }
              self.index.put(log_record.key, log_record_pos);
              read_offset+=size as u64;
         }
@@ -237,7 +1386,7 @@ pub struct Iterator<'a> {
     engine: &'a Engine,
 }
 
-impl<'a> Iterator<'a> {
+impl Iterator<'_> {
     pub fn rewind(&self) {
         let mut write_guard=self.index_iterator.write();
         write_guard.rewind();
@@ -250,18 +1399,26 @@ impl<'a> Iterator<'a> {
 
     pub fn next(&self)->Option<(Bytes,Bytes)> {
         let mut write_guard=self.index_iterator.write();
+        // skip entries whose value can not be read, e.g. a blob lost by a crash
         while let Some((key,log_record_pos))=write_guard.next() {
-            let value=self.engine.get_value_on_offset(log_record_pos).expect("get value from active file error");
-            
-            return Some((Bytes::from(key),value));
+            match self.engine.get_value_on_offset(log_record_pos) {
+                Ok(value)=>return Some((Bytes::from(key),value)),
+                Err(e)=>warn!("iterator skips key at file {} offset {}: {}",log_record_pos.file_id,log_record_pos.offset,e),
+            }
         }
 
         None
     }
+
+    // next key without reading its value
+    pub(crate) fn next_key(&self)->Option<Bytes> {
+        let mut write_guard=self.index_iterator.write();
+        write_guard.next().map(|(key,_)|Bytes::from(key))
+    }
 }
 
 impl Engine {
-    pub fn iter(&self, options: IteratorOptions) -> Iterator {
+    pub fn iter(&self, options: IteratorOptions) -> Iterator<'_> {
         Iterator {
             index_iterator: Arc::new(RwLock::new(self.index.iterator(options))),
             engine: self,
@@ -322,96 +1479,1450 @@ impl WriteBatch<'_> {
         }
 
         let mut write_guard=self.pending_writes.lock();
-        write_guard.remove(&key.to_vec());
+        // key not exists in engine, just drop the pending write
+        if self.engine.index.get(key.to_vec()).is_none() {
+            write_guard.remove(&key.to_vec());
+            return Ok(());
+        }
+
+        let log_record=LogRecord{
+            key:key.to_vec(),
+            value:Default::default(),
+            record_type:RecordType::DELETED,
+        };
+        write_guard.insert(key.to_vec(), log_record);
         Ok(())
     }
 
     pub fn commit(&self)->Result<()> {
+        let mut pending_writes=self.pending_writes.lock();
+        if pending_writes.is_empty() {
+            return Ok(());
+        }
+        if pending_writes.len()>self.options.max_batch_size {
+            return Err(Errors::ExceedMaxBatchSize);
+        }
 
+        self.engine.check_writable()?;
+        let _rotate_guard=self.engine.blob_store.rotate_lock.read();
+        let txn_id=self.engine.txn_id.fetch_add(1, Ordering::SeqCst)+1;
+
+        let mut log_records=Vec::with_capacity(pending_writes.len()+1);
+        let mut encoded_records=Vec::with_capacity(pending_writes.len()+1);
+        for (_,item) in pending_writes.iter() {
+            let mut log_record=LogRecord{
+                key:log_record_key_with_txn_id(item.key.clone(), txn_id),
+                value:item.value.clone(),
+                record_type:item.record_type,
+            };
+            if item.record_type==RecordType::NORMAL&&self.engine.blob_store.is_blob_value(item.value.len()) {
+                let blob_pos=self.engine.blob_store.write(&item.key,&item.value).map_err(|e|self.engine.on_write_error(e))?;
+                log_record.value=blob_pos.encode();
+                log_record.record_type=RecordType::BLOB;
+            }
+            encoded_records.push(log_record.encode_with_compression(
+                self.engine.options.compression,
+                self.engine.options.compression_threshold,
+            )?);
+            log_records.push((item.key.clone(),log_record));
+        }
+
+        // mark transaction finished
+        let fin_record=LogRecord{
+            key:log_record_key_with_txn_id(TXN_FIN_KEY.to_vec(), txn_id),
+            value:Default::default(),
+            record_type:RecordType::TXNFIN,
+        };
+        encoded_records.push(fin_record.encode());
+        log_records.push((TXN_FIN_KEY.to_vec(),fin_record));
+
+        // records and txn finished mark are committed as one group request,
+        // memory index is updated once all of them are written
+        self.engine.append_encoded_records(encoded_records,log_records,self.options.sync)?;
+
+        pending_writes.clear();
         Ok(())
     }
 }
 
 impl Engine {
-    fn new_write_batch(){
+    pub fn new_write_batch(&self,options:WriteBatchOptions)->Result<WriteBatch<'_>>{
+        Ok(WriteBatch{
+            pending_writes: Arc::new(Mutex::new(HashMap::new())),
+            engine: self,
+            options,
+        })
+    }
+}
+
+// log record key is prefixed with the txn id which wrote it
+pub(crate) fn log_record_key_with_txn_id(key:Vec<u8>,txn_id:usize)->Vec<u8>{
+    let mut encoded_key=BytesMut::new();
+    prost::encoding::encode_varint(txn_id as u64,&mut encoded_key);
+    encoded_key.put_slice(&key);
+    encoded_key.to_vec()
+}
+
+// split log record key into real key and txn id
+pub(crate) fn parse_log_record_key(key:Vec<u8>)->(Vec<u8>,usize){
+    let mut buf=BytesMut::from(key.as_slice());
+    let txn_id=match prost::encoding::decode_varint(&mut buf) {
+        Ok(txn_id)=>txn_id,
+        Err(e)=>panic!("decode log record key err: {}",e),
+    };
+    (buf.to_vec(),txn_id as usize)
+}
+
+// whether chain is still the value of key, memory index points to its last operand
+fn is_chain_of(chain:&MergeChain,index_pos:Option<LogRecordPos>)->bool{
+    match (chain.operands.last(),index_pos) {
+        (Some(last),Some(index_pos))=>last.is_same_record(&index_pos),
+        _=>false,
+    }
+}
+
+// sync blob files before data files which reference them
+fn flush_files(active_file:&RwLock<DataFile>,blob_store:&BlobStore,written_bytes:&AtomicUsize,metrics:&dyn Metrics)->Result<()>{
+    blob_store.sync()?;
+    // holding the lock keeps writers out until the sync finished
+    let active_file=active_file.read();
+    sync_data_file(&active_file,metrics)?;
+    written_bytes.store(0, Ordering::SeqCst);
+    Ok(())
+}
+
+fn sync_data_file(data_file:&DataFile,metrics:&dyn Metrics)->Result<()>{
+    let _timer=LatencyTimer::new(metrics,metrics::FSYNC_SECONDS);
+    data_file.sync()
+}
+
+fn get_merge_path(dir_path:&Path)->PathBuf{
+    let file_name=dir_path.file_name().unwrap_or_default().to_string_lossy();
+    dir_path.with_file_name(format!("{}{}",file_name,MERGE_DIR_NAME_SUFFIX))
+}
+
+// read first record value of a small metadata file
+fn read_meta_value(data_file:&DataFile)->Result<Option<u64>>{
+    match data_file.read_log_record(0) {
+        Ok(read_log_record)=>{
+            let value=String::from_utf8_lossy(&read_log_record.log_record.value).to_string();
+            match value.parse::<u64>() {
+                Ok(value)=>Ok(Some(value)),
+                Err(_)=>Err(Errors::DataDirCorrupted),
+            }
+        },
+        Err(Errors::ReadFileEOF)=>Ok(None),
+        Err(e)=>Err(e),
+    }
+}
+
+// get the first file id not included by last merge
+fn get_non_merge_file_id(dir_path:&Path)->Result<Option<u64>>{
+    if !dir_path.join(MERGE_FINISHED_FILE_NAME_SUFFIX).is_file() {
+        return Ok(None);
+    }
+    let merge_fin_file=DataFile::new_merge_fin_file(dir_path.to_path_buf(),IOType::MmapIO)?;
+    read_meta_value(&merge_fin_file)
+}
+
+// blob files collected by last merge, stored after non merge file id
+fn get_blob_gc_file_ids(dir_path:&Path)->Result<Vec<u64>>{
+    let merge_fin_file=DataFile::new_merge_fin_file(dir_path.to_path_buf(),IOType::MmapIO)?;
+    let first_record=merge_fin_file.read_log_record(0)?;
+    let blob_gc_record=match merge_fin_file.read_log_record(first_record.size as u64) {
+        Ok(read_log_record)=>read_log_record.log_record,
+        Err(Errors::ReadFileEOF)=>return Ok(Vec::new()),
+        Err(e)=>return Err(e),
+    };
+    let value=String::from_utf8_lossy(&blob_gc_record.value).to_string();
+    let mut file_ids=Vec::new();
+    for file_id in value.split(',').filter(|file_id|!file_id.is_empty()) {
+        match file_id.parse::<u64>() {
+            Ok(file_id)=>file_ids.push(file_id),
+            Err(_)=>return Err(Errors::DataDirCorrupted),
+        }
+    }
+    Ok(file_ids)
+}
+
+fn write_txn_seq_file(dir_path:&Path,txn_id:usize)->Result<()>{
+    let txn_seq_path=dir_path.join(TXN_SEQ_FILE_NAME_SUFFIX);
+    if txn_seq_path.is_file() {
+        if let Err(e)=fs::remove_file(&txn_seq_path) {
+            warn!("remove txn seq file err: {}",e);
+            return Err(Errors::WriteFileError);
+        }
+    }
+    let txn_seq_file=DataFile::new_txn_seq_file(dir_path.to_path_buf(),IOType::StdIO)?;
+    let txn_seq_record=LogRecord{
+        key: TXN_SEQ_KEY.to_vec(),
+        value: txn_id.to_string().into_bytes(),
+        record_type: RecordType::NORMAL,
+    };
+    txn_seq_file.write(&txn_seq_record.encode())?;
+    txn_seq_file.sync()
+}
+
+fn load_txn_id(dir_path:&Path)->Result<usize>{
+    if !dir_path.join(TXN_SEQ_FILE_NAME_SUFFIX).is_file() {
+        return Ok(NON_TXN_ID);
+    }
+    let txn_seq_file=DataFile::new_txn_seq_file(dir_path.to_path_buf(),IOType::MmapIO)?;
+    Ok(read_meta_value(&txn_seq_file)?.unwrap_or_default() as usize)
+}
+
+// move files of a finished merge into database directory,
+// an unfinished merge directory is simply discarded
+fn load_merge_files(dir_path:&Path)->Result<()>{
+    let merge_path=get_merge_path(dir_path);
+    if !merge_path.is_dir() {
+        return Ok(());
+    }
+
+    let non_merge_file_id=match get_non_merge_file_id(&merge_path)? {
+        Some(file_id)=>file_id,
+        None=>{
+            if let Err(e)=fs::remove_dir_all(&merge_path) {
+                warn!("remove merge directory err: {}",e);
+                return Err(Errors::ReadDirError);
+            }
+            return Ok(());
+        }
+    };
+
+    // merged files no longer match checkpoint manifest
+    remove_manifest(dir_path)?;
+    event!(Info,"merge_loaded",dir=dir_path.display(),non_merge_file_id=non_merge_file_id);
+
+    // remove collected blob files
+    for file_id in get_blob_gc_file_ids(&merge_path)?.into_iter() {
+        let file_name=get_blob_file_name(dir_path.to_path_buf(),file_id);
+        if file_name.is_file() {
+            if let Err(e)=fs::remove_file(file_name) {
+                warn!("remove collected blob file err: {}",e);
+                return Err(Errors::DataDirCorrupted);
+            }
+        }
+    }
+
+    // remove merged data files
+    for file_id in 0..non_merge_file_id {
+        let file_name=get_data_file_name(dir_path.to_path_buf(),file_id);
+        if file_name.is_file() {
+            if let Err(e)=fs::remove_file(file_name) {
+                warn!("remove merged data file err: {}",e);
+                return Err(Errors::DataDirCorrupted);
+            }
+        }
+    }
+
+    let dir=match fs::read_dir(&merge_path) {
+        Ok(dir)=>dir,
+        Err(e)=>{
+            warn!("read merge directory err: {}",e);
+            return Err(Errors::ReadDirError);
+        }
+    };
+    for entry in dir.flatten() {
+        let file_name=entry.file_name();
+        let name=file_name.to_string_lossy();
+        if name.ends_with(DATA_FILE_NAME_SUFFIX)||name.ends_with(BLOB_FILE_NAME_SUFFIX)||name==HINT_FILE_NAME_SUFFIX||name==MERGE_FINISHED_FILE_NAME_SUFFIX {
+            if let Err(e)=fs::rename(entry.path(),dir_path.join(&file_name)) {
+                warn!("move merge file err: {}",e);
+                return Err(Errors::DataDirCorrupted);
+            }
+        }
+    }
+
+    if let Err(e)=fs::remove_dir_all(&merge_path) {
+        warn!("remove merge directory err: {}",e);
+    }
+    Ok(())
+}
+
+// lock database directory, exclusively for a writable engine and shared for read only ones,
+// lock file is created by writable engine only
+fn open_lock_file(dir_path:&Path,read_only:bool)->Result<Option<File>>{
+    let lock_path=dir_path.join(LOCK_FILE_NAME);
+    if read_only&&!lock_path.is_file() {
+        return Ok(None);
+    }
+    let file_lock=match OpenOptions::new()
+        .read(true)
+        .write(!read_only)
+        .create(!read_only)
+        .truncate(false)
+        .open(lock_path)
+    {
+        Ok(file)=>file,
+        Err(e)=>{
+            warn!("open lock file err: {}",e);
+            return Err(Errors::OpenFileError);
+        }
+    };
+    let lock_res=match read_only {
+        true=>FileExt::try_lock_shared(&file_lock),
+        false=>FileExt::try_lock_exclusive(&file_lock),
+    };
+    if lock_res.is_err() {
+        return Err(Errors::DatabaseInUse);
+    }
+    Ok(Some(file_lock))
+}
 
+// load all data files in database directory, sorted by file id
+fn load_data_files(dir_path:&PathBuf,use_mmap:bool)->Result<Vec<DataFile>>{
+    let dir=match fs::read_dir(dir_path) {
+        Ok(dir)=>dir,
+        Err(e)=>{
+            warn!("read database directory err: {}",e);
+            return Err(Errors::ReadDirError);
+        }
+    };
+
+    let mut file_ids=Vec::new();
+    for entry in dir.flatten() {
+        let file_name=entry.file_name();
+        let file_name=file_name.to_string_lossy();
+        if let Some(file_id)=file_name.strip_suffix(DATA_FILE_NAME_SUFFIX) {
+            match file_id.parse::<u64>() {
+                Ok(file_id)=>file_ids.push(file_id),
+                Err(_)=>return Err(Errors::DataDirCorrupted),
+            }
+        }
     }
+    file_ids.sort();
+
+    // mmap speeds up loading index, io type is reset after startup
+    let io_type=match use_mmap {
+        true=>IOType::MmapIO,
+        false=>IOType::StdIO,
+    };
+    let mut data_files=Vec::with_capacity(file_ids.len());
+    for file_id in file_ids.into_iter() {
+        data_files.push(DataFile::new(dir_path.clone(),file_id,io_type)?);
+    }
+    Ok(data_files)
 }
 
 #[cfg(test)]
-mod engine_tests {
+pub(crate) mod engine_tests {
+    use bytes::Bytes;
+    use crate::engine::Engine;
+    use crate::errors::Errors;
+    use crate::metrics::PrometheusMetrics;
+    use std::sync::Arc;
+    use crate::options::{CompressionType, Options, WriteBatchOptions};
+    use std::sync::atomic::Ordering;
 
     #[test]
     fn test_open_db() {
+        let options=create_options("open");
+        let engine=Engine::open(options.clone());
+        assert!(engine.is_ok());
+
+        // directory is locked by the first engine
+        let engine2=Engine::open(options.clone());
+        assert_eq!(engine2.err(),Some(Errors::DatabaseInUse));
 
+        drop(engine);
+        remove_db(&options);
     }
 
     #[test]
     fn test_close_db() {
-
+        let options=create_options("close");
+        let engine=Engine::open(options.clone()).unwrap();
+        engine.put(Bytes::from("key"),Bytes::from("value")).unwrap();
+        assert!(engine.close().is_ok());
+        drop(engine);
+
+        // reopen and load index from data files
+        let engine=Engine::open(options.clone()).unwrap();
+        assert_eq!(engine.get(Bytes::from("key")).unwrap(),Bytes::from("value"));
+        drop(engine);
+        remove_db(&options);
     }
 
     #[test]
-    fn test_get(){}
+    fn test_get(){
+        let options=create_options("get");
+        let engine=Engine::open(options.clone()).unwrap();
+        engine.put(Bytes::from("key"),Bytes::from("value")).unwrap();
+        assert_eq!(engine.get(Bytes::from("key")).unwrap(),Bytes::from("value"));
+        assert_eq!(engine.get(Bytes::from("not-exist")).err(),Some(Errors::KeyNotFound));
+        assert_eq!(engine.get(Bytes::new()).err(),Some(Errors::KeyIsEmpty));
+        drop(engine);
+        remove_db(&options);
+    }
 
     #[test]
-    fn test_put(){}
+    fn test_put(){
+        let options=create_options("put");
+        let engine=Engine::open(options.clone()).unwrap();
+        engine.put(Bytes::from("key"),Bytes::from("value-1")).unwrap();
+        engine.put(Bytes::from("key"),Bytes::from("value-2")).unwrap();
+        assert_eq!(engine.get(Bytes::from("key")).unwrap(),Bytes::from("value-2"));
+        assert_eq!(engine.put(Bytes::new(),Bytes::from("value")).err(),Some(Errors::KeyIsEmpty));
+        drop(engine);
+        remove_db(&options);
+    }
 
     #[test]
     fn test_remove(){
-
+        let options=create_options("remove");
+        let engine=Engine::open(options.clone()).unwrap();
+        engine.put(Bytes::from("key"),Bytes::from("value")).unwrap();
+        engine.remove(Bytes::from("key")).unwrap();
+        assert_eq!(engine.get(Bytes::from("key")).err(),Some(Errors::KeyNotFound));
+        drop(engine);
+
+        // deleted record is applied when loading index
+        let engine=Engine::open(options.clone()).unwrap();
+        assert_eq!(engine.get(Bytes::from("key")).err(),Some(Errors::KeyNotFound));
+        drop(engine);
+        remove_db(&options);
     }
 
     #[test]
     fn test_read_log_record_with_pos() {
-        
+        let options=create_options("read_with_pos");
+        let engine=Engine::open(options.clone()).unwrap();
+        engine.put(Bytes::from("key"),Bytes::from("value")).unwrap();
+        let pos=engine.index.get(b"key".to_vec()).unwrap();
+        assert_eq!(engine.get_value_on_offset(pos).unwrap(),Bytes::from("value"));
+        drop(engine);
+        remove_db(&options);
     }
 
     #[test]
     fn test_append_log_record() {
-        
+        let mut options=create_options("append");
+        options.data_file_size=64;
+        let engine=Engine::open(options.clone()).unwrap();
+        for i in 0..20 {
+            engine.put(Bytes::from(format!("key-{}",i)),Bytes::from(format!("value-{}",i))).unwrap();
+        }
+        // small data file size leads to file rotation
+        assert!(!engine.inactive_files.read().is_empty());
+        drop(engine);
+
+        let engine=Engine::open(options.clone()).unwrap();
+        for i in 0..20 {
+            assert_eq!(engine.get(Bytes::from(format!("key-{}",i))).unwrap(),Bytes::from(format!("value-{}",i)));
+        }
+        drop(engine);
+        remove_db(&options);
+    }
+
+    #[test]
+    fn test_compression() {
+        let mut options=create_options("compression");
+        let value=Bytes::from("{\"name\":\"lightkv\",\"type\":\"json\"}".repeat(64));
+        let engine=Engine::open(options.clone()).unwrap();
+        engine.put(Bytes::from("plain"),value.clone()).unwrap();
+        engine.put(Bytes::from("small"),Bytes::from("value")).unwrap();
+        drop(engine);
+
+        // mixed compressed and plain records stay readable
+        for compression in [CompressionType::Lz4,CompressionType::Zstd] {
+            if !compression.is_available() {
+                continue;
+            }
+            options.compression=compression;
+            options.compression_threshold=128;
+            let engine=Engine::open(options.clone()).unwrap();
+            let key=Bytes::from(format!("{:?}",compression));
+            engine.put(key.clone(),value.clone()).unwrap();
+            let pos=engine.index.get(key.to_vec()).unwrap();
+            assert!(pos.size<value.len() as u64);
+            drop(engine);
+        }
+
+        options.compression=CompressionType::None;
+        let engine=Engine::open(options.clone()).unwrap();
+        for compression in [CompressionType::None,CompressionType::Lz4,CompressionType::Zstd] {
+            if !compression.is_available() {
+                continue;
+            }
+            let key=match compression {
+                CompressionType::None=>Bytes::from("plain"),
+                _=>Bytes::from(format!("{:?}",compression)),
+            };
+            assert_eq!(engine.get(key).unwrap(),value);
+        }
+        assert_eq!(engine.get(Bytes::from("small")).unwrap(),Bytes::from("value"));
+        drop(engine);
+        remove_db(&options);
+    }
+
+    #[test]
+    fn test_blob_value() {
+        let mut options=create_options("blob_value");
+        options.blob_threshold=128;
+        let engine=Engine::open(options.clone()).unwrap();
+        let large_value=Bytes::from(vec![b'v';1024]);
+        engine.put(Bytes::from("large"),large_value.clone()).unwrap();
+        engine.put(Bytes::from("small"),Bytes::from("value")).unwrap();
+
+        // data file only keeps a small blob reference
+        let pos=engine.index.get(b"large".to_vec()).unwrap();
+        assert!(pos.size<128);
+        assert_eq!(engine.get(Bytes::from("large")).unwrap(),large_value);
+        let stats=engine.blob_stats();
+        assert_eq!(stats.len(),1);
+        assert_eq!(stats.values().next().unwrap().live_ratio(),1.0);
+
+        // overwritten blob is no longer live
+        engine.put(Bytes::from("large"),Bytes::from("inline")).unwrap();
+        assert_eq!(engine.blob_stats().values().next().unwrap().live_size,0);
+        engine.put(Bytes::from("large"),large_value.clone()).unwrap();
+        drop(engine);
+
+        let engine=Engine::open(options.clone()).unwrap();
+        assert_eq!(engine.get(Bytes::from("large")).unwrap(),large_value);
+        assert_eq!(engine.get(Bytes::from("small")).unwrap(),Bytes::from("value"));
+        let live_size:u64=engine.blob_stats().values().map(|stat|stat.live_size).sum();
+        assert!(live_size>1024);
+        drop(engine);
+        remove_db(&options);
+    }
+
+    #[test]
+    fn test_blob_synced_before_data_file() {
+        let mut options=create_options("blob_synced");
+        options.blob_threshold=128;
+        options.sync_bytes_write=4096;
+        let engine=Engine::open(options.clone()).unwrap();
+        engine.put(Bytes::from("large"),Bytes::from(vec![b'v';1024])).unwrap();
+        assert!(engine.blob_store.is_dirty());
+
+        // a later group without blobs syncs the blob it may reference
+        engine.put(Bytes::from("small"),Bytes::from(vec![b'v';100])).unwrap();
+        assert!(engine.blob_store.is_dirty());
+        for i in 0..40 {
+            engine.put(Bytes::from(format!("small-{}",i)),Bytes::from(vec![b'v';100])).unwrap();
+        }
+        assert!(!engine.blob_store.is_dirty());
+
+        engine.put(Bytes::from("large"),Bytes::from(vec![b'w';1024])).unwrap();
+        engine.sync().unwrap();
+        assert!(!engine.blob_store.is_dirty());
+        drop(engine);
+        remove_db(&options);
+    }
+
+    #[test]
+    fn test_stat() {
+        let options=create_options("stat");
+        let engine=Engine::open(options.clone()).unwrap();
+        let stat=engine.stat().unwrap();
+        assert_eq!(stat.key_counts,0);
+        assert_eq!(stat.file_counts,1);
+        assert_eq!(stat.reclaimable_size,0);
+
+        for i in 0..10 {
+            engine.put(Bytes::from(format!("key-{}",i)),Bytes::from("value")).unwrap();
+        }
+        let live_size=engine.stat().unwrap().disk_size;
+        // overwritten value and deleted value with its tombstone are reclaimable
+        let overwritten=engine.index.get(b"key-0".to_vec()).unwrap();
+        engine.put(Bytes::from("key-0"),Bytes::from("value-0")).unwrap();
+        let deleted=engine.index.get(b"key-1".to_vec()).unwrap();
+        engine.remove(Bytes::from("key-1")).unwrap();
+
+        let stat=engine.stat().unwrap();
+        assert_eq!(stat.key_counts,9);
+        assert!(stat.disk_size>live_size);
+        assert!(stat.reclaimable_size>overwritten.size+deleted.size);
+        assert_eq!(stat.active_file_id,0);
+        let reclaimable_size=stat.reclaimable_size;
+        drop(engine);
+
+        // rebuilt from data files when reopening
+        let engine=Engine::open(options.clone()).unwrap();
+        assert_eq!(engine.stat().unwrap().reclaimable_size,reclaimable_size);
+        engine.compact().unwrap();
+        drop(engine);
+
+        let engine=Engine::open(options.clone()).unwrap();
+        let stat=engine.stat().unwrap();
+        assert_eq!(stat.key_counts,9);
+        assert_eq!(stat.reclaimable_size,0);
+        drop(engine);
+        remove_db(&options);
+    }
+
+    #[test]
+    fn test_metrics() {
+        let mut options=create_options("metrics");
+        options.data_file_size=256;
+        let metrics=Arc::new(PrometheusMetrics::new());
+        options.metrics=metrics.clone();
+        let engine=Engine::open(options.clone()).unwrap();
+
+        for i in 0..20 {
+            engine.put(Bytes::from(format!("key-{}",i)),Bytes::from("value")).unwrap();
+        }
+        engine.get(Bytes::from("key-0")).unwrap();
+        for i in 0..5 {
+            engine.remove(Bytes::from(format!("key-{}",i))).unwrap();
+        }
+        engine.sync().unwrap();
+        engine.compact().unwrap();
+
+        let text=metrics.render();
+        assert!(text.contains("lightkv_put_seconds_count 20\n"));
+        assert!(text.contains("lightkv_get_seconds_count 1\n"));
+        assert!(text.contains("lightkv_remove_seconds_count 5\n"));
+        assert!(text.contains("lightkv_compact_seconds_count 1\n"));
+        assert!(text.contains("lightkv_fsync_seconds_count"));
+        assert!(text.contains("lightkv_file_rotations_total"));
+        assert!(text.contains("lightkv_bytes_written_total"));
+        assert!(text.contains("lightkv_compact_reclaimed_bytes_total"));
+        drop(engine);
+        remove_db(&options);
+    }
+
+    #[test]
+    fn test_disk_full() {
+        let mut options=create_options("disk_full");
+        options.data_file_size=256;
+        options.min_free_space=u64::MAX;
+        let engine=Engine::open(options.clone()).unwrap();
+
+        // no space to create the next data file
+        let mut res=Ok(());
+        for i in 0..20 {
+            res=engine.put(Bytes::from(format!("key-{}",i)),Bytes::from("value"));
+            if res.is_err() {
+                break;
+            }
+        }
+        assert_eq!(res.err(),Some(Errors::DiskFull));
+        assert!(engine.stat().unwrap().disk_full);
+        assert_eq!(engine.put(Bytes::from("key"),Bytes::from("value")).err(),Some(Errors::DiskFull));
+        assert_eq!(engine.compact().err(),Some(Errors::DiskFull));
+        // reads still work
+        assert_eq!(engine.get(Bytes::from("key-0")).unwrap(),Bytes::from("value"));
+        drop(engine);
+
+        // writes resume in a new data file once space is available
+        options.min_free_space=0;
+        let engine=Engine::open(options.clone()).unwrap();
+        engine.disk_full.store(true,Ordering::SeqCst);
+        assert!(!engine.stat().unwrap().disk_full);
+        let active_file_id=engine.stat().unwrap().active_file_id;
+        engine.put(Bytes::from("key"),Bytes::from("value")).unwrap();
+        assert_eq!(engine.stat().unwrap().active_file_id,active_file_id+1);
+        assert!(!engine.disk_full.load(Ordering::SeqCst));
+        drop(engine);
+
+        let engine=Engine::open(options.clone()).unwrap();
+        assert_eq!(engine.get(Bytes::from("key")).unwrap(),Bytes::from("value"));
+        assert_eq!(engine.get(Bytes::from("key-0")).unwrap(),Bytes::from("value"));
+        drop(engine);
+        remove_db(&options);
+    }
+
+    #[test]
+    fn test_read_only() {
+        let mut options=create_options("read_only");
+        options.read_only=true;
+        // missing directory is not created
+        assert!(Engine::open(options.clone()).is_err());
+        assert!(!options.path.exists());
+
+        options.read_only=false;
+        options.data_file_size=256;
+        let engine=Engine::open(options.clone()).unwrap();
+        for i in 0..20 {
+            engine.put(Bytes::from(format!("key-{}",i)),Bytes::from(format!("value-{}",i))).unwrap();
+        }
+        engine.compact().unwrap();
+        engine.remove(Bytes::from("key-0")).unwrap();
+        drop(engine);
+        let snapshot=dir_snapshot(&options.path);
+
+        options.read_only=true;
+        let engine=Engine::open(options.clone()).unwrap();
+        // read only engines share the directory, writers are rejected
+        let engine2=Engine::open(options.clone()).unwrap();
+        let mut writable_options=options.clone();
+        writable_options.read_only=false;
+        assert_eq!(Engine::open(writable_options).err(),Some(Errors::DatabaseInUse));
+
+        assert_eq!(engine.get(Bytes::from("key-0")).err(),Some(Errors::KeyNotFound));
+        assert_eq!(engine2.get(Bytes::from("key-19")).unwrap(),Bytes::from("value-19"));
+        assert_eq!(engine.put(Bytes::from("key"),Bytes::from("value")).err(),Some(Errors::DatabaseReadOnly));
+        assert_eq!(engine.remove(Bytes::from("key-1")).err(),Some(Errors::DatabaseReadOnly));
+        assert_eq!(engine.compact().err(),Some(Errors::DatabaseReadOnly));
+        let batch=engine.new_write_batch(WriteBatchOptions::default()).unwrap();
+        batch.put(Bytes::from("key"),Bytes::from("value")).unwrap();
+        assert_eq!(batch.commit().err(),Some(Errors::DatabaseReadOnly));
+        drop(batch);
+        engine.flush().unwrap();
+        drop(engine2);
+        drop(engine);
+
+        // nothing in the directory is touched, pending merge is not applied
+        assert_eq!(dir_snapshot(&options.path),snapshot);
+        remove_db(&options);
+    }
+
+    fn dir_snapshot(path:&std::path::Path)->Vec<(std::path::PathBuf,u64,std::time::SystemTime)>{
+        let mut files=Vec::new();
+        let mut dirs=vec![path.to_path_buf(),crate::engine::get_merge_path(path)];
+        while let Some(dir)=dirs.pop() {
+            for entry in std::fs::read_dir(&dir).into_iter().flatten().flatten() {
+                let metadata=entry.metadata().unwrap();
+                files.push((entry.path(),metadata.len(),metadata.modified().unwrap()));
+            }
+        }
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_sync_policy() {
+        let mut options=create_options("sync_policy");
+        options.sync_write=true;
+        options.sync_interval_ms=10;
+        assert_eq!(Engine::open(options.clone()).err(),Some(Errors::SyncPolicyConflict));
+
+        // byte threshold and time interval compose
+        options.sync_write=false;
+        options.sync_bytes_write=1024*1024;
+        let engine=Engine::open(options.clone()).unwrap();
+        engine.put(Bytes::from("key"),Bytes::from("value")).unwrap();
+        assert!(engine.written_bytes.load(Ordering::SeqCst)>0);
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        assert_eq!(engine.written_bytes.load(Ordering::SeqCst),0);
+
+        engine.put(Bytes::from("key"),Bytes::from("value-1")).unwrap();
+        engine.flush().unwrap();
+        assert_eq!(engine.written_bytes.load(Ordering::SeqCst),0);
+        engine.close().unwrap();
+        drop(engine);
+
+        let engine=Engine::open(options.clone()).unwrap();
+        assert_eq!(engine.get(Bytes::from("key")).unwrap(),Bytes::from("value-1"));
+        drop(engine);
+        remove_db(&options);
+    }
+
+    #[test]
+    fn test_concurrent_sync_put() {
+        let mut options=create_options("concurrent_sync_put");
+        options.sync_write=true;
+        options.data_file_size=4*1024;
+        let engine=std::sync::Arc::new(Engine::open(options.clone()).unwrap());
+
+        let handles:Vec<_>=(0..16).map(|w|{
+            let engine=engine.clone();
+            std::thread::spawn(move||{
+                for i in 0..50 {
+                    engine.put(Bytes::from(format!("key-{}-{}",w,i)),Bytes::from(format!("value-{}-{}",w,i))).unwrap();
+                }
+            })
+        }).collect();
+        for handle in handles.into_iter() {
+            handle.join().unwrap();
+        }
+        for w in 0..16 {
+            for i in 0..50 {
+                assert_eq!(engine.get(Bytes::from(format!("key-{}-{}",w,i))).unwrap(),Bytes::from(format!("value-{}-{}",w,i)));
+            }
+        }
+        drop(engine);
+
+        // grouped writes are laid out correctly across rotated files
+        let engine=Engine::open(options.clone()).unwrap();
+        assert_eq!(engine.list_keys().unwrap().len(),16*50);
+        drop(engine);
+        remove_db(&options);
+    }
+
+    #[test]
+    fn test_compare_and_swap() {
+        let mut options=create_options("compare_and_swap");
+        options.data_file_size=4*1024;
+        options.blob_threshold=128;
+        let engine=Engine::open(options.clone()).unwrap();
+        assert!(engine.put_if_absent(Bytes::from("lock"),Bytes::from("owner-1")).unwrap());
+        assert!(!engine.put_if_absent(Bytes::from("lock"),Bytes::from("owner-2")).unwrap());
+        assert_eq!(engine.get(Bytes::from("lock")).unwrap(),Bytes::from("owner-1"));
+
+        assert!(!engine.compare_and_swap(Bytes::from("lock"),Some(Bytes::from("owner-2")),None).unwrap());
+        assert!(engine.compare_and_swap(Bytes::from("lock"),Some(Bytes::from("owner-1")),Some(Bytes::from("owner-2"))).unwrap());
+        assert!(engine.compare_and_swap(Bytes::from("lock"),Some(Bytes::from("owner-2")),None).unwrap());
+        assert_eq!(engine.get(Bytes::from("lock")),Err(Errors::KeyNotFound));
+        assert!(engine.compare_and_swap(Bytes::from("lock"),None,None).unwrap());
+        assert!(!engine.compare_and_swap(Bytes::from("lock"),Some(Bytes::new()),None).unwrap());
+        let large_value=Bytes::from(vec![b'v';1024]);
+        assert!(engine.compare_and_swap(Bytes::from("large"),None,Some(large_value.clone())).unwrap());
+        assert_eq!(engine.compare_and_swap(Bytes::new(),None,None),Err(Errors::KeyIsEmpty));
+
+        // concurrent read-modify-write loops never lose an update
+        engine.put(Bytes::from("counter"),Bytes::from("0")).unwrap();
+        let engine=Arc::new(engine);
+        let handles:Vec<_>=(0..8).map(|_|{
+            let engine=engine.clone();
+            std::thread::spawn(move||{
+                for _ in 0..50 {
+                    loop {
+                        let current=engine.get(Bytes::from("counter")).unwrap();
+                        let next=std::str::from_utf8(&current).unwrap().parse::<u64>().unwrap()+1;
+                        if engine.compare_and_swap(Bytes::from("counter"),Some(current),Some(Bytes::from(next.to_string()))).unwrap() {
+                            break;
+                        }
+                    }
+                }
+            })
+        }).collect();
+        for handle in handles.into_iter() {
+            handle.join().unwrap();
+        }
+        assert_eq!(engine.get(Bytes::from("counter")).unwrap(),Bytes::from("400"));
+        drop(engine);
+
+        let engine=Engine::open(options.clone()).unwrap();
+        assert_eq!(engine.get(Bytes::from("counter")).unwrap(),Bytes::from("400"));
+        assert_eq!(engine.get(Bytes::from("large")).unwrap(),large_value);
+        assert_eq!(engine.get(Bytes::from("lock")),Err(Errors::KeyNotFound));
+        drop(engine);
+        remove_db(&options);
+    }
+
+    #[test]
+    fn test_compare_and_swap_with_put() {
+        let options=create_options("compare_and_swap_with_put");
+        let engine=Arc::new(Engine::open(options.clone()).unwrap());
+        engine.put(Bytes::from("key"),Bytes::from("put-0")).unwrap();
+        let writer={
+            let engine=engine.clone();
+            std::thread::spawn(move||{
+                for i in 1..500 {
+                    engine.put(Bytes::from("key"),Bytes::from(format!("put-{}",i))).unwrap();
+                }
+            })
+        };
+        let mut swapped=0;
+        for i in 0..500 {
+            let current=engine.get(Bytes::from("key")).unwrap();
+            if engine.compare_and_swap(Bytes::from("key"),Some(current),Some(Bytes::from(format!("cas-{}",i)))).unwrap() {
+                swapped+=1;
+            }
+        }
+        writer.join().unwrap();
+        assert!(swapped>0);
+
+        // memory index follows the order of records on disk
+        let value=engine.get(Bytes::from("key")).unwrap();
+        drop(engine);
+        let engine=Engine::open(options.clone()).unwrap();
+        assert_eq!(engine.get(Bytes::from("key")).unwrap(),value);
+        drop(engine);
+        remove_db(&options);
+    }
+
+    pub(crate) fn create_options(name:&str)->Options{
+        let options=Options{
+            path: std::env::temp_dir().join(format!("lightkv-{}",name)),
+            ..Default::default()
+        };
+        let _=std::fs::remove_dir_all(&options.path);
+        options
     }
 
-    fn create_db() {}
+    pub(crate) fn remove_db(options:&Options){
+        std::fs::remove_dir_all(&options.path).unwrap();
+    }
 }
 
 #[cfg(test)]
 mod transaction_tests{
+    use bytes::Bytes;
+    use crate::engine::Engine;
+    use crate::engine::engine_tests::{create_options, remove_db};
+    use crate::errors::Errors;
+    use crate::options::WriteBatchOptions;
+
     #[test]
     fn test_commit() {
-        
+        let options=create_options("commit");
+        let engine=Engine::open(options.clone()).unwrap();
+        engine.put(Bytes::from("key-0"),Bytes::from("value")).unwrap();
+
+        let batch=engine.new_write_batch(WriteBatchOptions::default()).unwrap();
+        batch.put(Bytes::from("key-1"),Bytes::from("value-1")).unwrap();
+        batch.put(Bytes::from("key-2"),Bytes::from("value-2")).unwrap();
+        batch.delete(Bytes::from("key-0")).unwrap();
+
+        // uncommitted writes are invisible
+        assert_eq!(engine.get(Bytes::from("key-1")).err(),Some(Errors::KeyNotFound));
+        batch.commit().unwrap();
+        assert_eq!(engine.get(Bytes::from("key-1")).unwrap(),Bytes::from("value-1"));
+        assert_eq!(engine.get(Bytes::from("key-0")).err(),Some(Errors::KeyNotFound));
+        drop(batch);
+        drop(engine);
+
+        let engine=Engine::open(options.clone()).unwrap();
+        assert_eq!(engine.get(Bytes::from("key-2")).unwrap(),Bytes::from("value-2"));
+        assert_eq!(engine.get(Bytes::from("key-0")).err(),Some(Errors::KeyNotFound));
+        assert_eq!(engine.txn_id.load(std::sync::atomic::Ordering::SeqCst),1);
+        drop(engine);
+        remove_db(&options);
+    }
+}
+
+#[cfg(test)]
+mod backup_tests{
+    use bytes::Bytes;
+    use std::os::unix::fs::MetadataExt;
+    use crate::data::data_file::get_data_file_name;
+    use crate::engine::Engine;
+    use crate::engine::engine_tests::{create_options, remove_db};
+    use crate::errors::Errors;
+    use crate::options::WriteBatchOptions;
+
+    #[test]
+    fn test_backup() {
+        let mut options=create_options("backup");
+        options.data_file_size=256;
+        options.blob_threshold=128;
+        let backup_options=create_options("backup-dest");
+
+        let engine=Engine::open(options.clone()).unwrap();
+        for i in 0..20 {
+            engine.put(Bytes::from(format!("key-{}",i)),Bytes::from(format!("value-{}",i))).unwrap();
+        }
+        engine.put(Bytes::from("large"),Bytes::from(vec![b'v';1024])).unwrap();
+        engine.remove(Bytes::from("key-0")).unwrap();
+        engine.backup(&backup_options.path).unwrap();
+        let old_file=get_data_file_name(backup_options.path.clone(),0);
+        let old_inode=std::fs::metadata(&old_file).unwrap().ino();
+
+        // writes after backup are not included
+        engine.put(Bytes::from("key-1"),Bytes::from("new-value")).unwrap();
+        let backup_engine=Engine::open(backup_options.clone()).unwrap();
+        assert_eq!(backup_engine.get(Bytes::from("key-0")).err(),Some(Errors::KeyNotFound));
+        assert_eq!(backup_engine.get(Bytes::from("key-1")).unwrap(),Bytes::from("value-1"));
+        assert_eq!(backup_engine.get(Bytes::from("large")).unwrap(),Bytes::from(vec![b'v';1024]));
+        assert_eq!(backup_engine.list_keys().unwrap().len(),20);
+        drop(backup_engine);
+
+        // incremental backup keeps unchanged old files
+        let batch=engine.new_write_batch(WriteBatchOptions::default()).unwrap();
+        for i in 20..40 {
+            batch.put(Bytes::from(format!("key-{}",i)),Bytes::from(format!("value-{}",i))).unwrap();
+        }
+        batch.commit().unwrap();
+        drop(batch);
+        engine.compact().unwrap();
+        engine.backup(&backup_options.path).unwrap();
+        assert_eq!(std::fs::metadata(&old_file).unwrap().ino(),old_inode);
+        drop(engine);
+
+        let backup_engine=Engine::open(backup_options.clone()).unwrap();
+        assert_eq!(backup_engine.get(Bytes::from("key-1")).unwrap(),Bytes::from("new-value"));
+        assert_eq!(backup_engine.get(Bytes::from("key-39")).unwrap(),Bytes::from("value-39"));
+        assert_eq!(backup_engine.list_keys().unwrap().len(),40);
+        assert_eq!(backup_engine.txn_id.load(std::sync::atomic::Ordering::SeqCst),1);
+        drop(backup_engine);
+
+        // files replaced by merge are copied again
+        let engine=Engine::open(options.clone()).unwrap();
+        engine.backup(&backup_options.path).unwrap();
+        drop(engine);
+        assert_ne!(std::fs::metadata(&old_file).unwrap().ino(),old_inode);
+        let backup_engine=Engine::open(backup_options.clone()).unwrap();
+        assert_eq!(backup_engine.get(Bytes::from("large")).unwrap(),Bytes::from(vec![b'v';1024]));
+        assert_eq!(backup_engine.list_keys().unwrap().len(),40);
+        drop(backup_engine);
+
+        remove_db(&options);
+        remove_db(&backup_options);
+    }
+
+    #[test]
+    fn test_checkpoint_restore() {
+        let mut options=create_options("checkpoint");
+        options.data_file_size=256;
+        let checkpoint_options=create_options("checkpoint-dest");
+        let restore_options=create_options("checkpoint-restore");
+
+        let engine=Engine::open(options.clone()).unwrap();
+        for i in 0..20 {
+            engine.put(Bytes::from(format!("key-{}",i)),Bytes::from(format!("value-{}",i))).unwrap();
+        }
+        engine.checkpoint(&checkpoint_options.path).unwrap();
+        engine.put(Bytes::from("key-20"),Bytes::from("value-20")).unwrap();
+        drop(engine);
+
+        // restored directory only holds checkpoint data
+        Engine::restore(&checkpoint_options.path,&restore_options.path).unwrap();
+        let restore_engine=Engine::open(restore_options.clone()).unwrap();
+        assert_eq!(restore_engine.get(Bytes::from("key-19")).unwrap(),Bytes::from("value-19"));
+        assert_eq!(restore_engine.get(Bytes::from("key-20")).err(),Some(Errors::KeyNotFound));
+        drop(restore_engine);
+        assert_eq!(Engine::restore(&checkpoint_options.path,&restore_options.path).err(),Some(Errors::RestoreDirNotEmpty));
+        remove_db(&restore_options);
+
+        // truncated data file is refused
+        let data_file=get_data_file_name(checkpoint_options.path.clone(),0);
+        std::fs::OpenOptions::new().write(true).open(&data_file).unwrap().set_len(10).unwrap();
+        assert_eq!(Engine::open(checkpoint_options.clone()).err(),Some(Errors::ManifestMismatch));
+        assert_eq!(Engine::restore(&checkpoint_options.path,&restore_options.path).err(),Some(Errors::ManifestMismatch));
+        assert!(!restore_options.path.exists());
+
+        remove_db(&options);
+        remove_db(&checkpoint_options);
     }
 }
 
 #[cfg(test)]
 mod iterator_tests{
+    use bytes::Bytes;
+    use crate::engine::Engine;
+    use crate::engine::engine_tests::{create_options, remove_db};
+    use crate::options::IteratorOptions;
+
     #[test]
     fn test_list_keys() {
-        
+        let options=create_options("list_keys");
+        let engine=Engine::open(options.clone()).unwrap();
+        assert!(engine.list_keys().unwrap().is_empty());
+        put_keys(&engine);
+        assert_eq!(engine.list_keys().unwrap(),vec![Bytes::from("aa"),Bytes::from("ab"),Bytes::from("bb")]);
+        drop(engine);
+        remove_db(&options);
     }
 
     #[test]
     fn test_seek() {
-        
+        let options=create_options("seek");
+        let engine=Engine::open(options.clone()).unwrap();
+        put_keys(&engine);
+        let iter=engine.iter(IteratorOptions::default());
+        iter.seek(b"b".to_vec());
+        assert_eq!(iter.next().unwrap().0,Bytes::from("bb"));
+        assert!(iter.next().is_none());
+        drop(iter);
+        drop(engine);
+        remove_db(&options);
     }
 
     #[test]
     fn test_rewind() {
-        
+        let options=create_options("rewind");
+        let engine=Engine::open(options.clone()).unwrap();
+        put_keys(&engine);
+        let iter=engine.iter(IteratorOptions{ prefix: b"a".to_vec(), reverse: true });
+        assert_eq!(iter.next().unwrap().0,Bytes::from("ab"));
+        iter.rewind();
+        assert_eq!(iter.next().unwrap().0,Bytes::from("ab"));
+        assert_eq!(iter.next().unwrap().0,Bytes::from("aa"));
+        assert!(iter.next().is_none());
+        drop(iter);
+        drop(engine);
+        remove_db(&options);
     }
 
     #[test]
     fn test_next() {
-        
+        let options=create_options("next");
+        let engine=Engine::open(options.clone()).unwrap();
+        put_keys(&engine);
+        let iter=engine.iter(IteratorOptions::default());
+        assert_eq!(iter.next(),Some((Bytes::from("aa"),Bytes::from("value-aa"))));
+        assert_eq!(iter.next(),Some((Bytes::from("ab"),Bytes::from("value-ab"))));
+        assert_eq!(iter.next(),Some((Bytes::from("bb"),Bytes::from("value-bb"))));
+        assert!(iter.next().is_none());
+        drop(iter);
+        drop(engine);
+        remove_db(&options);
+    }
+
+    #[test]
+    fn test_next_lost_blob() {
+        let mut options=create_options("next_lost_blob");
+        options.blob_threshold=128;
+        let engine=Engine::open(options.clone()).unwrap();
+        engine.put(Bytes::from("a"),Bytes::from("value-a")).unwrap();
+        engine.put(Bytes::from("b"),Bytes::from(vec![b'v';1024])).unwrap();
+        engine.put(Bytes::from("c"),Bytes::from("value-c")).unwrap();
+        drop(engine);
+
+        // blob file lost its data while data file kept the reference
+        for entry in std::fs::read_dir(&options.path).unwrap() {
+            let path=entry.unwrap().path();
+            if path.extension().is_some_and(|extension|extension=="blob") {
+                std::fs::OpenOptions::new().write(true).open(path).unwrap().set_len(0).unwrap();
+            }
+        }
+
+        let engine=Engine::open(options.clone()).unwrap();
+        let iter=engine.iter(IteratorOptions::default());
+        assert_eq!(iter.next(),Some((Bytes::from("a"),Bytes::from("value-a"))));
+        assert_eq!(iter.next(),Some((Bytes::from("c"),Bytes::from("value-c"))));
+        assert!(iter.next().is_none());
+        drop(iter);
+        drop(engine);
+        remove_db(&options);
     }
 
     #[test]
     fn test_fold() {
-        
+        let options=create_options("fold");
+        let engine=Engine::open(options.clone()).unwrap();
+        put_keys(&engine);
+        let count=std::sync::atomic::AtomicUsize::new(0);
+        engine.fold(|_,_|{
+            count.fetch_add(1,std::sync::atomic::Ordering::SeqCst);
+            true
+        }).unwrap();
+        assert_eq!(count.into_inner(),3);
+        drop(engine);
+        remove_db(&options);
+    }
+
+    fn put_keys(engine:&Engine){
+        for key in ["bb","aa","ab"] {
+            engine.put(Bytes::from(key),Bytes::from(format!("value-{}",key))).unwrap();
+        }
     }
 }
 
 #[cfg(test)]
 mod compaction_tests{
+    use bytes::Bytes;
+    use crate::data::data_file::{get_blob_file_name, DATA_FILE_NAME_SUFFIX, MERGE_FINISHED_FILE_NAME_SUFFIX};
+    use crate::engine::{get_merge_path, Engine};
+    use crate::engine::engine_tests::{create_options, remove_db};
+    use crate::errors::Errors;
+    use crate::options::WriteBatchOptions;
+
+    #[test]
+    fn test_auto_compact() {
+        let mut options=create_options("auto_compact");
+        options.data_file_size=1024;
+        options.compact_ratio=0.5;
+        options.compact_interval_ms=10;
+        options.compact_bytes_per_sec=64*1024;
+        let engine=std::sync::Arc::new(Engine::open(options.clone()).unwrap());
+        engine.start_auto_compact();
+        for i in 0..100 {
+            engine.put(Bytes::from(format!("key-{}",i%10)),Bytes::from(format!("value-{}",i))).unwrap();
+        }
+
+        // overwritten old files trigger a merge in background
+        let merge_fin_file=get_merge_path(&options.path).join(MERGE_FINISHED_FILE_NAME_SUFFIX);
+        for _ in 0..200 {
+            if merge_fin_file.is_file() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(merge_fin_file.is_file());
+
+        // old files are kept until reopen, newer garbage does not trigger merging them again
+        let data_files=count_data_files(&options.path);
+        for i in 0..100 {
+            engine.put(Bytes::from(format!("key-{}",i%10)),Bytes::from(format!("value-{}",i))).unwrap();
+        }
+        assert!(!engine.need_compact());
+        let data_files_before_reopen=count_data_files(&options.path);
+        assert!(data_files_before_reopen>data_files);
+        drop(engine);
+
+        let engine=Engine::open(options.clone()).unwrap();
+        assert!(count_data_files(&options.path)<data_files_before_reopen);
+        for i in 90..100 {
+            assert_eq!(engine.get(Bytes::from(format!("key-{}",i%10))).unwrap(),Bytes::from(format!("value-{}",i)));
+        }
+        assert!(engine.need_compact());
+        drop(engine);
+
+        options.compact_ratio=1.5;
+        assert_eq!(Engine::open(options.clone()).err(),Some(Errors::CompactRatioError));
+        remove_db(&options);
+    }
+
+    fn count_data_files(path:&std::path::Path)->usize{
+        std::fs::read_dir(path).unwrap()
+            .filter(|entry|entry.as_ref().unwrap().file_name().to_string_lossy().ends_with(DATA_FILE_NAME_SUFFIX))
+            .count()
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut options=create_options("compact");
+        options.data_file_size=1024;
+        let engine=Engine::open(options.clone()).unwrap();
+        for i in 0..200 {
+            engine.put(Bytes::from(format!("key-{}",i)),Bytes::from(format!("value-{}",i))).unwrap();
+        }
+        for i in 0..100 {
+            engine.remove(Bytes::from(format!("key-{}",i))).unwrap();
+        }
+        let batch=engine.new_write_batch(WriteBatchOptions::default()).unwrap();
+        batch.put(Bytes::from("key-0"),Bytes::from("batch-value")).unwrap();
+        batch.commit().unwrap();
+        drop(batch);
+
+        engine.compact().unwrap();
+        // writes after compaction go into newer data files
+        engine.put(Bytes::from("key-1"),Bytes::from("new-value")).unwrap();
+        drop(engine);
+
+        // merged files are replaced when reopening
+        let engine=Engine::open(options.clone()).unwrap();
+        assert!(!get_merge_path(&options.path).exists());
+        assert_eq!(engine.get(Bytes::from("key-0")).unwrap(),Bytes::from("batch-value"));
+        assert_eq!(engine.get(Bytes::from("key-1")).unwrap(),Bytes::from("new-value"));
+        assert_eq!(engine.get(Bytes::from("key-2")).err(),Some(Errors::KeyNotFound));
+        for i in 100..200 {
+            assert_eq!(engine.get(Bytes::from(format!("key-{}",i))).unwrap(),Bytes::from(format!("value-{}",i)));
+        }
+        assert_eq!(engine.list_keys().unwrap().len(),102);
+        assert_eq!(engine.txn_id.load(std::sync::atomic::Ordering::SeqCst),1);
+        drop(engine);
+        remove_db(&options);
+    }
+
+    #[test]
+    fn test_compact_blob_gc() {
+        let mut options=create_options("compact_blob_gc");
+        options.blob_threshold=128;
+        options.blob_file_size=8*1024;
+        options.blob_gc_ratio=0.6;
+        let engine=Engine::open(options.clone()).unwrap();
+        for i in 0..20 {
+            engine.put(Bytes::from(format!("key-{}",i)),Bytes::from(vec![i as u8;1024])).unwrap();
+        }
+        // leave the first blob files mostly dead
+        for i in 0..10 {
+            engine.remove(Bytes::from(format!("key-{}",i))).unwrap();
+        }
+        let batch=engine.new_write_batch(WriteBatchOptions::default()).unwrap();
+        batch.put(Bytes::from("key-0"),Bytes::from(vec![b'b';1024])).unwrap();
+        batch.commit().unwrap();
+        drop(batch);
+
+        let gc_file_ids:Vec<u64>=engine.blob_stats().into_iter()
+            .filter(|(_,stat)|stat.live_ratio()<options.blob_gc_ratio)
+            .map(|(file_id,_)|file_id)
+            .collect();
+        assert_eq!(gc_file_ids.len(),2);
+        engine.compact().unwrap();
+        // values stay readable before merge is loaded
+        assert_eq!(engine.get(Bytes::from("key-15")).unwrap(),Bytes::from(vec![15u8;1024]));
+        drop(engine);
+
+        let engine=Engine::open(options.clone()).unwrap();
+        for file_id in gc_file_ids.iter() {
+            assert!(!get_blob_file_name(options.path.clone(),*file_id).exists());
+        }
+        for stat in engine.blob_stats().values() {
+            assert!(stat.live_size>0);
+        }
+        assert_eq!(engine.get(Bytes::from("key-0")).unwrap(),Bytes::from(vec![b'b';1024]));
+        assert_eq!(engine.get(Bytes::from("key-1")).err(),Some(Errors::KeyNotFound));
+        for i in 10..20 {
+            assert_eq!(engine.get(Bytes::from(format!("key-{}",i))).unwrap(),Bytes::from(vec![i as u8;1024]));
+        }
+        drop(engine);
+        remove_db(&options);
+    }
+
+    #[test]
+    fn test_compact_empty() {
+        let options=create_options("compact_empty");
+        let engine=Engine::open(options.clone()).unwrap();
+        assert!(engine.compact().is_ok());
+        drop(engine);
+
+        let engine=Engine::open(options.clone()).unwrap();
+        assert!(engine.list_keys().unwrap().is_empty());
+        drop(engine);
+        remove_db(&options);
+    }
+
+    #[test]
+    fn test_unfinished_compact() {
+        let options=create_options("unfinished_compact");
+        let engine=Engine::open(options.clone()).unwrap();
+        engine.put(Bytes::from("key"),Bytes::from("value")).unwrap();
+        drop(engine);
+
+        // merge directory without merge finished file is discarded
+        let merge_path=get_merge_path(&options.path);
+        std::fs::create_dir_all(&merge_path).unwrap();
+        std::fs::write(merge_path.join("000000000.data"),b"").unwrap();
+
+        let engine=Engine::open(options.clone()).unwrap();
+        assert!(!merge_path.exists());
+        assert_eq!(engine.get(Bytes::from("key")).unwrap(),Bytes::from("value"));
+        drop(engine);
+        remove_db(&options);
+    }
+}
+
+#[cfg(test)]
+mod merge_tests{
+    use bytes::Bytes;
+    use crate::engine::Engine;
+    use crate::engine::engine_tests::{create_options, remove_db};
+    use crate::errors::Errors;
+    use crate::merge::{MergeOperator, INCR_OPERATOR};
+    use std::sync::Arc;
+
+    // keeps the largest operand
+    struct MaxOperator;
+
+    impl MergeOperator for MaxOperator {
+        fn name(&self)->&str {
+            "max"
+        }
+
+        fn merge(&self,_key:&[u8],existing:Option<&[u8]>,operand:&[u8])->Option<Vec<u8>> {
+            Some(existing.filter(|existing|*existing>operand).unwrap_or(operand).to_vec())
+        }
+    }
+
+    #[test]
+    fn test_incr_by() {
+        let options=create_options("incr_by");
+        let engine=Engine::open(options.clone()).unwrap();
+        assert_eq!(engine.incr_by(Bytes::from("counter"),5).unwrap(),5);
+        assert_eq!(engine.incr_by(Bytes::from("counter"),-7).unwrap(),-2);
+        assert_eq!(engine.get(Bytes::from("counter")).unwrap(),Bytes::from("-2"));
+
+        // operands apply on top of a plain value
+        engine.put(Bytes::from("counter"),Bytes::from("10")).unwrap();
+        assert_eq!(engine.incr_by(Bytes::from("counter"),1).unwrap(),11);
+        engine.put(Bytes::from("text"),Bytes::from("abc")).unwrap();
+        assert_eq!(engine.incr_by(Bytes::from("text"),1),Err(Errors::NotInteger));
+        engine.put(Bytes::from("max"),Bytes::from(i64::MAX.to_string())).unwrap();
+        assert_eq!(engine.incr_by(Bytes::from("max"),1),Err(Errors::IntegerOverflow));
+        assert_eq!(engine.get(Bytes::from("max")).unwrap(),Bytes::from(i64::MAX.to_string()));
+
+        assert_eq!(engine.append(Bytes::from("text"),Bytes::from("de")).unwrap(),5);
+        assert_eq!(engine.append(Bytes::from("new"),Bytes::from("a")).unwrap(),1);
+        assert_eq!(engine.get(Bytes::from("text")).unwrap(),Bytes::from("abcde"));
+        // a removed key starts over
+        engine.remove(Bytes::from("counter")).unwrap();
+        assert_eq!(engine.get(Bytes::from("counter")),Err(Errors::KeyNotFound));
+        assert_eq!(engine.incr_by(Bytes::from("counter"),3).unwrap(),3);
+
+        // concurrent increments are never lost
+        let engine=Arc::new(engine);
+        let handles:Vec<_>=(0..4).map(|_|{
+            let engine=engine.clone();
+            std::thread::spawn(move||{
+                for _ in 0..100 {
+                    engine.incr_by(Bytes::from("shared"),1).unwrap();
+                }
+            })
+        }).collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(engine.get(Bytes::from("shared")).unwrap(),Bytes::from("400"));
+
+        // an increment fails only if it is never applied
+        engine.put(Bytes::from("limit"),Bytes::from((i64::MAX-10).to_string())).unwrap();
+        let handles:Vec<_>=(0..4).map(|_|{
+            let engine=engine.clone();
+            std::thread::spawn(move||{
+                (0..5).filter(|_|match engine.incr_by(Bytes::from("limit"),1) {
+                    Ok(_)=>true,
+                    Err(e)=>{
+                        assert_eq!(e,Errors::IntegerOverflow);
+                        false
+                    },
+                }).count()
+            })
+        }).collect();
+        let applied:usize=handles.into_iter().map(|handle|handle.join().unwrap()).sum();
+        assert_eq!(applied,10);
+        assert_eq!(engine.get(Bytes::from("limit")).unwrap(),Bytes::from(i64::MAX.to_string()));
+        drop(engine);
+
+        let engine=Engine::open(options.clone()).unwrap();
+        assert_eq!(engine.get(Bytes::from("counter")).unwrap(),Bytes::from("3"));
+        assert_eq!(engine.get(Bytes::from("shared")).unwrap(),Bytes::from("400"));
+        assert_eq!(engine.get(Bytes::from("text")).unwrap(),Bytes::from("abcde"));
+        drop(engine);
+        remove_db(&options);
+    }
+
+    #[test]
+    fn test_merge_operator() {
+        let mut options=create_options("merge_operator");
+        options.merge_operators=vec![Arc::new(MaxOperator)];
+        let engine=Engine::open(options.clone()).unwrap();
+        for operand in ["b","c","a"] {
+            engine.merge(Bytes::from("key"),"max",Bytes::from(operand)).unwrap();
+        }
+        assert_eq!(engine.get(Bytes::from("key")).unwrap(),Bytes::from("c"));
+        assert_eq!(engine.merge(Bytes::from("key"),"min",Bytes::from("a")),Err(Errors::MergeOperatorNotFound));
+        // a rejected operand keeps the value
+        engine.merge(Bytes::from("key"),INCR_OPERATOR,Bytes::from(1i64.to_be_bytes().to_vec())).unwrap();
+        assert_eq!(engine.get(Bytes::from("key")).unwrap(),Bytes::from("c"));
+        assert_eq!(engine.iter(Default::default()).next(),Some((Bytes::from("key"),Bytes::from("c"))));
+        drop(engine);
+
+        let engine=Engine::open(options.clone()).unwrap();
+        assert_eq!(engine.get(Bytes::from("key")).unwrap(),Bytes::from("c"));
+        drop(engine);
+
+        // operands can not be read without their operator
+        let mut options=options.clone();
+        options.merge_operators.clear();
+        let engine=Engine::open(options.clone()).unwrap();
+        assert_eq!(engine.get(Bytes::from("key")),Err(Errors::MergeOperatorNotFound));
+        drop(engine);
+
+        options.merge_operators=vec![Arc::new(MaxOperator),Arc::new(MaxOperator)];
+        assert_eq!(Engine::open(options.clone()).err(),Some(Errors::MergeOperatorConflict));
+        remove_db(&options);
+    }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_compact_merge_operands() {
+        let mut options=create_options("compact_merge_operands");
+        options.data_file_size=1024;
+        let engine=Engine::open(options.clone()).unwrap();
+        engine.put(Bytes::from("base"),Bytes::from("100")).unwrap();
+        for i in 0..100 {
+            engine.incr_by(Bytes::from("base"),1).unwrap();
+            engine.incr_by(Bytes::from(format!("counter-{}",i%10)),i).unwrap();
+        }
+        engine.append(Bytes::from("removed"),Bytes::from("a")).unwrap();
+        engine.remove(Bytes::from("removed")).unwrap();
+
+        engine.compact().unwrap();
+        // operands written after compaction apply to the folded value
+        engine.incr_by(Bytes::from("base"),1).unwrap();
+        assert_eq!(engine.get(Bytes::from("base")).unwrap(),Bytes::from("201"));
+        drop(engine);
+
+        let engine=Engine::open(options.clone()).unwrap();
+        assert_eq!(engine.get(Bytes::from("base")).unwrap(),Bytes::from("201"));
+        for i in 0..10 {
+            let expected:i64=(0..10).map(|j|j*10+i).sum();
+            assert_eq!(engine.get(Bytes::from(format!("counter-{}",i))).unwrap(),Bytes::from(expected.to_string()));
+        }
+        assert_eq!(engine.get(Bytes::from("removed")),Err(Errors::KeyNotFound));
+        assert_eq!(engine.list_keys().unwrap().len(),11);
+        // operands of merged files are folded, the one written after compaction is kept
+        let chain=engine.merge_chains.read().get(b"base".as_slice()).cloned().unwrap();
+        assert!(chain.base.is_some());
+        assert_eq!(chain.operands.len(),1);
+        assert!(engine.merge_chains.read().get(b"counter-0".as_slice()).is_none());
+        drop(engine);
+        remove_db(&options);
+    }
+}
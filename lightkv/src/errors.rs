@@ -1,7 +1,7 @@
 use std::result;
 use thiserror::Error;
 
-#[derive(Error, Debug,PartialEq)]
+#[derive(Error, Debug,PartialEq, Clone)]
 pub enum Errors {
     #[error("failed to read data file")]
     ReadFileError,
@@ -41,6 +41,179 @@ pub enum Errors {
 
     #[error("multiple compaction process")]
     ProcessCompactError,
+
+    #[error("failed to create database directory")]
+    CreateDirError,
+
+    #[error("failed to read database directory")]
+    ReadDirError,
+
+    #[error("database directory maybe corrupted")]
+    DataDirCorrupted,
+
+    #[error("database directory is used by another process")]
+    DatabaseInUse,
+
+    #[error("exceed max batch size")]
+    ExceedMaxBatchSize,
+
+    #[error("compression codec is not supported")]
+    UnsupportedCompression,
+
+    #[error("failed to compress value")]
+    CompressError,
+
+    #[error("failed to decompress value")]
+    DecompressError,
+
+    #[error("blob gc ratio must be between 0 and 1")]
+    BlobGcRatioError,
+
+    #[error("sync write can not be combined with other sync policies")]
+    SyncPolicyConflict,
+
+    #[error("compact ratio must be between 0 and 1 with a positive check interval")]
+    CompactRatioError,
+
+    #[error("no enough disk space, database is read only until space is freed")]
+    DiskFull,
+
+    #[error("database is opened in read only mode")]
+    DatabaseReadOnly,
+
+    #[error("failed to backup database")]
+    BackupError,
+
+    #[error("database files do not match checkpoint manifest")]
+    ManifestMismatch,
+
+    #[error("checkpoint format version is not supported")]
+    UnsupportedFormatVersion,
+
+    #[error("restore target directory is not empty")]
+    RestoreDirNotEmpty,
+
+    #[error("failed to initialize logger")]
+    InitLoggerError,
+
+    #[error("invalid resp protocol data")]
+    ProtocolError,
+
+    #[error("failed to load tls certificate")]
+    TlsConfigError,
+
+    #[error("failed to bind server address")]
+    BindAddressError,
+
+    #[error("operation against a key holding the wrong kind of value")]
+    WrongType,
+
+    #[error("invalid metadata of data type")]
+    InvalidMetadata,
+
+    #[error("merge operator not found")]
+    MergeOperatorNotFound,
+
+    #[error("merge operator names must be unique and not taken by built-in operators")]
+    MergeOperatorConflict,
+
+    #[error("invalid merge operand")]
+    InvalidMergeOperand,
+
+    #[error("value is not an integer or out of range")]
+    NotInteger,
+
+    #[error("increment or decrement would overflow")]
+    IntegerOverflow,
+
+    #[error("table is opened with types different from its metadata")]
+    TableTypeMismatch,
+
+    #[error("failed to decode key or value of table")]
+    DecodeTableValueError,
+
+    #[error("failed to encode key or value of table")]
+    EncodeTableValueError,
+
+    #[error("write is aborted by a failed group commit leader")]
+    WriteGroupAborted,
+
+    #[error("key starts with a byte reserved for internal keys")]
+    ReservedKey,
+}
+
+impl Errors {
+    // every error, keep it in sync with the enum
+    const ALL:&'static [Errors]=&[
+        Errors::ReadFileError,
+        Errors::WriteFileError,
+        Errors::SyncFileError,
+        Errors::OpenFileError,
+        Errors::ReadFileEOF,
+        Errors::CrcCheckError,
+        Errors::PathEmpty,
+        Errors::DataFileSizeError,
+        Errors::DataFileNotFound,
+        Errors::KeyNotFound,
+        Errors::KeyIsEmpty,
+        Errors::IndexUpdateError,
+        Errors::ProcessCompactError,
+        Errors::CreateDirError,
+        Errors::ReadDirError,
+        Errors::DataDirCorrupted,
+        Errors::DatabaseInUse,
+        Errors::ExceedMaxBatchSize,
+        Errors::UnsupportedCompression,
+        Errors::CompressError,
+        Errors::DecompressError,
+        Errors::BlobGcRatioError,
+        Errors::SyncPolicyConflict,
+        Errors::CompactRatioError,
+        Errors::DiskFull,
+        Errors::DatabaseReadOnly,
+        Errors::BackupError,
+        Errors::ManifestMismatch,
+        Errors::UnsupportedFormatVersion,
+        Errors::RestoreDirNotEmpty,
+        Errors::InitLoggerError,
+        Errors::ProtocolError,
+        Errors::TlsConfigError,
+        Errors::BindAddressError,
+        Errors::WrongType,
+        Errors::InvalidMetadata,
+        Errors::MergeOperatorNotFound,
+        Errors::MergeOperatorConflict,
+        Errors::InvalidMergeOperand,
+        Errors::NotInteger,
+        Errors::IntegerOverflow,
+        Errors::TableTypeMismatch,
+        Errors::DecodeTableValueError,
+        Errors::EncodeTableValueError,
+        Errors::WriteGroupAborted,
+        Errors::ReservedKey,
+    ];
+
+    /// Error with the given message, rebuilds engine errors reported by a server
+    pub fn from_message(message:&str)->Option<Errors>{
+        Errors::ALL.iter().find(|e|e.to_string()==message).cloned()
+    }
 }
 
 pub type Result<T> = result::Result<T, Errors>;
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::Errors;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_from_message() {
+        let mut messages=HashSet::new();
+        for e in Errors::ALL.iter() {
+            assert!(messages.insert(e.to_string()),"duplicated message of {:?}",e);
+            assert_eq!(Errors::from_message(&e.to_string()).as_ref(),Some(e));
+        }
+        assert_eq!(Errors::from_message("unknown"),None);
+    }
+}
+
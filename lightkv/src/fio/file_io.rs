@@ -1,9 +1,8 @@
 use crate::errors::{Errors, Result};
-use crate::fio::IOManager;
+use crate::fio::{write_all_or_truncate, IOManager};
 use log::error;
 use parking_lot::RwLock;
 use std::fs::{File, OpenOptions};
-use std::io::Write;
 use std::os::unix::fs::FileExt;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -17,7 +16,6 @@ impl FileIO {
         match OpenOptions::new()
             .create(true)
             .read(true)
-            .write(true)
             .append(true)
             .open(file_name)
         {
@@ -46,13 +44,8 @@ impl IOManager for FileIO {
 
     fn write(&self, buf: &[u8]) -> Result<usize> {
         let mut write_guard = self.fd.write();
-        match write_guard.write(buf) {
-            Ok(len) => Ok(len),
-            Err(e) => {
-                error!("write file err: {}", e);
-                Err(Errors::WriteFileError)
-            }
-        }
+        write_all_or_truncate(&mut write_guard, buf)?;
+        Ok(buf.len())
     }
 
     fn sync(&self) -> Result<()> {
@@ -72,6 +65,7 @@ impl IOManager for FileIO {
 
 #[cfg(test)]
 mod tests {
+    use crate::errors::Errors;
     use crate::fio::file_io::FileIO;
     use crate::fio::IOManager;
     use std::{fs, vec, assert_eq};
@@ -93,7 +87,7 @@ mod tests {
         for item in write_data.into_iter() {
             let write_size=file_io.write(item.0);
             assert!(write_size.is_ok());
-            assert_eq!(write_size.ok(), Some(item.0.len() as usize));
+            assert_eq!(write_size.ok(), Some(item.0.len()));
         }
 
         let mut read_data = [0u8; 6];
@@ -167,6 +161,14 @@ mod tests {
         assert!(remove_res);
     }
 
+    #[test]
+    fn test_write_disk_full() {
+        // every write to /dev/full fails with no space left
+        let file_io = FileIO::new(PathBuf::from("/dev/full")).unwrap();
+        assert_eq!(file_io.write(&[0u8; 4096]), Err(Errors::DiskFull));
+        assert_eq!(file_io.size(), 0);
+    }
+
     fn remove_tmp_file(path: &PathBuf) -> bool {
         let remove_res = fs::remove_file(path);
         remove_res.is_ok()
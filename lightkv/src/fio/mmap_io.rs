@@ -1,32 +1,36 @@
-use std::fs::OpenOptions;
+use std::fs::{File, OpenOptions};
 use std::path::PathBuf;
 use std::sync::Arc;
 use log::error;
 use memmap2::Mmap;
-use parking_lot::{RwLock};
+use parking_lot::{Mutex, RwLock};
 use crate::errors::Errors;
-use crate::fio::IOManager;
+use crate::fio::{write_all_or_truncate, IOManager};
 use crate::Result;
 
 #[derive(Debug)]
 pub struct MmapIO {
-    mmap:Arc<RwLock<Mmap>>
+    file_name:PathBuf,
+    mmap:Arc<RwLock<Mmap>>,
+    // opened on first write, so read only files are never opened for writing
+    writer:Mutex<Option<File>>,
 }
 
 impl MmapIO {
     pub fn new(file_name:PathBuf)->Result<Self>{
-        return match OpenOptions::new().
-            create(true).
+        // mapped files are only read, opening never creates or modifies them
+        match OpenOptions::new().
             read(true).
-            write(true).
-            open(file_name)
+            open(&file_name)
         {
             Ok(file) => {
                 let map = unsafe {
                     Mmap::map(&file).expect("map file error")
                 };
                 Ok(Self {
+                    file_name,
                     mmap: Arc::new(RwLock::new(map)),
+                    writer: Mutex::new(None),
                 })
             },
             Err(e) => {
@@ -50,12 +54,35 @@ impl IOManager for MmapIO {
         Ok(buf.len())
     }
 
+    // append to the file and map it again, so written data is readable right away
     fn write(&self, buf: &[u8]) -> Result<usize> {
-        unimplemented!()
+        let mut write_guard=self.mmap.write();
+        let mut writer=self.writer.lock();
+        if writer.is_none() {
+            let file=OpenOptions::new().read(true).append(true).open(&self.file_name).map_err(|e|{
+                error!("open mmap file for write err: {}",e);
+                Errors::OpenFileError
+            })?;
+            *writer=Some(file);
+        }
+        let file=writer.as_mut().unwrap();
+        write_all_or_truncate(file,buf)?;
+        *write_guard=unsafe { Mmap::map(&*file) }.map_err(|e|{
+            error!("map file err: {}",e);
+            Errors::OpenFileError
+        })?;
+        Ok(buf.len())
     }
 
     fn sync(&self) -> Result<()> {
-        unimplemented!()
+        // nothing to persist if file was never written through this manager
+        if let Some(file) = self.writer.lock().as_ref() {
+            if let Err(e) = file.sync_all() {
+                error!("sync mmap file err: {}",e);
+                return Err(Errors::SyncFileError);
+            }
+        }
+        Ok(())
     }
 
     fn size(&self) -> u64 {
@@ -67,7 +94,7 @@ impl IOManager for MmapIO {
 #[cfg(test)]
 mod tests{
     use std::fs;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
 
     use crate::fio::file_io::FileIO;
     use crate::fio::IOManager;
@@ -80,12 +107,13 @@ mod tests{
         write_data(&file_path);
 
         let mmap_io=MmapIO::new(file_path.clone());
-        if mmap_io.is_err() {
-            remove_tmp_file(file_path);
-            panic!("{:?}",mmap_io.unwrap_err())
-        }
-        assert!(mmap_io.is_ok());
-        let mmap_io=mmap_io.unwrap();
+        let mmap_io=match mmap_io {
+            Ok(mmap_io)=>mmap_io,
+            Err(e)=>{
+                remove_tmp_file(file_path);
+                panic!("{:?}",e)
+            }
+        };
         let mut buf=[0u8;7];
         let read_size=mmap_io.read(&mut buf,0);
         assert!(read_size.is_ok());
@@ -94,8 +122,28 @@ mod tests{
         assert!(remove_tmp_file(file_path))
     }
 
-    fn write_data(path:&PathBuf){
-        let file_io=FileIO::new(path.clone()).unwrap();
+    #[test]
+    fn test_write(){
+        let file_path=PathBuf::from("/tmp/mmap-write.data");
+        write_data(&file_path);
+
+        let mmap_io=MmapIO::new(file_path.clone()).unwrap();
+        assert_eq!(mmap_io.write(b"hij").unwrap(),3);
+        assert!(mmap_io.sync().is_ok());
+        assert_eq!(mmap_io.size(),10);
+        let mut buf=[0u8;10];
+        assert_eq!(mmap_io.read(&mut buf,0).unwrap(),10);
+        assert_eq!(&buf,b"abcdefghij");
+
+        // written data is visible to other io managers
+        let mmap_io=MmapIO::new(file_path.clone()).unwrap();
+        assert_eq!(mmap_io.size(),10);
+
+        assert!(remove_tmp_file(file_path))
+    }
+
+    fn write_data(path:&Path){
+        let file_io=FileIO::new(path.to_path_buf()).unwrap();
         let write_size=file_io.write(b"abcdefg");
         assert!(write_size.is_ok());
         assert_eq!(write_size.unwrap(),7);
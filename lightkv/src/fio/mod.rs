@@ -1,8 +1,12 @@
 mod file_io;
 mod mmap_io;
 
+use crate::errors::Errors;
 use crate::fio::file_io::FileIO;
 use crate::Result;
+use log::error;
+use std::fs::File;
+use std::io::{ErrorKind, Write};
 use std::path::PathBuf;
 use crate::fio::mmap_io::MmapIO;
 use crate::options::IOType;
@@ -29,3 +33,22 @@ pub fn new_io_manager(file_name: PathBuf,io_type:IOType) -> Result<Box<dyn IOMan
         IOType::MmapIO => Ok(Box::new(MmapIO::new(file_name)?)),
     }
 }
+
+// write the whole buffer or cut file back to its size before the write,
+// so a failed write never leaves a partial record behind
+fn write_all_or_truncate(file: &mut File, buf: &[u8]) -> Result<()> {
+    let previous_size = file.metadata().map(|metadata| metadata.len());
+    let e = match file.write_all(buf) {
+        Ok(()) => return Ok(()),
+        Err(e) => e,
+    };
+    error!("write file err: {}", e);
+    match previous_size.and_then(|size| file.set_len(size)) {
+        Ok(()) => {}
+        Err(truncate_err) => error!("truncate file after failed write err: {}", truncate_err),
+    }
+    match e.kind() {
+        ErrorKind::StorageFull => Err(Errors::DiskFull),
+        _ => Err(Errors::WriteFileError),
+    }
+}
@@ -4,18 +4,22 @@ use crate::{
     }, options::IteratorOptions
 };
 
+// on-disk b+ tree index, not wired into `new_index` yet
+#[allow(dead_code)]
 pub struct BPlusTreeIndex{
     
 }
 
+#[allow(dead_code)]
 impl BPlusTreeIndex {
     pub fn new()->Self{
         Self {  }
     }
 }
 
+#[allow(unused_variables)]
 impl Index for BPlusTreeIndex {
-    fn put(&self, key: Vec<u8>, pos: crate::data::log_record::LogRecordPos) -> bool {
+    fn put(&self, key: Vec<u8>, pos: crate::data::log_record::LogRecordPos) -> Option<crate::data::log_record::LogRecordPos> {
         todo!()
     }
 
@@ -23,7 +27,11 @@ impl Index for BPlusTreeIndex {
         todo!()
     }
 
-    fn delete(&self, key: Vec<u8>) -> bool {
+    fn delete(&self, key: Vec<u8>) -> Option<crate::data::log_record::LogRecordPos> {
+        todo!()
+    }
+
+    fn size(&self) -> usize {
         todo!()
     }
 
@@ -36,6 +44,7 @@ impl Index for BPlusTreeIndex {
     }
 }
 
+#[allow(dead_code)]
 pub struct BPlusTreeIndexIterator{
     
     index:usize,
@@ -1,7 +1,8 @@
 use crate::data::log_record::LogRecordPos;
-use crate::index::{Index, IndexIterator};
+use crate::index::{seek_bound, start_bound, Index, IndexIterator};
 use parking_lot::RwLock;
 use std::collections::BTreeMap;
+use std::ops::Bound;
 use std::sync::Arc;
 use bytes::Bytes;
 use crate::options::IteratorOptions;
@@ -19,10 +20,9 @@ impl BTreeIndex {
 }
 
 impl Index for BTreeIndex {
-    fn put(&self, key: Vec<u8>, pos: LogRecordPos) -> bool {
+    fn put(&self, key: Vec<u8>, pos: LogRecordPos) -> Option<LogRecordPos> {
         let mut write_guard = self.index.write();
-        write_guard.insert(key, pos);
-        true
+        write_guard.insert(key, pos)
     }
 
     fn get(&self, key: Vec<u8>) -> Option<LogRecordPos> {
@@ -30,10 +30,14 @@ impl Index for BTreeIndex {
         read_guard.get(&key).copied()
     }
 
-    fn delete(&self, key: Vec<u8>) -> bool {
+    fn delete(&self, key: Vec<u8>) -> Option<LogRecordPos> {
         let mut write_guard = self.index.write();
-        let res = write_guard.remove(&key);
-        res.is_some()
+        write_guard.remove(&key)
+    }
+
+    fn size(&self) -> usize {
+        let read_guard = self.index.read();
+        read_guard.len()
     }
 
     fn list_keys(&self) -> Option<Vec<Bytes>> {
@@ -46,39 +50,42 @@ impl Index for BTreeIndex {
     }
 
     fn iterator(&self, options: IteratorOptions) -> Box<dyn IndexIterator> {
-        let read_guard=self.index.read();
-        let mut items=Vec::with_capacity(read_guard.len());
-        for (key, value) in read_guard.iter() {
-            items.push((key.clone(),value.clone()));
-        }
-        if options.reverse {
-            items.reverse();
-        }
         Box::new(BTreeIndexIterator{
-            items,
-            index: 0,
+            index: self.index.clone(),
+            cursor: start_bound(&options),
             options,
         })
     }
 }
 
+// walks keys with prefix in the index, no key is copied before it is reached
 pub struct BTreeIndexIterator{
-    items:Vec<(Vec<u8>,LogRecordPos)>,
-    index:usize,
+    index:Arc<RwLock<BTreeMap<Vec<u8>, LogRecordPos>>>,
+    // bound of the next key, the upper one when reverse
+    cursor:Bound<Vec<u8>>,
     options:IteratorOptions,
 }
 
 impl IndexIterator for BTreeIndexIterator {
     fn rewind(&mut self) {
-        self.index=0;
+        self.cursor=start_bound(&self.options);
     }
 
     fn seek(&mut self, key: Vec<u8>) {
-
+        self.cursor=seek_bound(&self.options,key);
     }
 
     fn next(&mut self) -> Option<(Vec<u8>, LogRecordPos)> {
-        todo!()
+        let read_guard=self.index.read();
+        let (key,pos)=match self.options.reverse {
+            true=>read_guard.range((Bound::Unbounded,self.cursor.clone())).next_back(),
+            false=>read_guard.range((self.cursor.clone(),Bound::Unbounded)).next(),
+        }?;
+        if !key.starts_with(&self.options.prefix) {
+            return None;
+        }
+        self.cursor=Bound::Excluded(key.clone());
+        Some((key.clone(),*pos))
     }
 }
 
@@ -87,21 +94,27 @@ mod tests {
     use crate::data::log_record::LogRecordPos;
     use crate::index::btree::BTreeIndex;
     use crate::index::Index;
+    use crate::options::IteratorOptions;
 
     #[test]
     fn test_put() {
-        // let btree_index = BTreeIndex::new();
-        //
-        // let test_data = vec![
-        //     ("test-1".into(), LogRecordPos { file_id: 0, offset: 10 }),
-        //     ("test-2".into(), LogRecordPos { file_id: 0, offset: 20 }),
-        //     ("test-3".into(), LogRecordPos { file_id: 0, offset: 30 }),
-        // ];
-        //
-        // for item in test_data.into_iter() {
-        //     let put_res= btree_index.put(item.0, item.1);
-        //     assert!(put_res);
-        // }
+        let btree_index = BTreeIndex::new();
+
+        let test_data = vec![
+            ("test-1".into(), LogRecordPos { file_id: 0, offset: 10, size: 10 }),
+            ("test-2".into(), LogRecordPos { file_id: 0, offset: 20, size: 10 }),
+            ("test-3".into(), LogRecordPos { file_id: 0, offset: 30, size: 10 }),
+        ];
+
+        for item in test_data.into_iter() {
+            let put_res= btree_index.put(item.0, item.1);
+            assert!(put_res.is_none());
+        }
+
+        // overwrite returns previous position
+        let put_res = btree_index.put("test-1".into(), LogRecordPos { file_id: 1, offset: 0, size: 10 });
+        assert_eq!(put_res.unwrap().offset, 10);
+        assert_eq!(btree_index.size(), 3);
 
     }
 
@@ -109,41 +122,71 @@ mod tests {
     fn test_get() {
         let btree_index = BTreeIndex::new();
 
-        // let test_data=vec![
-        //     ("test-1".as_bytes().to_vec(), LogRecordPos { file_id: 0, offset: 10 }),
-        //     ("test-2".as_bytes().to_vec(), LogRecordPos { file_id: 0, offset: 20 }),
-        //     ("test-3".as_bytes().to_vec(), LogRecordPos { file_id: 0, offset: 30 }),
-        // ];
-        //
-        // for item in test_data.into_iter() {
-        //     let put_res= btree_index.put(item.0.clone(), item.1);
-        //     assert!(put_res);
-        //     let get_res = btree_index.get(item.0);
-        //     assert!(get_res.is_some());
-        //     assert_eq!(get_res.unwrap().offset, item.1.offset);
-        //     assert_eq!(get_res.unwrap().file_id, item.1.file_id);
-        // }
+        let test_data=vec![
+            ("test-1".as_bytes().to_vec(), LogRecordPos { file_id: 0, offset: 10, size: 10 }),
+            ("test-2".as_bytes().to_vec(), LogRecordPos { file_id: 0, offset: 20, size: 10 }),
+            ("test-3".as_bytes().to_vec(), LogRecordPos { file_id: 0, offset: 30, size: 10 }),
+        ];
+
+        for item in test_data.into_iter() {
+            let put_res= btree_index.put(item.0.clone(), item.1);
+            assert!(put_res.is_none());
+            let get_res = btree_index.get(item.0);
+            assert!(get_res.is_some());
+            assert_eq!(get_res.unwrap().offset, item.1.offset);
+            assert_eq!(get_res.unwrap().file_id, item.1.file_id);
+        }
 
     }
 
     #[test]
     fn test_delete() {
         let btree_index = BTreeIndex::new();
-        //
-        // let test_data=vec![
-        //     ("test-1".as_bytes().to_vec(), LogRecordPos { file_id: 0, offset: 10 }),
-        //     ("test-2".as_bytes().to_vec(), LogRecordPos { file_id: 0, offset: 20 }),
-        //     ("test-3".as_bytes().to_vec(), LogRecordPos { file_id: 0, offset: 30 }),
-        // ];
-        //
-        // for item in test_data.into_iter() {
-        //     let put_res= btree_index.put(item.0.clone(), item.1);
-        //     assert!(put_res);
-        //     let del_res = btree_index.delete(item.0);
-        //     assert!(del_res);
-        // }
 
-    }
+        let test_data=vec![
+            ("test-1".as_bytes().to_vec(), LogRecordPos { file_id: 0, offset: 10, size: 10 }),
+            ("test-2".as_bytes().to_vec(), LogRecordPos { file_id: 0, offset: 20, size: 10 }),
+            ("test-3".as_bytes().to_vec(), LogRecordPos { file_id: 0, offset: 30, size: 10 }),
+        ];
+
+        for item in test_data.into_iter() {
+            let put_res= btree_index.put(item.0.clone(), item.1);
+            assert!(put_res.is_none());
+            let del_res = btree_index.delete(item.0);
+            assert_eq!(del_res.unwrap().offset, item.1.offset);
+        }
+        assert_eq!(btree_index.size(), 0);
 
+    }
 
+    #[test]
+    fn test_iterator() {
+        let btree_index = BTreeIndex::new();
+        let all: [&[u8]; 7] = [b"a", b"ba", b"bb", b"bc", b"c", b"\xff", b"\xff\xff"];
+        for (i, key) in all.iter().enumerate() {
+            btree_index.put(key.to_vec(), LogRecordPos { file_id: 0, offset: i as u64, size: 10 });
+        }
+        let keys = |prefix: &[u8], reverse: bool, seek: Option<&[u8]>| {
+            let mut iter = btree_index.iterator(IteratorOptions { prefix: prefix.to_vec(), reverse });
+            if let Some(key) = seek {
+                iter.seek(key.to_vec());
+            }
+            let mut keys = Vec::new();
+            while let Some((key, _)) = iter.next() {
+                keys.push(key);
+            }
+            keys
+        };
+
+        // iteration never leaves keys with prefix
+        assert_eq!(keys(b"b", false, None), vec![b"ba", b"bb", b"bc"]);
+        assert_eq!(keys(b"b", true, None), vec![b"bc", b"bb", b"ba"]);
+        assert_eq!(keys(b"b", false, Some(b"bb")), vec![b"bb", b"bc"]);
+        assert_eq!(keys(b"b", false, Some(b"a")), vec![b"ba", b"bb", b"bc"]);
+        assert_eq!(keys(b"b", true, Some(b"bb")), vec![b"bb", b"ba"]);
+        assert_eq!(keys(b"b", true, Some(b"z")), vec![b"bc", b"bb", b"ba"]);
+        assert_eq!(keys(b"\xff", true, None), vec![b"\xff\xff".to_vec(), b"\xff".to_vec()]);
+        assert_eq!(keys(b"", true, None).len(), all.len());
+        assert!(keys(b"d", false, None).is_empty());
+    }
 }
@@ -1,6 +1,8 @@
+use std::ops::Bound;
 use bytes::Bytes;
 use crate::data::log_record::LogRecordPos;
 use crate::index::btree::BTreeIndex;
+use crate::index::skiplist::SkipListIndex;
 use crate::options::{IndexType, IteratorOptions};
 
 mod btree;
@@ -8,14 +10,17 @@ mod b_plus_tree;
 mod skiplist;
 
 pub trait Index: Sync + Send {
-    // put key to into index
-    fn put(&self, key: Vec<u8>, pos: LogRecordPos) -> bool;
+    // put key to into index, return position of the overwritten value
+    fn put(&self, key: Vec<u8>, pos: LogRecordPos) -> Option<LogRecordPos>;
 
     // get value's position in data file
     fn get(&self, key: Vec<u8>) -> Option<LogRecordPos>;
 
-    // delete specific key value pair in index
-    fn delete(&self, key: Vec<u8>) -> bool;
+    // delete specific key value pair in index, return position of the deleted value
+    fn delete(&self, key: Vec<u8>) -> Option<LogRecordPos>;
+
+    // number of keys in index
+    fn size(&self) -> usize;
 
     // list all keys
     fn list_keys(&self)->Option<Vec<Bytes>>;
@@ -27,7 +32,9 @@ pub trait Index: Sync + Send {
 pub fn new_index(index_type:IndexType)->Box<dyn Index>{
     match index_type {
         IndexType::BTree=>Box::new(BTreeIndex::new()),
-        _=>Box::new(BTreeIndex::new()),
+        IndexType::SkipList=>Box::new(SkipListIndex::new()),
+        // on-disk b+ tree index is not finished yet
+        IndexType::BPlusTree=>Box::new(BTreeIndex::new()),
     }
 }
 
@@ -40,4 +47,32 @@ pub trait IndexIterator:Sync+Send{
 
     // return key value pair of current index
     fn next(&mut self)->Option<(Vec<u8>,LogRecordPos)>;
-}
\ No newline at end of file
+}
+// bound the iteration starts from, the upper bound of keys with prefix when reverse
+fn start_bound(options:&IteratorOptions)->Bound<Vec<u8>>{
+    match options.reverse {
+        false=>Bound::Included(options.prefix.clone()),
+        true=>prefix_end(&options.prefix),
+    }
+}
+
+// bound of seeking key, clamped to keys with prefix
+fn seek_bound(options:&IteratorOptions,key:Vec<u8>)->Bound<Vec<u8>>{
+    match (options.reverse,prefix_end(&options.prefix)) {
+        (false,_) if key<options.prefix=>Bound::Included(options.prefix.clone()),
+        (true,Bound::Excluded(end)) if key>=end=>Bound::Excluded(end),
+        _=>Bound::Included(key),
+    }
+}
+
+// bound right after every key starting with prefix
+fn prefix_end(prefix:&[u8])->Bound<Vec<u8>>{
+    let mut end=prefix.to_vec();
+    while let Some(last)=end.pop() {
+        if last<u8::MAX {
+            end.push(last+1);
+            return Bound::Excluded(end);
+        }
+    }
+    Bound::Unbounded
+}
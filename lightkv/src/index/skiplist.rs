@@ -1,8 +1,9 @@
+use std::ops::Bound;
 use std::sync::Arc;
 use bytes::Bytes;
 use crossbeam_skiplist::{SkipMap};
 use crate::data::log_record::LogRecordPos;
-use crate::index::{Index, IndexIterator};
+use crate::index::{seek_bound, start_bound, Index, IndexIterator};
 use crate::options::IteratorOptions;
 
 pub struct SkipListIndex{
@@ -18,66 +19,67 @@ impl SkipListIndex {
 }
 
 impl Index for SkipListIndex {
-    fn put(&self, key: Vec<u8>, pos: LogRecordPos) -> bool {
-        todo!()
+    fn put(&self, key: Vec<u8>, pos: LogRecordPos) -> Option<LogRecordPos> {
+        let old_pos = self.index.get(&key).map(|entry| *entry.value());
+        self.index.insert(key, pos);
+        old_pos
     }
 
     fn get(&self, key: Vec<u8>) -> Option<LogRecordPos> {
-        todo!()
+        self.index.get(&key).map(|entry| *entry.value())
     }
 
-    fn delete(&self, key: Vec<u8>) -> bool {
-        todo!()
+    fn delete(&self, key: Vec<u8>) -> Option<LogRecordPos> {
+        self.index.remove(&key).map(|entry| *entry.value())
+    }
+
+    fn size(&self) -> usize {
+        self.index.len()
     }
 
     fn list_keys(&self) -> Option<Vec<Bytes>> {
-        todo!()
+        let mut keys=Vec::with_capacity(self.index.len());
+        for entry in self.index.iter() {
+            keys.push(Bytes::copy_from_slice(entry.key()));
+        }
+        Some(keys)
     }
 
     fn iterator(&self, options: IteratorOptions) -> Box<dyn IndexIterator> {
-        todo!()
+        Box::new(SkipListIndexIterator{
+            index: self.index.clone(),
+            cursor: start_bound(&options),
+            options,
+        })
     }
 }
 
+// walks keys with prefix in the index, no key is copied before it is reached
 pub struct SkipListIndexIterator{
-    items:Vec<(Vec<u8>,LogRecordPos)>,
-    index:usize,
+    index:Arc<SkipMap<Vec<u8>,LogRecordPos>>,
+    // bound of the next key, the upper one when reverse
+    cursor:Bound<Vec<u8>>,
     options:IteratorOptions,
 }
 
 impl IndexIterator for SkipListIndexIterator {
     fn rewind(&mut self) {
-        self.index=0;
+        self.cursor=start_bound(&self.options);
     }
 
     fn seek(&mut self, key: Vec<u8>) {
-        let result=self.items.binary_search_by(|(x,_)|{
-            match self.options.reverse {
-                true=>{
-                    x.cmp(&key).reverse()
-                },
-                false=>{
-                    x.cmp(&key)
-                }
-            }
-        });
-        self.index=match result {
-            Ok(equal_value)=>equal_value,
-            Err(insert_value)=>insert_value,
-        }
+        self.cursor=seek_bound(&self.options,key);
     }
 
     fn next(&mut self) -> Option<(Vec<u8>, LogRecordPos)> {
-        if self.index>=self.items.len() {
+        let entry=match self.options.reverse {
+            true=>self.index.range((Bound::Unbounded,self.cursor.clone())).next_back(),
+            false=>self.index.range((self.cursor.clone(),Bound::Unbounded)).next(),
+        }?;
+        if !entry.key().starts_with(&self.options.prefix) {
             return None;
         }
-        while let Some(item) = self.items.get(self.index) {
-            self.index+=1;
-            let prefix=&self.options.prefix;
-            if prefix.is_empty()||item.0.starts_with(prefix) {
-                return Some((item.0.clone(),item.1));
-            }
-        }
-        None
+        self.cursor=Bound::Excluded(entry.key().clone());
+        Some((entry.key().clone(),*entry.value()))
     }
-}
\ No newline at end of file
+}
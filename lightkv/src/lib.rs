@@ -4,14 +4,26 @@ mod index;
 
 
 mod errors;
-pub use errors::Result;
+pub use errors::{Errors, Result};
 
 mod batch;
+mod blob;
+mod manifest;
+mod periodic;
+pub use blob::BlobFileStat;
 
 pub mod engine;
+pub mod merge;
+pub mod table;
+pub mod metrics;
+#[cfg(feature = "server")]
+pub mod server;
 
 pub mod options;
+#[cfg(feature = "server")]
+pub mod config;
 mod macros;
-mod types;
-mod log;
+pub mod types;
+pub mod log;
+pub mod redis;
 mod util;
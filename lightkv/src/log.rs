@@ -0,0 +1,158 @@
+use crate::errors::Errors;
+use crate::types::LogLevel;
+use crate::Result;
+use env_logger::{Builder, Target, WriteStyle};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Logging settings of the server binary
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    // minimum level of emitted logs
+    pub level: LogLevel,
+
+    // log file path, logs go to stderr if none
+    pub file: Option<PathBuf>,
+
+    // log file is rotated once it exceeds this size, 0 disables rotation
+    pub max_file_size: u64,
+
+    // number of rotated log files kept
+    pub max_files: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: LogLevel::Info,
+            file: None,
+            max_file_size: 64 * 1024 * 1024,
+            max_files: 5,
+        }
+    }
+}
+
+/// Install the global logger, `RUST_LOG` overrides the configured level,
+/// fails if a logger is already installed
+pub fn init_logger(config: &LogConfig) -> Result<()> {
+    let mut builder = Builder::new();
+    builder.filter_level(config.level.to_level_filter());
+    if let Ok(filters) = std::env::var("RUST_LOG") {
+        builder.parse_filters(&filters);
+    }
+
+    if let Some(path) = config.file.as_ref() {
+        let file = match RotatingFile::open(path, config.max_file_size, config.max_files) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("open log file {} err: {}", path.display(), e);
+                return Err(Errors::OpenFileError);
+            }
+        };
+        builder
+            .target(Target::Pipe(Box::new(file)))
+            .write_style(WriteStyle::Never);
+    }
+
+    builder.try_init().map_err(|_| Errors::InitLoggerError)
+}
+
+// log file rotated by size, `server.log` is renamed to `server.log.1`,
+// older files shift up by one and the oldest is removed
+struct RotatingFile {
+    path: PathBuf,
+    max_file_size: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: &Path, max_file_size: u64, max_files: usize) -> io::Result<Self> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_file_size,
+            max_files,
+            file,
+            size,
+        })
+    }
+
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", n));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..self.max_files).rev() {
+                let from = self.rotated_path(n);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(n + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.max_file_size > 0 && self.size > 0 && self.size + buf.len() as u64 > self.max_file_size {
+            self.rotate()?;
+        }
+        let n = self.file.write(buf)?;
+        self.size += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::log::RotatingFile;
+    use std::io::Write;
+
+    #[test]
+    fn test_rotating_file() {
+        let dir_path = std::env::temp_dir().join("lightkv-rotating-log");
+        let _ = std::fs::remove_dir_all(&dir_path);
+        let path = dir_path.join("server.log");
+
+        let mut file = RotatingFile::open(&path, 10, 2).unwrap();
+        for line in ["line-1\n", "line-2\n", "line-3\n", "line-4\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        // each line exceeds the limit together with the previous one, the oldest is dropped
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "line-4\n");
+        assert_eq!(std::fs::read_to_string(dir_path.join("server.log.1")).unwrap(), "line-3\n");
+        assert_eq!(std::fs::read_to_string(dir_path.join("server.log.2")).unwrap(), "line-2\n");
+        assert!(!dir_path.join("server.log.3").exists());
+
+        // size of existing file counts after reopen
+        let mut file = RotatingFile::open(&path, 10, 2).unwrap();
+        file.write_all(b"line-5\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "line-5\n");
+        std::fs::remove_dir_all(&dir_path).unwrap();
+    }
+}
@@ -1,5 +1,18 @@
-macro_rules! log {
-    (
-        $
-    ) => {};
-}
\ No newline at end of file
+/// Emit a structured event as `event=<name> key=value ...` under target `lightkv::event`,
+/// so events can be filtered and parsed apart from free-form messages
+///
+/// ```text
+/// event!(Info, "rotate", file_id = 3, offset = 1024);
+/// ```
+macro_rules! event {
+    ($level:ident, $event:literal $(, $key:ident = $value:expr)* $(,)?) => {
+        log::log!(
+            target: "lightkv::event",
+            log::Level::$level,
+            concat!("event=", $event $(, " ", stringify!($key), "={}")*)
+            $(, $value)*
+        )
+    };
+}
+
+pub(crate) use event;
@@ -0,0 +1,253 @@
+use crate::data::data_file::{
+    DataFile, BLOB_FILE_NAME_SUFFIX, DATA_FILE_NAME_SUFFIX, HINT_FILE_NAME_SUFFIX, MANIFEST_FILE_NAME_SUFFIX,
+    MERGE_FINISHED_FILE_NAME_SUFFIX,
+};
+use crate::data::log_record::{LogRecord, RecordType};
+use crate::errors::Errors;
+use crate::options::IOType;
+use crate::Result;
+use log::warn;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Version of data file layout, checkpoints of a newer version are refused
+pub(crate) const FORMAT_VERSION: u64 = 1;
+
+const FORMAT_VERSION_KEY: &[u8] = "format-version".as_bytes();
+const TXN_SEQ_KEY: &[u8] = "txn-seq".as_bytes();
+
+/// A file recorded by checkpoint manifest
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ManifestFile {
+    pub(crate) name: String,
+    pub(crate) size: u64,
+    pub(crate) checksum: u32,
+}
+
+/// Manifest describes files of a checkpoint directory
+#[derive(Debug, PartialEq)]
+pub(crate) struct Manifest {
+    pub(crate) format_version: u64,
+    pub(crate) txn_id: usize,
+    pub(crate) files: Vec<ManifestFile>,
+}
+
+impl Manifest {
+    // collect data, blob, hint and merge finished files of the directory
+    pub(crate) fn build(dir_path: &Path, txn_id: usize) -> Result<Self> {
+        let dir = match fs::read_dir(dir_path) {
+            Ok(dir) => dir,
+            Err(e) => {
+                warn!("read checkpoint directory err: {}", e);
+                return Err(Errors::ReadDirError);
+            }
+        };
+        let mut files = Vec::new();
+        for entry in dir.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !is_manifest_file(&name) {
+                continue;
+            }
+            let (size, checksum) = file_checksum(&entry.path(), u64::MAX)?;
+            files.push(ManifestFile { name, size, checksum });
+        }
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(Self {
+            format_version: FORMAT_VERSION,
+            txn_id,
+            files,
+        })
+    }
+
+    // written under a temporary name and renamed, a manifest is either complete or absent
+    pub(crate) fn write(&self, dir_path: &Path) -> Result<()> {
+        let mut records = vec![
+            meta_record(FORMAT_VERSION_KEY, self.format_version.to_string()),
+            meta_record(TXN_SEQ_KEY, self.txn_id.to_string()),
+        ];
+        for file in self.files.iter() {
+            records.push(meta_record(
+                file.name.as_bytes(),
+                format!("{},{}", file.size, file.checksum),
+            ));
+        }
+        let buf: Vec<u8> = records.iter().flat_map(|record| record.encode()).collect();
+
+        let tmp_path = dir_path.join(format!("{}.tmp", MANIFEST_FILE_NAME_SUFFIX));
+        let write_res = File::create(&tmp_path)
+            .and_then(|mut file| file.write_all(&buf).and_then(|_| file.sync_all()))
+            .and_then(|_| fs::rename(&tmp_path, dir_path.join(MANIFEST_FILE_NAME_SUFFIX)));
+        if let Err(e) = write_res {
+            warn!("write manifest file err: {}", e);
+            return Err(Errors::WriteFileError);
+        }
+        Ok(())
+    }
+
+    // load manifest of the directory, none if it is not a checkpoint
+    pub(crate) fn load(dir_path: &Path) -> Result<Option<Self>> {
+        if !dir_path.join(MANIFEST_FILE_NAME_SUFFIX).is_file() {
+            return Ok(None);
+        }
+        let manifest_file = DataFile::new_manifest_file(dir_path.to_path_buf(), IOType::MmapIO)?;
+
+        let mut format_version = None;
+        let mut txn_id = None;
+        let mut files = Vec::new();
+        let mut offset = 0;
+        loop {
+            let read_log_record = match manifest_file.read_log_record(offset) {
+                Ok(read_log_record) => read_log_record,
+                Err(Errors::ReadFileEOF) => break,
+                Err(e) => return Err(e),
+            };
+            offset += read_log_record.size as u64;
+
+            let record = read_log_record.log_record;
+            let value = String::from_utf8_lossy(&record.value).to_string();
+            if record.key == FORMAT_VERSION_KEY {
+                format_version = Some(parse_number(&value)?);
+            } else if record.key == TXN_SEQ_KEY {
+                txn_id = Some(parse_number(&value)? as usize);
+            } else {
+                let (size, checksum) = match value.split_once(',') {
+                    Some((size, checksum)) => (parse_number(size)?, parse_number(checksum)? as u32),
+                    None => return Err(Errors::DataDirCorrupted),
+                };
+                files.push(ManifestFile {
+                    name: String::from_utf8_lossy(&record.key).to_string(),
+                    size,
+                    checksum,
+                });
+            }
+        }
+
+        match (format_version, txn_id) {
+            (Some(format_version), Some(txn_id)) => Ok(Some(Self {
+                format_version,
+                txn_id,
+                files,
+            })),
+            _ => Err(Errors::DataDirCorrupted),
+        }
+    }
+
+    // every listed file must exist and not be truncated, the last data file may be
+    // appended after checkpoint, checksums are compared over the recorded length
+    pub(crate) fn verify(&self, dir_path: &Path, verify_checksum: bool) -> Result<()> {
+        if self.format_version > FORMAT_VERSION {
+            return Err(Errors::UnsupportedFormatVersion);
+        }
+        for file in self.files.iter() {
+            let file_path = dir_path.join(&file.name);
+            let size = match fs::metadata(&file_path) {
+                Ok(metadata) => metadata.len(),
+                Err(_) => {
+                    warn!("file {} in manifest is missing", file.name);
+                    return Err(Errors::ManifestMismatch);
+                }
+            };
+            if size < file.size {
+                warn!("file {} in manifest is truncated", file.name);
+                return Err(Errors::ManifestMismatch);
+            }
+            if verify_checksum && file_checksum(&file_path, file.size)?.1 != file.checksum {
+                warn!("file {} in manifest is corrupted", file.name);
+                return Err(Errors::ManifestMismatch);
+            }
+        }
+        Ok(())
+    }
+}
+
+// files replaced by merge no longer match manifest
+pub(crate) fn remove_manifest(dir_path: &Path) -> Result<()> {
+    let manifest_path = dir_path.join(MANIFEST_FILE_NAME_SUFFIX);
+    if manifest_path.is_file() {
+        if let Err(e) = fs::remove_file(&manifest_path) {
+            warn!("remove manifest file err: {}", e);
+            return Err(Errors::WriteFileError);
+        }
+    }
+    Ok(())
+}
+
+fn is_manifest_file(name: &str) -> bool {
+    name.ends_with(DATA_FILE_NAME_SUFFIX)
+        || name.ends_with(BLOB_FILE_NAME_SUFFIX)
+        || name == HINT_FILE_NAME_SUFFIX
+        || name == MERGE_FINISHED_FILE_NAME_SUFFIX
+}
+
+fn meta_record(key: &[u8], value: String) -> LogRecord {
+    LogRecord {
+        key: key.to_vec(),
+        value: value.into_bytes(),
+        record_type: RecordType::NORMAL,
+    }
+}
+
+fn parse_number(value: &str) -> Result<u64> {
+    value.parse::<u64>().map_err(|_| Errors::DataDirCorrupted)
+}
+
+// size and crc32 of at most `len` bytes of a file
+fn file_checksum(file_path: &Path, len: u64) -> Result<(u64, u32)> {
+    let file = match File::open(file_path) {
+        Ok(file) => file,
+        Err(e) => {
+            warn!("open file err: {}", e);
+            return Err(Errors::OpenFileError);
+        }
+    };
+    let mut reader = file.take(len);
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut size = 0;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) => {
+                warn!("read file err: {}", e);
+                return Err(Errors::ReadFileError);
+            }
+        };
+        hasher.update(&buf[..n]);
+        size += n as u64;
+    }
+    Ok((size, hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::Errors;
+    use crate::manifest::Manifest;
+
+    #[test]
+    fn test_manifest_roundtrip() {
+        let dir_path = std::env::temp_dir().join("lightkv-manifest");
+        let _ = std::fs::remove_dir_all(&dir_path);
+        std::fs::create_dir_all(&dir_path).unwrap();
+        std::fs::write(dir_path.join("000000000.data"), vec![1u8; 100]).unwrap();
+        std::fs::write(dir_path.join("lightkv_lock"), b"").unwrap();
+        assert_eq!(Manifest::load(&dir_path).unwrap(), None);
+
+        let manifest = Manifest::build(&dir_path, 7).unwrap();
+        assert_eq!(manifest.files.len(), 1);
+        manifest.write(&dir_path).unwrap();
+        let loaded = Manifest::load(&dir_path).unwrap().unwrap();
+        assert_eq!(loaded, manifest);
+        loaded.verify(&dir_path, true).unwrap();
+
+        // appended data is allowed, changed data is not
+        std::fs::write(dir_path.join("000000000.data"), vec![1u8; 120]).unwrap();
+        loaded.verify(&dir_path, true).unwrap();
+        std::fs::write(dir_path.join("000000000.data"), vec![2u8; 120]).unwrap();
+        assert_eq!(loaded.verify(&dir_path, false), Ok(()));
+        assert_eq!(loaded.verify(&dir_path, true), Err(Errors::ManifestMismatch));
+        std::fs::remove_dir_all(&dir_path).unwrap();
+    }
+}
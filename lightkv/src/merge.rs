@@ -0,0 +1,164 @@
+use crate::data::log_record::LogRecordPos;
+use crate::errors::Errors;
+use crate::Result;
+use bytes::{Buf, BufMut, BytesMut};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Name of the built-in operator behind `Engine::incr_by`
+pub const INCR_OPERATOR: &str = "lightkv.incr";
+/// Name of the built-in operator behind `Engine::append`
+pub const APPEND_OPERATOR: &str = "lightkv.append";
+
+/// Folds operands written by `Engine::merge` into the value of a key,
+/// registered through `Options::merge_operators`.
+///
+/// Operands are kept as records and folded on read, compaction folds them
+/// into one value, so `merge` must be deterministic.
+pub trait MergeOperator: Send + Sync {
+    /// Name recorded with every operand, must never change once operands are written
+    fn name(&self) -> &str;
+
+    /// New value after applying `operand` to `existing`, which is none if key
+    /// has no value, return none to reject the operand and keep the value
+    fn merge(&self, key: &[u8], existing: Option<&[u8]>, operand: &[u8]) -> Option<Vec<u8>>;
+}
+
+/// Adds a signed 64-bit operand to a value holding a decimal integer,
+/// a missing value counts as 0
+pub struct IncrOperator;
+
+impl MergeOperator for IncrOperator {
+    fn name(&self) -> &str {
+        INCR_OPERATOR
+    }
+
+    fn merge(&self, _key: &[u8], existing: Option<&[u8]>, operand: &[u8]) -> Option<Vec<u8>> {
+        let current = match existing {
+            Some(existing) => parse_integer(existing)?,
+            None => 0,
+        };
+        let delta = i64::from_be_bytes(operand.try_into().ok()?);
+        Some(current.checked_add(delta)?.to_string().into_bytes())
+    }
+}
+
+/// Appends the operand to the value, a missing value counts as empty
+pub struct AppendOperator;
+
+impl MergeOperator for AppendOperator {
+    fn name(&self) -> &str {
+        APPEND_OPERATOR
+    }
+
+    fn merge(&self, _key: &[u8], existing: Option<&[u8]>, operand: &[u8]) -> Option<Vec<u8>> {
+        let mut value = existing
+            .map(|existing| existing.to_vec())
+            .unwrap_or_default();
+        value.extend_from_slice(operand);
+        Some(value)
+    }
+}
+
+/// Value as a decimal integer, none if it is not one or out of range
+pub fn parse_integer(value: &[u8]) -> Option<i64> {
+    std::str::from_utf8(value).ok()?.parse().ok()
+}
+
+// operators by name, built-in operators can not be replaced
+pub(crate) fn operator_registry(
+    operators: &[Arc<dyn MergeOperator>],
+) -> HashMap<String, Arc<dyn MergeOperator>> {
+    let mut registry: HashMap<String, Arc<dyn MergeOperator>> = HashMap::new();
+    for operator in operators.iter() {
+        registry.insert(operator.name().to_string(), operator.clone());
+    }
+    registry.insert(INCR_OPERATOR.to_string(), Arc::new(IncrOperator));
+    registry.insert(APPEND_OPERATOR.to_string(), Arc::new(AppendOperator));
+    registry
+}
+
+// whether operator names are unique and none of them is taken by a built-in operator
+pub(crate) fn check_operator_names(operators: &[Arc<dyn MergeOperator>]) -> bool {
+    let mut names: Vec<&str> = operators.iter().map(|operator| operator.name()).collect();
+    names.sort();
+    names.dedup();
+    names.len() == operators.len()
+        && !names.contains(&INCR_OPERATOR)
+        && !names.contains(&APPEND_OPERATOR)
+}
+
+// value of merge record: varint length of operator name, operator name, operand
+pub(crate) fn encode_operand(name: &str, operand: &[u8]) -> Vec<u8> {
+    let mut buf = BytesMut::new();
+    prost::encoding::encode_varint(name.len() as u64, &mut buf);
+    buf.put_slice(name.as_bytes());
+    buf.put_slice(operand);
+    buf.to_vec()
+}
+
+pub(crate) fn decode_operand(value: &[u8]) -> Result<(&str, &[u8])> {
+    let mut buf = value;
+    let name_len =
+        prost::encoding::decode_varint(&mut buf).map_err(|_| Errors::InvalidMergeOperand)?;
+    if (buf.remaining() as u64) < name_len {
+        return Err(Errors::InvalidMergeOperand);
+    }
+    let (name, operand) = buf.split_at(name_len as usize);
+    let name = std::str::from_utf8(name).map_err(|_| Errors::InvalidMergeOperand)?;
+    Ok((name, operand))
+}
+
+// records making up the value of a key with merge operands,
+// operands are ordered by their position in data files
+#[derive(Clone, Debug)]
+pub(crate) struct MergeChain {
+    // value the operands apply to, none if key had no value
+    pub(crate) base: Option<LogRecordPos>,
+    pub(crate) operands: Vec<LogRecordPos>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::merge::{
+        decode_operand, encode_operand, AppendOperator, IncrOperator, MergeOperator,
+    };
+
+    #[test]
+    fn test_builtin_operators() {
+        let incr = IncrOperator;
+        assert_eq!(
+            incr.merge(b"k", None, &5i64.to_be_bytes()),
+            Some(b"5".to_vec())
+        );
+        assert_eq!(
+            incr.merge(b"k", Some(b"-7"), &5i64.to_be_bytes()),
+            Some(b"-2".to_vec())
+        );
+        assert_eq!(incr.merge(b"k", Some(b"abc"), &5i64.to_be_bytes()), None);
+        assert_eq!(
+            incr.merge(
+                b"k",
+                Some(i64::MAX.to_string().as_bytes()),
+                &1i64.to_be_bytes()
+            ),
+            None
+        );
+        assert_eq!(incr.merge(b"k", None, b"bad"), None);
+
+        let append = AppendOperator;
+        assert_eq!(append.merge(b"k", None, b"a"), Some(b"a".to_vec()));
+        assert_eq!(append.merge(b"k", Some(b"a"), b"b"), Some(b"ab".to_vec()));
+    }
+
+    #[test]
+    fn test_encode_operand() {
+        let encoded = encode_operand("max", b"operand");
+        assert_eq!(decode_operand(&encoded).unwrap(), ("max", &b"operand"[..]));
+        assert_eq!(
+            decode_operand(&encode_operand("max", b"")).unwrap(),
+            ("max", &b""[..])
+        );
+        assert!(decode_operand(&encoded[..3]).is_err());
+    }
+}
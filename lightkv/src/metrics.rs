@@ -0,0 +1,241 @@
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+/// Latency of `Engine::put` in seconds
+pub const PUT_SECONDS: &str = "lightkv_put_seconds";
+/// Latency of `Engine::get` in seconds
+pub const GET_SECONDS: &str = "lightkv_get_seconds";
+/// Latency of `Engine::remove` in seconds
+pub const REMOVE_SECONDS: &str = "lightkv_remove_seconds";
+/// Bytes appended to data files
+pub const BYTES_WRITTEN_TOTAL: &str = "lightkv_bytes_written_total";
+/// Latency of data file fsync in seconds, its count is the number of fsync
+pub const FSYNC_SECONDS: &str = "lightkv_fsync_seconds";
+/// Times the active data file is rotated
+pub const FILE_ROTATIONS_TOTAL: &str = "lightkv_file_rotations_total";
+/// Duration of successful compactions in seconds
+pub const COMPACT_SECONDS: &str = "lightkv_compact_seconds";
+/// Bytes of data files reclaimed by compaction
+pub const COMPACT_RECLAIMED_BYTES_TOTAL: &str = "lightkv_compact_reclaimed_bytes_total";
+
+// upper bounds of histogram buckets, in seconds
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.00001, 0.00005, 0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0,
+];
+
+/// Receiver of engine metrics, set through `Options::metrics`,
+/// all methods default to no-op so implementations pick what they need
+pub trait Metrics: Send + Sync {
+    /// Add `value` to counter `name`
+    fn incr_counter(&self, _name: &'static str, _value: u64) {}
+
+    /// Record one observation of histogram `name`
+    fn observe_histogram(&self, _name: &'static str, _value: f64) {}
+}
+
+/// Metrics discarding everything, used by default
+pub struct NoopMetrics;
+
+impl Metrics for NoopMetrics {}
+
+// metrics of the engine, registered up front so updating them never locks
+const COUNTERS: [&str; 3] = [
+    BYTES_WRITTEN_TOTAL,
+    FILE_ROTATIONS_TOTAL,
+    COMPACT_RECLAIMED_BYTES_TOTAL,
+];
+const HISTOGRAMS: [&str; 5] = [
+    PUT_SECONDS,
+    GET_SECONDS,
+    REMOVE_SECONDS,
+    FSYNC_SECONDS,
+    COMPACT_SECONDS,
+];
+
+#[derive(Default)]
+struct Histogram {
+    // observations of each bucket, not cumulative
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    count: AtomicU64,
+    // bits of the f64 sum
+    sum: AtomicU64,
+}
+
+impl Histogram {
+    fn observe(&self, value: f64) {
+        if let Some(i) = LATENCY_BUCKETS.iter().position(|bound| value <= *bound) {
+            self.buckets[i].fetch_add(1, Ordering::Relaxed);
+        }
+        let _ = self
+            .sum
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |sum| {
+                Some((f64::from_bits(sum) + value).to_bits())
+            });
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn render(&self, name: &str, buf: &mut String) {
+        let count = self.count.load(Ordering::Relaxed);
+        let _ = writeln!(buf, "# TYPE {} histogram", name);
+        let mut cumulative = 0;
+        for (bound, bucket) in LATENCY_BUCKETS.iter().zip(self.buckets.iter()) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let _ = writeln!(buf, "{}_bucket{{le=\"{}\"}} {}", name, bound, cumulative);
+        }
+        // observations racing with rendering may be in buckets but not yet counted
+        let count = count.max(cumulative);
+        let _ = writeln!(buf, "{}_bucket{{le=\"+Inf\"}} {}", name, count);
+        let _ = writeln!(
+            buf,
+            "{}_sum {}",
+            name,
+            f64::from_bits(self.sum.load(Ordering::Relaxed))
+        );
+        let _ = writeln!(buf, "{}_count {}", name, count);
+    }
+}
+
+/// In-memory metrics rendered in Prometheus text exposition format
+pub struct PrometheusMetrics {
+    counters: Vec<(&'static str, AtomicU64)>,
+    histograms: Vec<(&'static str, Histogram)>,
+    // metrics of other names, e.g. reported by applications sharing the receiver
+    other_counters: Mutex<BTreeMap<&'static str, u64>>,
+    other_histograms: Mutex<BTreeMap<&'static str, Histogram>>,
+}
+
+impl Default for PrometheusMetrics {
+    fn default() -> Self {
+        Self {
+            counters: COUNTERS
+                .iter()
+                .map(|name| (*name, AtomicU64::new(0)))
+                .collect(),
+            histograms: HISTOGRAMS
+                .iter()
+                .map(|name| (*name, Histogram::default()))
+                .collect(),
+            other_counters: Mutex::new(BTreeMap::new()),
+            other_histograms: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl PrometheusMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Render all metrics collected so far, engine metrics are rendered even
+    /// before they are updated
+    pub fn render(&self) -> String {
+        let mut buf = String::new();
+        let counters = self
+            .counters
+            .iter()
+            .map(|(name, value)| (*name, value.load(Ordering::Relaxed)));
+        for (name, value) in counters.chain(self.other_counters.lock().clone()) {
+            let _ = writeln!(buf, "# TYPE {} counter", name);
+            let _ = writeln!(buf, "{} {}", name, value);
+        }
+        for (name, histogram) in self.histograms.iter() {
+            histogram.render(name, &mut buf);
+        }
+        for (name, histogram) in self.other_histograms.lock().iter() {
+            histogram.render(name, &mut buf);
+        }
+        buf
+    }
+}
+
+impl Metrics for PrometheusMetrics {
+    fn incr_counter(&self, name: &'static str, value: u64) {
+        match self.counters.iter().find(|(counter, _)| *counter == name) {
+            Some((_, counter)) => {
+                counter.fetch_add(value, Ordering::Relaxed);
+            }
+            None => *self.other_counters.lock().entry(name).or_default() += value,
+        }
+    }
+
+    fn observe_histogram(&self, name: &'static str, value: f64) {
+        match self
+            .histograms
+            .iter()
+            .find(|(histogram, _)| *histogram == name)
+        {
+            Some((_, histogram)) => histogram.observe(value),
+            None => self
+                .other_histograms
+                .lock()
+                .entry(name)
+                .or_default()
+                .observe(value),
+        }
+    }
+}
+
+// observe elapsed seconds into a histogram when dropped
+pub(crate) struct LatencyTimer<'a> {
+    metrics: &'a dyn Metrics,
+    name: &'static str,
+    start: Instant,
+}
+
+impl<'a> LatencyTimer<'a> {
+    pub(crate) fn new(metrics: &'a dyn Metrics, name: &'static str) -> Self {
+        Self {
+            metrics,
+            name,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Drop for LatencyTimer<'_> {
+    fn drop(&mut self) {
+        self.metrics
+            .observe_histogram(self.name, self.start.elapsed().as_secs_f64());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::{Metrics, PrometheusMetrics, BYTES_WRITTEN_TOTAL, PUT_SECONDS};
+
+    #[test]
+    fn test_render() {
+        let metrics = PrometheusMetrics::new();
+        let text = metrics.render();
+        assert!(text.contains(
+            "# TYPE lightkv_bytes_written_total counter\nlightkv_bytes_written_total 0\n"
+        ));
+        assert!(text.contains("lightkv_put_seconds_count 0\n"));
+
+        metrics.incr_counter(BYTES_WRITTEN_TOTAL, 7);
+        metrics.observe_histogram(PUT_SECONDS, 0.002);
+        let text = metrics.render();
+        assert!(text.contains("lightkv_bytes_written_total 7\n"));
+        assert!(text.contains("lightkv_put_seconds_bucket{le=\"0.005\"} 1\n"));
+        assert!(text.contains("lightkv_put_seconds_count 1\n"));
+
+        metrics.incr_counter("bytes_total", 10);
+        metrics.incr_counter("bytes_total", 5);
+        metrics.observe_histogram("latency_seconds", 0.002);
+        metrics.observe_histogram("latency_seconds", 0.2);
+        metrics.observe_histogram("latency_seconds", 20.0);
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE bytes_total counter\nbytes_total 15\n"));
+        assert!(text.contains("# TYPE latency_seconds histogram\n"));
+        assert!(text.contains("latency_seconds_bucket{le=\"0.001\"} 0\n"));
+        assert!(text.contains("latency_seconds_bucket{le=\"0.005\"} 1\n"));
+        assert!(text.contains("latency_seconds_bucket{le=\"5\"} 2\n"));
+        assert!(text.contains("latency_seconds_bucket{le=\"+Inf\"} 3\n"));
+        assert!(text.contains("latency_seconds_sum 20.202\n"));
+        assert!(text.contains("latency_seconds_count 3\n"));
+    }
+}
@@ -1,4 +1,10 @@
 use std::path::PathBuf;
+use std::str::FromStr;
+use crate::log::LogConfig;
+use std::sync::Arc;
+use crate::merge::MergeOperator;
+use crate::metrics::{Metrics, NoopMetrics};
+use crate::types::LogLevel;
 use serde::{Deserialize,Serialize};
 
 #[derive(Clone)]
@@ -17,9 +23,55 @@ pub struct Options {
 
     // sync write bytes size threshold
     pub sync_bytes_write: usize,
+
+    // sync from a background thread at most every interval milliseconds, 0 disables it
+    pub sync_interval_ms: u64,
+
+    // value compression codec
+    pub compression: CompressionType,
+
+    // values shorter than this are stored uncompressed
+    pub compression_threshold: usize,
+
+    // load data files with mmap when opening engine
+    pub mmap_at_startup: bool,
+
+    // values not shorter than this are stored in blob files, 0 disables it
+    pub blob_threshold: usize,
+
+    // blob file size
+    pub blob_file_size: u64,
+
+    // blob files whose live ratio is below this are rewritten by compaction
+    pub blob_gc_ratio: f32,
+
+    // auto compaction is triggered once dead bytes ratio of an old data file reaches this, 0 disables it
+    pub compact_ratio: f32,
+
+    // how often auto compaction checks dead bytes ratio
+    pub compact_interval_ms: u64,
+
+    // io bandwidth of compaction, 0 means unlimited
+    pub compact_bytes_per_sec: u64,
+
+    // writes are rejected once available disk space is not above this
+    pub min_free_space: u64,
+
+    // open database without modifying its directory, all writes are rejected
+    pub read_only: bool,
+
+    // receiver of latency, io and compaction metrics
+    pub metrics: Arc<dyn Metrics>,
+
+    // install a stderr logger of this level when opening engine, none keeps the logger of application
+    pub log_level: Option<LogLevel>,
+
+    // operators of `Engine::merge` besides the built-in incr and append operators
+    pub merge_operators: Vec<Arc<dyn MergeOperator>>,
 }
 
-#[derive(Clone)]
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all="lowercase")]
 pub enum IndexType {
     BTree,
     BPlusTree,
@@ -33,51 +85,248 @@ impl Default for Options {
             data_file_size: 256 * 1024 * 1024,
             sync_write: false,
             index_type: IndexType::BTree,
-            sync_bytes_write:0, 
+            sync_bytes_write:0,
+            sync_interval_ms: 0,
+            compression: CompressionType::None,
+            compression_threshold: 1024,
+            mmap_at_startup: true,
+            blob_threshold: 0,
+            blob_file_size: 256 * 1024 * 1024,
+            blob_gc_ratio: 0.5,
+            compact_ratio: 0.0,
+            compact_interval_ms: 10 * 1000,
+            compact_bytes_per_sec: 0,
+            min_free_space: 0,
+            read_only: false,
+            metrics: Arc::new(NoopMetrics),
+            log_level: None,
+            merge_operators: Vec::new(),
         }
     }
 }
 
+#[derive(Default)]
 pub struct IteratorOptions{
     pub prefix:Vec<u8>,
     pub reverse:bool,
 }
 
-impl Default for IteratorOptions {
-    fn default() -> Self {
-        Self { prefix: Default::default(), reverse: Default::default() }
-    }
-}
-
 pub struct WriteBatchOptions{
     pub max_batch_size:usize,
     pub sync:bool,
 }
 
-#[derive(Clone,Debug,Serialize,Deserialize,PartialEq)]
+impl Default for WriteBatchOptions {
+    fn default() -> Self {
+        Self { max_batch_size: 10000, sync: true }
+    }
+}
+
+/// Config of server, loaded from toml by `ServerConfig::load`
+#[derive(Clone,Debug,Default,Serialize,Deserialize,PartialEq)]
+#[serde(default,deny_unknown_fields)]
 pub struct ServerConfig{
+    #[serde(rename="network")]
     pub general_config:GeneralConfig,
+
+    // address serving metrics in prometheus text format, none disables it
+    pub metrics_addr:Option<String>,
+
+    // pem certificate chain and private key of quic transport, self-signed if none
+    pub tls_cert:Option<PathBuf>,
+    pub tls_key:Option<PathBuf>,
+
+    pub storage:StorageConfig,
+
+    #[serde(rename="log")]
+    pub log_config:LogConfig,
+
+    pub limits:LimitsConfig,
+}
+
+/// Engine options of server, same as `Options` except runtime only fields
+#[derive(Clone,Debug,Serialize,Deserialize,PartialEq)]
+#[serde(default,deny_unknown_fields)]
+pub struct StorageConfig{
+    pub path:PathBuf,
+    pub data_file_size:u64,
+    pub sync_write:bool,
+    pub sync_bytes_write:usize,
+    pub sync_interval_ms:u64,
+    pub index_type:IndexType,
+    // io of data files loaded at startup, mmap sets `Options::mmap_at_startup`
+    pub io_type:IOType,
+    pub compression:CompressionType,
+    pub compression_threshold:usize,
+    pub blob_threshold:usize,
+    pub blob_file_size:u64,
+    pub blob_gc_ratio:f32,
+    pub compact_ratio:f32,
+    pub compact_interval_ms:u64,
+    pub compact_bytes_per_sec:u64,
+    pub min_free_space:u64,
+    pub read_only:bool,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        let options=Options::default();
+        Self {
+            path: PathBuf::from("lightkv-data"),
+            data_file_size: options.data_file_size,
+            sync_write: options.sync_write,
+            sync_bytes_write: options.sync_bytes_write,
+            sync_interval_ms: options.sync_interval_ms,
+            index_type: options.index_type,
+            io_type: match options.mmap_at_startup {
+                true => IOType::MmapIO,
+                false => IOType::StdIO,
+            },
+            compression: options.compression,
+            compression_threshold: options.compression_threshold,
+            blob_threshold: options.blob_threshold,
+            blob_file_size: options.blob_file_size,
+            blob_gc_ratio: options.blob_gc_ratio,
+            compact_ratio: options.compact_ratio,
+            compact_interval_ms: options.compact_interval_ms,
+            compact_bytes_per_sec: options.compact_bytes_per_sec,
+            min_free_space: options.min_free_space,
+            read_only: options.read_only,
+        }
+    }
+}
+
+impl StorageConfig {
+    /// Engine options of this config, runtime only fields keep their defaults
+    pub fn to_options(&self)->Options{
+        Options{
+            path:self.path.clone(),
+            data_file_size:self.data_file_size,
+            sync_write:self.sync_write,
+            index_type:self.index_type.clone(),
+            sync_bytes_write:self.sync_bytes_write,
+            sync_interval_ms:self.sync_interval_ms,
+            compression:self.compression,
+            compression_threshold:self.compression_threshold,
+            mmap_at_startup:self.io_type==IOType::MmapIO,
+            blob_threshold:self.blob_threshold,
+            blob_file_size:self.blob_file_size,
+            blob_gc_ratio:self.blob_gc_ratio,
+            compact_ratio:self.compact_ratio,
+            compact_interval_ms:self.compact_interval_ms,
+            compact_bytes_per_sec:self.compact_bytes_per_sec,
+            min_free_space:self.min_free_space,
+            read_only:self.read_only,
+            ..Default::default()
+        }
+    }
 }
 
+/// Resource limits of server
 #[derive(Clone,Debug,Serialize,Deserialize,PartialEq)]
+#[serde(default,deny_unknown_fields)]
+pub struct LimitsConfig{
+    // max client connections, 0 means unlimited, a quic connection counts once for all its streams
+    pub max_connections:usize,
+
+    // max bytes of a single request, the connection is closed once a pending request exceeds it
+    pub max_request_size:usize,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self { max_connections: 0, max_request_size: 512 * 1024 * 1024 }
+    }
+}
+
+/// Config of client, loaded from toml by `ClientConfig::load`
+#[derive(Clone,Debug,Serialize,Deserialize,PartialEq)]
+#[serde(default,deny_unknown_fields)]
 pub struct ClientConfig{
+    #[serde(rename="network")]
     pub general_config:GeneralConfig,
+
+    // max tcp connections to server, quic multiplexes requests over one connection
+    pub pool_size:usize,
+
+    pub connect_timeout_ms:u64,
+
+    pub request_timeout_ms:u64,
+
+    // connect attempts after the first one fails, delay doubles from retry_backoff_ms
+    pub max_retries:usize,
+
+    pub retry_backoff_ms:u64,
+
+    // certificate trusted by quic transport in pem or der, such as a self-signed server certificate
+    pub tls_ca:Option<PathBuf>,
+
+    // name the quic server certificate is issued for
+    pub server_name:String,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            general_config: GeneralConfig::default(),
+            pool_size: 8,
+            connect_timeout_ms: 3 * 1000,
+            request_timeout_ms: 10 * 1000,
+            max_retries: 3,
+            retry_backoff_ms: 100,
+            tls_ca: None,
+            server_name: "localhost".to_string(),
+        }
+    }
 }
 
 #[derive(Clone,Debug,Serialize,Deserialize,PartialEq)]
+#[serde(default,deny_unknown_fields)]
 pub struct GeneralConfig{
     pub addr:String,
+    #[serde(rename="type")]
     pub network:NetworkType,
 }
 
+impl Default for GeneralConfig {
+    fn default() -> Self {
+        Self { addr: "127.0.0.1:6380".to_string(), network: NetworkType::Tcp }
+    }
+}
+
 #[derive(Clone,Debug,Serialize,Deserialize,PartialEq)]
+#[serde(rename_all="lowercase")]
 pub enum NetworkType{
     Tcp,
     Quic,
 }
 
-#[derive(Copy, Clone)]
+impl FromStr for NetworkType {
+    type Err=String;
+
+    fn from_str(s:&str)->Result<Self,Self::Err>{
+        match s.to_ascii_lowercase().as_str() {
+            "tcp"=>Ok(NetworkType::Tcp),
+            "quic"=>Ok(NetworkType::Quic),
+            _=>Err(format!("unknown network type {}",s)),
+        }
+    }
+}
+
+#[derive(Copy,Clone,Debug,PartialEq,Serialize,Deserialize)]
 pub enum IOType{
+    #[serde(rename="std")]
     StdIO,
+    #[serde(rename="mmap")]
     MmapIO,
-}
\ No newline at end of file
+}
+
+/// Codec used to compress record values,
+/// Lz4 and Zstd are only available with the matching cargo feature
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all="lowercase")]
+pub enum CompressionType {
+    None,
+    Lz4,
+    Zstd,
+}
@@ -0,0 +1,83 @@
+use parking_lot::{Condvar, Mutex};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// PeriodicTask runs a task from a background thread at a fixed interval
+/// until it is stopped
+pub(crate) struct PeriodicTask {
+    // stopped flag, notified to wake the thread up on stop
+    state: Arc<(Mutex<bool>, Condvar)>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl PeriodicTask {
+    pub(crate) fn start<F>(interval: Duration, task: F) -> Self
+    where
+        F: Fn() + Send + 'static,
+    {
+        let state = Arc::new((Mutex::new(false), Condvar::new()));
+        let thread_state = state.clone();
+        let handle = std::thread::spawn(move || {
+            let (stopped, cond) = &*thread_state;
+            loop {
+                let mut guard = stopped.lock();
+                if !*guard {
+                    cond.wait_for(&mut guard, interval);
+                }
+                if *guard {
+                    break;
+                }
+                drop(guard);
+                task();
+            }
+        });
+
+        Self {
+            state,
+            handle: Mutex::new(Some(handle)),
+        }
+    }
+
+    // stop the background thread and wait until it exits, can be called more than once
+    pub(crate) fn stop(&self) {
+        let (stopped, cond) = &*self.state;
+        *stopped.lock() = true;
+        cond.notify_all();
+        if let Some(handle) = self.handle.lock().take() {
+            // the task itself may drop the last owner and stop from its own thread
+            if handle.thread().id() == std::thread::current().id() {
+                return;
+            }
+            if handle.join().is_err() {
+                log::error!("periodic task thread panicked");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::periodic::PeriodicTask;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_periodic_task() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let task_count = count.clone();
+        let task = PeriodicTask::start(Duration::from_millis(5), move || {
+            task_count.fetch_add(1, Ordering::SeqCst);
+        });
+        std::thread::sleep(Duration::from_millis(100));
+        task.stop();
+        let stopped_count = count.load(Ordering::SeqCst);
+        assert!(stopped_count > 0);
+
+        // no task runs after stop returned
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(count.load(Ordering::SeqCst), stopped_count);
+        task.stop();
+    }
+}
@@ -0,0 +1,272 @@
+use crate::errors::Errors;
+use crate::options::IteratorOptions;
+use crate::redis::meta::{element_key_prefix, Metadata};
+use crate::redis::{normalize_range, RedisDataStructure, RedisDataType};
+use crate::Result;
+use bytes::{BufMut, Bytes};
+
+// bitmaps are split into chunks of this many bytes, a write rewrites one chunk,
+// chunks never written are zero and take no space
+const CHUNK_SIZE: u64 = 4096;
+
+/// Bitwise operation of `RedisDataStructure::bitop`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BitOp {
+    And,
+    Or,
+    Xor,
+    /// Inverts the first source
+    Not,
+}
+
+fn chunk_key(key: &[u8], version: u64, chunk: u64) -> Bytes {
+    let mut buf = element_key_prefix(key, version);
+    buf.put_u64(chunk);
+    buf.freeze()
+}
+
+impl RedisDataStructure {
+    /// Set or clear bit at `offset`, bit 0 is the most significant bit of the
+    /// first byte, return the previous bit
+    pub fn setbit(&self, key: &[u8], offset: u64, value: bool) -> Result<bool> {
+        let _guard = self.lock(key);
+        let mut metadata = self
+            .get_metadata(key, RedisDataType::Bitmap)?
+            .unwrap_or_else(|| Metadata::new(RedisDataType::Bitmap));
+
+        let byte = offset / 8;
+        let chunk = byte / CHUNK_SIZE;
+        let pos = (byte % CHUNK_SIZE) as usize;
+        let mut data = self.read_chunk(key, metadata.version, chunk)?.to_vec();
+        if data.len() <= pos {
+            data.resize(pos + 1, 0);
+        }
+        let mask = 0x80 >> (offset % 8);
+        let old = data[pos] & mask != 0;
+        match value {
+            true => data[pos] |= mask,
+            false => data[pos] &= !mask,
+        }
+        metadata.size = metadata.size.max(byte + 1);
+
+        let batch = self.new_batch()?;
+        batch.put(chunk_key(key, metadata.version, chunk), Bytes::from(data))?;
+        self.put_metadata(&batch, key, &metadata)?;
+        batch.commit()?;
+        Ok(old)
+    }
+
+    /// Bit at `offset`, bits beyond the end are 0
+    pub fn getbit(&self, key: &[u8], offset: u64) -> Result<bool> {
+        let metadata = match self.get_metadata(key, RedisDataType::Bitmap)? {
+            Some(metadata) => metadata,
+            None => return Ok(false),
+        };
+        let byte = offset / 8;
+        let data = self.read_chunk(key, metadata.version, byte / CHUNK_SIZE)?;
+        let pos = (byte % CHUNK_SIZE) as usize;
+        Ok(data
+            .get(pos)
+            .is_some_and(|b| b & (0x80 >> (offset % 8)) != 0))
+    }
+
+    /// Number of set bits within bytes `start` to `stop` inclusive,
+    /// negative positions count from the last byte
+    pub fn bitcount(&self, key: &[u8], start: i64, stop: i64) -> Result<u64> {
+        let metadata = match self.get_metadata(key, RedisDataType::Bitmap)? {
+            Some(metadata) => metadata,
+            None => return Ok(0),
+        };
+        let (start, stop) = match normalize_range(start, stop, metadata.size) {
+            Some(range) => range,
+            None => return Ok(0),
+        };
+
+        let prefix = element_key_prefix(key, metadata.version);
+        let iter = self.engine.iter(IteratorOptions {
+            prefix: prefix.to_vec(),
+            reverse: false,
+        });
+        iter.seek(chunk_key(key, metadata.version, start / CHUNK_SIZE).to_vec());
+        let mut count = 0;
+        while let Some((chunk_key, data)) = iter.next() {
+            let chunk = u64::from_be_bytes(
+                chunk_key[prefix.len()..]
+                    .try_into()
+                    .map_err(|_| Errors::InvalidMetadata)?,
+            );
+            let base = chunk * CHUNK_SIZE;
+            if base > stop {
+                break;
+            }
+            let from = start.saturating_sub(base).min(data.len() as u64) as usize;
+            let to = (stop + 1 - base).min(data.len() as u64) as usize;
+            count += data[from..to]
+                .iter()
+                .map(|b| b.count_ones() as u64)
+                .sum::<u64>();
+        }
+        Ok(count)
+    }
+
+    /// Store the result of `op` over source bitmaps into `dest`, replacing any
+    /// value of it, missing sources are all zero, return the length in bytes
+    /// of the result, the longest of the sources
+    pub fn bitop(&self, op: BitOp, dest: &[u8], keys: &[Bytes]) -> Result<u64> {
+        let mut sources = Vec::with_capacity(keys.len());
+        for key in keys.iter() {
+            let metadata = self.get_metadata(key, RedisDataType::Bitmap)?;
+            sources.push((key, metadata));
+            if op == BitOp::Not {
+                break;
+            }
+        }
+        let len = sources
+            .iter()
+            .filter_map(|(_, metadata)| metadata.as_ref().map(|metadata| metadata.size))
+            .max()
+            .unwrap_or(0);
+
+        let _guard = self.lock(dest);
+        let mut metadata = Metadata::new(RedisDataType::Bitmap);
+        metadata.size = len;
+        let batch = self.new_batch()?;
+        if let Some(previous) = self.find_metadata(dest)? {
+            self.delete_elements(&batch, dest, &previous)?;
+        }
+        if self.plain_exists(dest)? {
+            batch.delete(Bytes::copy_from_slice(dest))?;
+        }
+        for chunk in 0..len.div_ceil(CHUNK_SIZE) {
+            let chunk_len = CHUNK_SIZE.min(len - chunk * CHUNK_SIZE) as usize;
+            let mut result: Option<Vec<u8>> = None;
+            for (key, source) in sources.iter() {
+                let mut data = match source {
+                    Some(source) => self.read_chunk(key, source.version, chunk)?.to_vec(),
+                    None => Vec::new(),
+                };
+                data.resize(chunk_len, 0);
+                result = Some(match result {
+                    None if op == BitOp::Not => data.iter().map(|b| !b).collect(),
+                    None => data,
+                    Some(mut result) => {
+                        for (r, b) in result.iter_mut().zip(data) {
+                            match op {
+                                BitOp::And => *r &= b,
+                                BitOp::Or => *r |= b,
+                                BitOp::Xor => *r ^= b,
+                                BitOp::Not => unreachable!(),
+                            }
+                        }
+                        result
+                    }
+                });
+            }
+            if let Some(result) = result.filter(|result| result.iter().any(|b| *b != 0)) {
+                batch.put(
+                    chunk_key(dest, metadata.version, chunk),
+                    Bytes::from(result),
+                )?;
+            }
+        }
+        self.put_metadata(&batch, dest, &metadata)?;
+        batch.commit()?;
+        Ok(len)
+    }
+
+    // bytes of a chunk, empty if it is never written
+    fn read_chunk(&self, key: &[u8], version: u64, chunk: u64) -> Result<Bytes> {
+        match self.engine.get(chunk_key(key, version, chunk)) {
+            Ok(data) => Ok(data),
+            Err(Errors::KeyNotFound) => Ok(Bytes::new()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::engine_tests::{create_options, remove_db};
+    use crate::engine::Engine;
+    use crate::errors::Errors;
+    use crate::redis::bitmap::{BitOp, CHUNK_SIZE};
+    use crate::redis::meta::ELEMENT_KEY_PREFIX;
+    use crate::redis::{RedisDataStructure, RedisDataType};
+    use bytes::Bytes;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_bitmap() {
+        let options = create_options("redis-bitmap");
+        let redis = RedisDataStructure::new(Arc::new(Engine::open(options.clone()).unwrap()));
+
+        assert!(!redis.setbit(b"bits", 1, true).unwrap());
+        assert!(redis.setbit(b"bits", 1, true).unwrap());
+        redis.setbit(b"bits", 7, true).unwrap();
+        // bit in a far chunk leaves the chunks between unwritten
+        let far = CHUNK_SIZE * 8 * 3 + 5;
+        redis.setbit(b"bits", far, true).unwrap();
+        assert_eq!(
+            redis.key_type(b"bits").unwrap(),
+            Some(RedisDataType::Bitmap)
+        );
+        assert!(redis.getbit(b"bits", 1).unwrap());
+        assert!(!redis.getbit(b"bits", 2).unwrap());
+        assert!(redis.getbit(b"bits", far).unwrap());
+        assert!(!redis.getbit(b"bits", far * 2).unwrap());
+        assert!(!redis.getbit(b"missing", 0).unwrap());
+
+        assert_eq!(redis.bitcount(b"bits", 0, -1).unwrap(), 3);
+        assert_eq!(redis.bitcount(b"bits", 0, 0).unwrap(), 2);
+        assert_eq!(redis.bitcount(b"bits", 1, -2).unwrap(), 0);
+        assert_eq!(redis.bitcount(b"bits", -1, -1).unwrap(), 1);
+        assert_eq!(redis.bitcount(b"missing", 0, -1).unwrap(), 0);
+        assert!(redis.setbit(b"bits", far, false).unwrap());
+        assert_eq!(redis.bitcount(b"bits", 0, -1).unwrap(), 2);
+
+        // a = 0b0100_0001, b = 0b1100_0000 0b0000_0001
+        redis.setbit(b"b", 0, true).unwrap();
+        redis.setbit(b"b", 1, true).unwrap();
+        redis.setbit(b"b", 15, true).unwrap();
+        let sources = [Bytes::from("bits"), Bytes::from("b")];
+        assert_eq!(
+            redis.bitop(BitOp::And, b"dest", &sources).unwrap(),
+            CHUNK_SIZE * 3 + 1
+        );
+        assert_eq!(redis.bitcount(b"dest", 0, -1).unwrap(), 1);
+        assert!(redis.getbit(b"dest", 1).unwrap());
+        redis.bitop(BitOp::Or, b"dest", &sources).unwrap();
+        assert_eq!(redis.bitcount(b"dest", 0, -1).unwrap(), 4);
+        redis.bitop(BitOp::Xor, b"dest", &sources).unwrap();
+        assert_eq!(redis.bitcount(b"dest", 0, -1).unwrap(), 3);
+        assert_eq!(redis.bitop(BitOp::Not, b"dest", &sources[1..]).unwrap(), 2);
+        assert_eq!(redis.bitcount(b"dest", 0, -1).unwrap(), 13);
+
+        // result replaces a value of another type, an empty result removes it
+        let element_count = || {
+            let keys = redis.engine().list_keys().unwrap();
+            keys.iter().filter(|key| key[0] == ELEMENT_KEY_PREFIX).count()
+        };
+        let elements = element_count();
+        redis.rpush(b"list", &[Bytes::from("a")]).unwrap();
+        redis.bitop(BitOp::Or, b"list", &sources[1..]).unwrap();
+        assert_eq!(
+            redis.key_type(b"list").unwrap(),
+            Some(RedisDataType::Bitmap)
+        );
+        assert_eq!(
+            redis
+                .bitop(BitOp::And, b"list", &[Bytes::from("missing")])
+                .unwrap(),
+            0
+        );
+        assert_eq!(redis.key_type(b"list").unwrap(), None);
+        // elements of replaced values are deleted with them
+        assert_eq!(element_count(), elements);
+        redis.rpush(b"list", &[Bytes::from("a")]).unwrap();
+        assert_eq!(redis.getbit(b"list", 0), Err(Errors::WrongType));
+
+        drop(redis);
+        remove_db(&options);
+    }
+}
@@ -0,0 +1,249 @@
+use crate::errors::Errors;
+use crate::options::IteratorOptions;
+use crate::redis::meta::{element_key_prefix, Metadata};
+use crate::redis::{RedisDataStructure, RedisDataType};
+use crate::Result;
+use bytes::{BufMut, Bytes};
+use std::collections::HashSet;
+
+// field the next page starts from and the fields of a page
+type FieldPage = (Option<Bytes>, Vec<(Bytes, Bytes)>);
+
+fn field_key(key: &[u8], version: u64, field: &[u8]) -> Bytes {
+    let mut buf = element_key_prefix(key, version);
+    buf.put_slice(field);
+    buf.freeze()
+}
+
+impl RedisDataStructure {
+    /// Set fields of hash, return the number of fields newly added
+    pub fn hset(&self, key: &[u8], fields: &[(Bytes, Bytes)]) -> Result<usize> {
+        let _guard = self.lock(key);
+        let mut metadata = self
+            .get_metadata(key, RedisDataType::Hash)?
+            .unwrap_or_else(|| Metadata::new(RedisDataType::Hash));
+
+        let batch = self.new_batch()?;
+        // a field repeated in one call is added once
+        let mut added = HashSet::new();
+        for (field, value) in fields.iter() {
+            let field_key = field_key(key, metadata.version, field);
+            if !self.engine.exists(field_key.clone())? {
+                added.insert(field_key.clone());
+            }
+            batch.put(field_key, value.clone())?;
+        }
+        metadata.size += added.len() as u64;
+        self.put_metadata(&batch, key, &metadata)?;
+        batch.commit()?;
+        Ok(added.len())
+    }
+
+    /// Value of a field, none if hash or field does not exist
+    pub fn hget(&self, key: &[u8], field: &[u8]) -> Result<Option<Bytes>> {
+        let metadata = match self.get_metadata(key, RedisDataType::Hash)? {
+            Some(metadata) => metadata,
+            None => return Ok(None),
+        };
+        match self.engine.get(field_key(key, metadata.version, field)) {
+            Ok(value) => Ok(Some(value)),
+            Err(Errors::KeyNotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Delete fields of hash, return the number of fields removed,
+    /// hash is removed with its last field
+    pub fn hdel(&self, key: &[u8], fields: &[Bytes]) -> Result<usize> {
+        let _guard = self.lock(key);
+        let mut metadata = match self.get_metadata(key, RedisDataType::Hash)? {
+            Some(metadata) => metadata,
+            None => return Ok(0),
+        };
+
+        let batch = self.new_batch()?;
+        let mut removed = HashSet::new();
+        for field in fields.iter() {
+            let field_key = field_key(key, metadata.version, field);
+            if self.engine.exists(field_key.clone())? && removed.insert(field_key.clone()) {
+                batch.delete(field_key)?;
+            }
+        }
+        if removed.is_empty() {
+            return Ok(0);
+        }
+        metadata.size -= removed.len() as u64;
+        self.put_metadata(&batch, key, &metadata)?;
+        batch.commit()?;
+        Ok(removed.len())
+    }
+
+    /// All fields and values of hash in field order
+    pub fn hgetall(&self, key: &[u8]) -> Result<Vec<(Bytes, Bytes)>> {
+        match self.get_metadata(key, RedisDataType::Hash)? {
+            Some(metadata) => Ok(self.scan_elements(&element_key_prefix(key, metadata.version))),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Number of fields of hash
+    pub fn hlen(&self, key: &[u8]) -> Result<u64> {
+        let metadata = self.get_metadata(key, RedisDataType::Hash)?;
+        Ok(metadata.map_or(0, |metadata| metadata.size))
+    }
+
+    /// Up to `count` fields in field order starting from field `cursor`, an
+    /// empty cursor starts from the first field, return the field the next
+    /// page starts from, none at the end, with the fields
+    pub fn hscan(
+        &self,
+        key: &[u8],
+        cursor: &[u8],
+        count: usize,
+    ) -> Result<FieldPage> {
+        let metadata = match self.get_metadata(key, RedisDataType::Hash)? {
+            Some(metadata) => metadata,
+            None => return Ok((None, Vec::new())),
+        };
+        let prefix = element_key_prefix(key, metadata.version);
+        let iter = self.engine.iter(IteratorOptions {
+            prefix: prefix.to_vec(),
+            reverse: false,
+        });
+        iter.seek(field_key(key, metadata.version, cursor).to_vec());
+        let mut fields = Vec::with_capacity(count.min(metadata.size as usize));
+        while let Some(field_key) = iter.next_key() {
+            let field = field_key.slice(prefix.len()..);
+            if fields.len() == count {
+                return Ok((Some(field), fields));
+            }
+            fields.push((field, self.engine.get(field_key)?));
+        }
+        Ok((None, fields))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::engine_tests::{create_options, remove_db};
+    use crate::engine::Engine;
+    use crate::errors::Errors;
+    use crate::redis::{RedisDataStructure, RedisDataType};
+    use bytes::Bytes;
+    use std::sync::Arc;
+
+    fn pair(field: &str, value: &str) -> (Bytes, Bytes) {
+        (
+            Bytes::from(field.to_string()),
+            Bytes::from(value.to_string()),
+        )
+    }
+
+    #[test]
+    fn test_hash() {
+        let options = create_options("redis-hash");
+        let redis = RedisDataStructure::new(Arc::new(Engine::open(options.clone()).unwrap()));
+
+        assert_eq!(
+            redis
+                .hset(b"user", &[pair("name", "alice"), pair("age", "20")])
+                .unwrap(),
+            2
+        );
+        // overwritten and repeated fields are not counted again
+        assert_eq!(
+            redis
+                .hset(
+                    b"user",
+                    &[pair("age", "21"), pair("city", "x"), pair("city", "y")]
+                )
+                .unwrap(),
+            1
+        );
+        assert_eq!(redis.hlen(b"user").unwrap(), 3);
+        assert_eq!(
+            redis.hget(b"user", b"age").unwrap(),
+            Some(Bytes::from("21"))
+        );
+        assert_eq!(
+            redis.hget(b"user", b"city").unwrap(),
+            Some(Bytes::from("y"))
+        );
+        assert_eq!(redis.hget(b"user", b"missing").unwrap(), None);
+        assert_eq!(redis.hget(b"missing", b"age").unwrap(), None);
+        assert_eq!(redis.key_type(b"user").unwrap(), Some(RedisDataType::Hash));
+
+        // fields of a key never mix with a key sharing its prefix
+        redis.hset(b"users", &[pair("name", "bob")]).unwrap();
+        assert_eq!(
+            redis.hgetall(b"user").unwrap(),
+            vec![pair("age", "21"), pair("city", "y"), pair("name", "alice")]
+        );
+        assert_eq!(
+            redis.hscan(b"user", b"", 2).unwrap(),
+            (
+                Some(Bytes::from("name")),
+                vec![pair("age", "21"), pair("city", "y")]
+            )
+        );
+        assert_eq!(
+            redis.hscan(b"user", b"name", 2).unwrap(),
+            (None, vec![pair("name", "alice")])
+        );
+        // a cursor between fields starts from the next one
+        assert_eq!(
+            redis.hscan(b"user", b"b", 1).unwrap(),
+            (Some(Bytes::from("name")), vec![pair("city", "y")])
+        );
+        assert_eq!(redis.hscan(b"missing", b"", 2).unwrap(), (None, vec![]));
+
+        assert_eq!(
+            redis
+                .hdel(
+                    b"user",
+                    &[Bytes::from("age"), Bytes::from("age"), Bytes::from("x")]
+                )
+                .unwrap(),
+            1
+        );
+        assert_eq!(redis.hlen(b"user").unwrap(), 2);
+
+        // a hash name is never shared with a plain string
+        assert!(!redis.set_nx(b"user", Bytes::from("plain")).unwrap());
+        assert_eq!(redis.get(b"user"), Err(Errors::WrongType));
+        assert_eq!(redis.hlen(b"user").unwrap(), 2);
+
+        // hash disappears with its last field, a recreated hash starts empty
+        assert_eq!(
+            redis
+                .hdel(b"user", &[Bytes::from("city"), Bytes::from("name")])
+                .unwrap(),
+            2
+        );
+        assert_eq!(redis.key_type(b"user").unwrap(), None);
+        let elements = redis.engine().list_keys().unwrap().len();
+        redis.hset(b"user", &[pair("name", "carol")]).unwrap();
+        assert!(redis.del(b"user").unwrap());
+        // fields are deleted with the hash
+        assert_eq!(redis.engine().list_keys().unwrap().len(), elements);
+        assert!(!redis.del(b"user").unwrap());
+        redis.hset(b"user", &[pair("age", "30")]).unwrap();
+        assert_eq!(redis.hgetall(b"user").unwrap(), vec![pair("age", "30")]);
+
+        // metadata and fields survive reopen
+        let engine = redis.engine().clone();
+        drop(redis);
+        engine.close().unwrap();
+        drop(engine);
+        let redis = RedisDataStructure::new(Arc::new(Engine::open(options.clone()).unwrap()));
+        assert_eq!(redis.hlen(b"user").unwrap(), 1);
+        assert_eq!(
+            redis.hget(b"users", b"name").unwrap(),
+            Some(Bytes::from("bob"))
+        );
+        assert_eq!(redis.hlen(b"plain").unwrap(), 0);
+
+        drop(redis);
+        remove_db(&options);
+    }
+}
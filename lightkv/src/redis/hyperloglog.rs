@@ -0,0 +1,194 @@
+use crate::errors::Errors;
+use crate::redis::meta::{element_key_prefix, Metadata};
+use crate::redis::{RedisDataStructure, RedisDataType};
+use crate::Result;
+use bytes::Bytes;
+
+// 2^14 registers of one byte each, the standard error is about 0.81%
+const PRECISION: u32 = 14;
+const REGISTERS: usize = 1 << PRECISION;
+
+// registers are kept in one element key under the prefix of the version
+fn registers_key(key: &[u8], version: u64) -> Bytes {
+    element_key_prefix(key, version).freeze()
+}
+
+// 64-bit FNV-1a with the murmur3 finalizer, the hash is persisted with the
+// registers so it must never change
+fn hash64(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in data.iter() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51afd7ed558ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ceb9fe1a85ec53);
+    hash ^ (hash >> 33)
+}
+
+// update register of element, return whether it changed
+fn add_element(registers: &mut [u8], element: &[u8]) -> bool {
+    let hash = hash64(element);
+    let index = (hash & (REGISTERS as u64 - 1)) as usize;
+    // position of the first set bit of the remaining bits, capped by a sentinel
+    let rank = ((hash >> PRECISION) | (1 << (64 - PRECISION))).trailing_zeros() as u8 + 1;
+    if registers[index] < rank {
+        registers[index] = rank;
+        return true;
+    }
+    false
+}
+
+fn estimate(registers: &[u8]) -> u64 {
+    let m = REGISTERS as f64;
+    let alpha = 0.7213 / (1.0 + 1.079 / m);
+    let sum: f64 = registers.iter().map(|r| 2f64.powi(-(*r as i32))).sum();
+    let raw = alpha * m * m / sum;
+    let zeros = registers.iter().filter(|r| **r == 0).count();
+    // linear counting is more accurate for small cardinalities
+    if raw <= 2.5 * m && zeros > 0 {
+        return (m * (m / zeros as f64).ln()).round() as u64;
+    }
+    raw.round() as u64
+}
+
+impl RedisDataStructure {
+    /// Add elements to HyperLogLog, return whether the estimate may have changed
+    pub fn pfadd(&self, key: &[u8], elements: &[Bytes]) -> Result<bool> {
+        let _guard = self.lock(key);
+        let (metadata, mut registers, mut changed) = match self.read_registers(key)? {
+            Some((metadata, registers)) => (metadata, registers, false),
+            None => (new_metadata(), vec![0; REGISTERS], true),
+        };
+        for element in elements.iter() {
+            changed |= add_element(&mut registers, element);
+        }
+        if changed {
+            self.write_registers(key, &metadata, registers)?;
+        }
+        Ok(changed)
+    }
+
+    /// Estimated number of distinct elements added to the union of HyperLogLogs
+    pub fn pfcount(&self, keys: &[Bytes]) -> Result<u64> {
+        let mut union = vec![0; REGISTERS];
+        for key in keys.iter() {
+            if let Some((_, registers)) = self.read_registers(key)? {
+                merge(&mut union, &registers);
+            }
+        }
+        Ok(estimate(&union))
+    }
+
+    /// Merge sources into `dest`, which is created if it does not exist
+    pub fn pfmerge(&self, dest: &[u8], sources: &[Bytes]) -> Result<()> {
+        let _guard = self.lock(dest);
+        let (metadata, mut registers) = self
+            .read_registers(dest)?
+            .unwrap_or_else(|| (new_metadata(), vec![0; REGISTERS]));
+        for source in sources.iter() {
+            if let Some((_, source)) = self.read_registers(source)? {
+                merge(&mut registers, &source);
+            }
+        }
+        self.write_registers(dest, &metadata, registers)
+    }
+
+    fn read_registers(&self, key: &[u8]) -> Result<Option<(Metadata, Vec<u8>)>> {
+        let metadata = match self.get_metadata(key, RedisDataType::HyperLogLog)? {
+            Some(metadata) => metadata,
+            None => return Ok(None),
+        };
+        let registers = self.engine.get(registers_key(key, metadata.version))?;
+        if registers.len() != REGISTERS {
+            return Err(Errors::InvalidMetadata);
+        }
+        Ok(Some((metadata, registers.to_vec())))
+    }
+
+    fn write_registers(&self, key: &[u8], metadata: &Metadata, registers: Vec<u8>) -> Result<()> {
+        let batch = self.new_batch()?;
+        batch.put(registers_key(key, metadata.version), Bytes::from(registers))?;
+        self.put_metadata(&batch, key, metadata)?;
+        batch.commit()
+    }
+}
+
+// size is the number of registers, so the key is kept while it has no elements
+fn new_metadata() -> Metadata {
+    let mut metadata = Metadata::new(RedisDataType::HyperLogLog);
+    metadata.size = REGISTERS as u64;
+    metadata
+}
+
+fn merge(registers: &mut [u8], other: &[u8]) {
+    for (r, o) in registers.iter_mut().zip(other.iter()) {
+        *r = (*r).max(*o);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::engine_tests::{create_options, remove_db};
+    use crate::engine::Engine;
+    use crate::errors::Errors;
+    use crate::redis::{RedisDataStructure, RedisDataType};
+    use bytes::Bytes;
+    use std::sync::Arc;
+
+    fn elements(range: std::ops::Range<usize>) -> Vec<Bytes> {
+        range
+            .map(|i| Bytes::from(format!("element-{}", i)))
+            .collect()
+    }
+
+    fn assert_close(count: u64, expected: u64) {
+        let error = (count as f64 - expected as f64).abs() / expected as f64;
+        assert!(error < 0.02, "count {} expected {}", count, expected);
+    }
+
+    #[test]
+    fn test_hyperloglog() {
+        let options = create_options("redis-hyperloglog");
+        let redis = RedisDataStructure::new(Arc::new(Engine::open(options.clone()).unwrap()));
+
+        // an empty HyperLogLog is still created
+        assert!(redis.pfadd(b"empty", &[]).unwrap());
+        assert_eq!(
+            redis.key_type(b"empty").unwrap(),
+            Some(RedisDataType::HyperLogLog)
+        );
+        assert_eq!(redis.pfcount(&[Bytes::from("empty")]).unwrap(), 0);
+
+        assert!(redis.pfadd(b"a", &elements(0..3)).unwrap());
+        assert!(!redis.pfadd(b"a", &elements(0..3)).unwrap());
+        assert_eq!(redis.pfcount(&[Bytes::from("a")]).unwrap(), 3);
+
+        redis.pfadd(b"a", &elements(0..20000)).unwrap();
+        redis.pfadd(b"b", &elements(10000..30000)).unwrap();
+        assert_close(redis.pfcount(&[Bytes::from("a")]).unwrap(), 20000);
+        assert_close(
+            redis
+                .pfcount(&[Bytes::from("a"), Bytes::from("b")])
+                .unwrap(),
+            30000,
+        );
+
+        redis
+            .pfmerge(b"merged", &[Bytes::from("a"), Bytes::from("b")])
+            .unwrap();
+        assert_close(redis.pfcount(&[Bytes::from("merged")]).unwrap(), 30000);
+        assert_eq!(redis.pfcount(&[Bytes::from("missing")]).unwrap(), 0);
+
+        redis.sadd(b"set", &[Bytes::from("a")]).unwrap();
+        assert_eq!(
+            redis.pfadd(b"set", &[Bytes::from("a")]),
+            Err(Errors::WrongType)
+        );
+
+        drop(redis);
+        remove_db(&options);
+    }
+}
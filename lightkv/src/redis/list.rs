@@ -0,0 +1,221 @@
+use crate::errors::Errors;
+use crate::options::IteratorOptions;
+use crate::redis::meta::{element_key_prefix, Metadata};
+use crate::redis::{normalize_range, RedisDataStructure, RedisDataType};
+use crate::Result;
+use bytes::{BufMut, Bytes};
+
+// elements are ordered by their sequence, so a prefix iteration walks the list
+fn element_key(key: &[u8], version: u64, seq: u64) -> Bytes {
+    let mut buf = element_key_prefix(key, version);
+    buf.put_u64(seq);
+    buf.freeze()
+}
+
+impl RedisDataStructure {
+    /// Insert values at the head one by one, return the length of list
+    pub fn lpush(&self, key: &[u8], values: &[Bytes]) -> Result<u64> {
+        self.push(key, values, true)
+    }
+
+    /// Append values at the tail, return the length of list
+    pub fn rpush(&self, key: &[u8], values: &[Bytes]) -> Result<u64> {
+        self.push(key, values, false)
+    }
+
+    /// Remove and return the first element, none if list does not exist
+    pub fn lpop(&self, key: &[u8]) -> Result<Option<Bytes>> {
+        self.pop(key, true)
+    }
+
+    /// Remove and return the last element, none if list does not exist
+    pub fn rpop(&self, key: &[u8]) -> Result<Option<Bytes>> {
+        self.pop(key, false)
+    }
+
+    /// Elements from `start` to `stop` inclusive, negative indexes count from the tail
+    pub fn lrange(&self, key: &[u8], start: i64, stop: i64) -> Result<Vec<Bytes>> {
+        let metadata = match self.get_metadata(key, RedisDataType::List)? {
+            Some(metadata) => metadata,
+            None => return Ok(Vec::new()),
+        };
+        let (start, stop) = match normalize_range(start, stop, metadata.size) {
+            Some(range) => range,
+            None => return Ok(Vec::new()),
+        };
+
+        let prefix = element_key_prefix(key, metadata.version);
+        let iter = self.engine.iter(IteratorOptions {
+            prefix: prefix.to_vec(),
+            reverse: false,
+        });
+        iter.seek(element_key(key, metadata.version, metadata.head + start).to_vec());
+        let count = (stop - start + 1) as usize;
+        let mut values = Vec::with_capacity(count);
+        while values.len() < count {
+            match iter.next() {
+                Some((_, value)) => values.push(value),
+                None => break,
+            }
+        }
+        Ok(values)
+    }
+
+    /// Number of elements of list
+    pub fn llen(&self, key: &[u8]) -> Result<u64> {
+        let metadata = self.get_metadata(key, RedisDataType::List)?;
+        Ok(metadata.map_or(0, |metadata| metadata.size))
+    }
+
+    /// Element at `index`, negative index counts from the tail
+    pub fn lindex(&self, key: &[u8], index: i64) -> Result<Option<Bytes>> {
+        let metadata = match self.get_metadata(key, RedisDataType::List)? {
+            Some(metadata) => metadata,
+            None => return Ok(None),
+        };
+        let index = if index < 0 {
+            index + metadata.size as i64
+        } else {
+            index
+        };
+        if index < 0 || index >= metadata.size as i64 {
+            return Ok(None);
+        }
+        let seq = metadata.head + index as u64;
+        match self.engine.get(element_key(key, metadata.version, seq)) {
+            Ok(value) => Ok(Some(value)),
+            Err(Errors::KeyNotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn push(&self, key: &[u8], values: &[Bytes], left: bool) -> Result<u64> {
+        let _guard = self.lock(key);
+        let mut metadata = self
+            .get_metadata(key, RedisDataType::List)?
+            .unwrap_or_else(|| Metadata::new(RedisDataType::List));
+
+        let batch = self.new_batch()?;
+        for value in values.iter() {
+            let seq = match left {
+                true => {
+                    metadata.head -= 1;
+                    metadata.head
+                }
+                false => {
+                    metadata.tail += 1;
+                    metadata.tail - 1
+                }
+            };
+            batch.put(element_key(key, metadata.version, seq), value.clone())?;
+        }
+        metadata.size += values.len() as u64;
+        self.put_metadata(&batch, key, &metadata)?;
+        batch.commit()?;
+        Ok(metadata.size)
+    }
+
+    fn pop(&self, key: &[u8], left: bool) -> Result<Option<Bytes>> {
+        let _guard = self.lock(key);
+        let mut metadata = match self.get_metadata(key, RedisDataType::List)? {
+            Some(metadata) => metadata,
+            None => return Ok(None),
+        };
+
+        let seq = match left {
+            true => metadata.head,
+            false => metadata.tail - 1,
+        };
+        let element_key = element_key(key, metadata.version, seq);
+        let value = self.engine.get(element_key.clone())?;
+        match left {
+            true => metadata.head += 1,
+            false => metadata.tail -= 1,
+        }
+        metadata.size -= 1;
+
+        let batch = self.new_batch()?;
+        batch.delete(element_key)?;
+        self.put_metadata(&batch, key, &metadata)?;
+        batch.commit()?;
+        Ok(Some(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::engine_tests::{create_options, remove_db};
+    use crate::engine::Engine;
+    use crate::errors::Errors;
+    use crate::redis::{RedisDataStructure, RedisDataType};
+    use bytes::Bytes;
+    use std::sync::Arc;
+
+    fn values(values: &[&str]) -> Vec<Bytes> {
+        values
+            .iter()
+            .map(|value| Bytes::from(value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_list() {
+        let options = create_options("redis-list");
+        let redis = RedisDataStructure::new(Arc::new(Engine::open(options.clone()).unwrap()));
+
+        assert_eq!(redis.rpush(b"list", &values(&["c", "d"])).unwrap(), 2);
+        assert_eq!(redis.lpush(b"list", &values(&["b", "a"])).unwrap(), 4);
+        assert_eq!(redis.llen(b"list").unwrap(), 4);
+        assert_eq!(redis.key_type(b"list").unwrap(), Some(RedisDataType::List));
+        assert_eq!(
+            redis.lrange(b"list", 0, -1).unwrap(),
+            values(&["a", "b", "c", "d"])
+        );
+        assert_eq!(redis.lrange(b"list", 1, 2).unwrap(), values(&["b", "c"]));
+        assert_eq!(redis.lrange(b"list", -2, 100).unwrap(), values(&["c", "d"]));
+        assert_eq!(redis.lrange(b"list", -100, 0).unwrap(), values(&["a"]));
+        assert!(redis.lrange(b"list", 3, 1).unwrap().is_empty());
+        assert!(redis.lrange(b"list", 4, 10).unwrap().is_empty());
+        assert!(redis.lrange(b"missing", 0, -1).unwrap().is_empty());
+        assert_eq!(redis.lindex(b"list", 0).unwrap(), Some(Bytes::from("a")));
+        assert_eq!(redis.lindex(b"list", -1).unwrap(), Some(Bytes::from("d")));
+        assert_eq!(redis.lindex(b"list", 4).unwrap(), None);
+        assert_eq!(redis.lindex(b"list", -5).unwrap(), None);
+
+        // elements of another list never show up in a range
+        redis.rpush(b"list2", &values(&["x"])).unwrap();
+        assert_eq!(redis.lpop(b"list").unwrap(), Some(Bytes::from("a")));
+        assert_eq!(redis.rpop(b"list").unwrap(), Some(Bytes::from("d")));
+        assert_eq!(redis.lrange(b"list", 0, -1).unwrap(), values(&["b", "c"]));
+
+        // key holding another type
+        assert_eq!(redis.lpush(b"hash", &values(&["a"])).unwrap(), 1);
+        let fields = [(Bytes::from("f"), Bytes::from("v"))];
+        assert_eq!(redis.hset(b"hash", &fields), Err(Errors::WrongType));
+        assert_eq!(redis.hget(b"list", b"f"), Err(Errors::WrongType));
+
+        // list disappears with its last element
+        assert_eq!(redis.rpop(b"list").unwrap(), Some(Bytes::from("c")));
+        assert_eq!(redis.rpop(b"list").unwrap(), Some(Bytes::from("b")));
+        assert_eq!(redis.rpop(b"list").unwrap(), None);
+        assert_eq!(redis.key_type(b"list").unwrap(), None);
+
+        // head and tail survive reopen
+        redis.rpush(b"list", &values(&["b"])).unwrap();
+        redis.lpush(b"list", &values(&["a"])).unwrap();
+        let engine = redis.engine().clone();
+        drop(redis);
+        engine.close().unwrap();
+        drop(engine);
+        let redis = RedisDataStructure::new(Arc::new(Engine::open(options.clone()).unwrap()));
+        redis.rpush(b"list", &values(&["c"])).unwrap();
+        assert_eq!(
+            redis.lrange(b"list", 0, -1).unwrap(),
+            values(&["a", "b", "c"])
+        );
+        assert_eq!(redis.lrange(b"list2", 0, -1).unwrap(), values(&["x"]));
+
+        drop(redis);
+        remove_db(&options);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::errors::Errors;
+use crate::redis::RedisDataType;
+use crate::table::{TABLE_DATA_PREFIX, TABLE_META_PREFIX};
+use crate::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use prost::encoding::{decode_varint, encode_varint};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// keys of data types are kept apart from plain string keys by a leading byte
+pub(crate) const META_KEY_PREFIX: u8 = 0xfe;
+pub(crate) const ELEMENT_KEY_PREFIX: u8 = 0xff;
+// lists start in the middle of the sequence space to grow on both ends
+const INITIAL_LIST_SEQ: u64 = u64::MAX / 2;
+
+/// Metadata of a data type value, stored under `META_KEY_PREFIX` and the user key
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Metadata {
+    pub(crate) data_type: RedisDataType,
+    // elements of a recreated key get a new version, so stale elements of
+    // a deleted key are never visible again
+    pub(crate) version: u64,
+    // number of elements, bytes of a bitmap
+    pub(crate) size: u64,
+    // sequence of the first element and the one after the last element of a
+    // list, kept only by lists
+    pub(crate) head: u64,
+    pub(crate) tail: u64,
+}
+
+impl Metadata {
+    pub(crate) fn new(data_type: RedisDataType) -> Self {
+        let version = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time before unix epoch")
+            .as_nanos() as u64;
+        Self {
+            data_type,
+            version,
+            size: 0,
+            head: INITIAL_LIST_SEQ,
+            tail: INITIAL_LIST_SEQ,
+        }
+    }
+
+    pub(crate) fn encode(&self) -> Bytes {
+        let mut buf = BytesMut::new();
+        buf.put_u8(self.data_type as u8);
+        encode_varint(self.version, &mut buf);
+        encode_varint(self.size, &mut buf);
+        if self.data_type == RedisDataType::List {
+            buf.put_u64(self.head);
+            buf.put_u64(self.tail);
+        }
+        buf.freeze()
+    }
+
+    pub(crate) fn decode(mut buf: Bytes) -> Result<Self> {
+        if !buf.has_remaining() {
+            return Err(Errors::InvalidMetadata);
+        }
+        let data_type = RedisDataType::from_u8(buf.get_u8()).ok_or(Errors::InvalidMetadata)?;
+        let version = decode_varint(&mut buf).map_err(|_| Errors::InvalidMetadata)?;
+        let size = decode_varint(&mut buf).map_err(|_| Errors::InvalidMetadata)?;
+        let (mut head, mut tail) = (INITIAL_LIST_SEQ, INITIAL_LIST_SEQ);
+        if data_type == RedisDataType::List {
+            if buf.remaining() < 16 {
+                return Err(Errors::InvalidMetadata);
+            }
+            head = buf.get_u64();
+            tail = buf.get_u64();
+        }
+        Ok(Self {
+            data_type,
+            version,
+            size,
+            head,
+            tail,
+        })
+    }
+}
+
+pub(crate) fn meta_key(key: &[u8]) -> Bytes {
+    let mut buf = BytesMut::with_capacity(key.len() + 1);
+    buf.put_u8(META_KEY_PREFIX);
+    buf.put_slice(key);
+    buf.freeze()
+}
+
+/// Common prefix of element keys of one version of a key, the key is length
+/// prefixed so elements of `a` and `ab` never share a prefix
+pub(crate) fn element_key_prefix(key: &[u8], version: u64) -> BytesMut {
+    let mut buf = BytesMut::with_capacity(key.len() + 18);
+    buf.put_u8(ELEMENT_KEY_PREFIX);
+    encode_varint(key.len() as u64, &mut buf);
+    buf.put_slice(key);
+    buf.put_u64(version);
+    buf
+}
+
+/// Whether key is stored by a data type or a typed table rather than set by user
+pub(crate) fn is_internal_key(key: &[u8]) -> bool {
+    matches!(
+        key.first(),
+        Some(&META_KEY_PREFIX)
+            | Some(&ELEMENT_KEY_PREFIX)
+            | Some(&TABLE_META_PREFIX)
+            | Some(&TABLE_DATA_PREFIX)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::meta::{element_key_prefix, Metadata};
+    use crate::redis::RedisDataType;
+
+    #[test]
+    fn test_metadata_encode_decode() {
+        let mut metadata = Metadata::new(RedisDataType::Hash);
+        metadata.size = 300;
+        assert_eq!(Metadata::decode(metadata.encode()).unwrap(), metadata);
+        let mut metadata = Metadata::new(RedisDataType::List);
+        metadata.head -= 1;
+        metadata.tail += 2;
+        metadata.size = 3;
+        assert_eq!(Metadata::decode(metadata.encode()).unwrap(), metadata);
+        assert!(Metadata::decode(bytes::Bytes::from_static(&[0x42])).is_err());
+
+        // elements of a key never share the prefix of a longer key
+        let prefix = element_key_prefix(b"a", 1);
+        assert!(!element_key_prefix(b"ab", 1).starts_with(&prefix));
+    }
+}
@@ -0,0 +1,245 @@
+//! Redis data types encoded onto the engine keyspace, each key of a data type
+//! has a metadata record and its elements are stored under separate keys,
+//! every mutation commits metadata and elements in one `WriteBatch`,
+//! plain keys starting with byte `0xfe` or `0xff` are reserved for them and
+//! rejected by string commands, a name holds either a plain string or one
+//! data type
+
+mod bitmap;
+mod hash;
+mod hyperloglog;
+mod list;
+pub(crate) mod meta;
+mod set;
+mod string;
+mod zset;
+
+pub use bitmap::BitOp;
+
+use crate::engine::{Engine, WriteBatch};
+use crate::errors::Errors;
+use crate::options::{IteratorOptions, WriteBatchOptions};
+use crate::redis::meta::{element_key_prefix, is_internal_key, meta_key, Metadata, META_KEY_PREFIX};
+use crate::Result;
+use bytes::Bytes;
+use parking_lot::{Mutex, MutexGuard};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+// mutations of keys sharing a stripe are serialized
+const LOCK_STRIPES: usize = 64;
+
+/// Type of value held by a key of `RedisDataStructure`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RedisDataType {
+    Hash = 1,
+    List = 2,
+    Set = 3,
+    ZSet = 4,
+    Bitmap = 5,
+    HyperLogLog = 6,
+}
+
+impl RedisDataType {
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(RedisDataType::Hash),
+            2 => Some(RedisDataType::List),
+            3 => Some(RedisDataType::Set),
+            4 => Some(RedisDataType::ZSet),
+            5 => Some(RedisDataType::Bitmap),
+            6 => Some(RedisDataType::HyperLogLog),
+            _ => None,
+        }
+    }
+
+    /// Name reported by the `TYPE` command
+    pub fn name(&self) -> &'static str {
+        match self {
+            RedisDataType::Hash => "hash",
+            RedisDataType::List => "list",
+            RedisDataType::Set => "set",
+            RedisDataType::ZSet => "zset",
+            // both are strings to redis clients
+            RedisDataType::Bitmap | RedisDataType::HyperLogLog => "string",
+        }
+    }
+}
+
+/// Redis data types over an engine, plain strings are values of the engine
+/// keys of the same name
+pub struct RedisDataStructure {
+    engine: Arc<Engine>,
+    locks: Vec<Mutex<()>>,
+}
+
+impl RedisDataStructure {
+    pub fn new(engine: Arc<Engine>) -> Self {
+        Self {
+            engine,
+            locks: (0..LOCK_STRIPES).map(|_| Mutex::new(())).collect(),
+        }
+    }
+
+    pub fn engine(&self) -> &Arc<Engine> {
+        &self.engine
+    }
+
+    /// Type of value held by key, none if key holds no data type
+    pub fn key_type(&self, key: &[u8]) -> Result<Option<RedisDataType>> {
+        Ok(self.find_metadata(key)?.map(|metadata| metadata.data_type))
+    }
+
+    /// Plain string keys and keys of data types in key order
+    pub fn keys(&self) -> Result<Vec<Bytes>> {
+        self.scan(&[], usize::MAX).map(|(_, keys)| keys)
+    }
+
+    /// Up to `count` keys in key order starting from key `cursor`, an empty
+    /// cursor starts from the first key, return the key the next page starts
+    /// from, none at the end, with the keys
+    pub fn scan(&self, cursor: &[u8], count: usize) -> Result<(Option<Bytes>, Vec<Bytes>)> {
+        // plain keys sort before internal keys, so they end at the first one
+        let plain_iter = self.engine.iter(IteratorOptions::default());
+        plain_iter.seek(cursor.to_vec());
+        let next_plain = || plain_iter.next_key().filter(|key| !is_internal_key(key));
+        let meta_iter = self.engine.iter(IteratorOptions {
+            prefix: vec![META_KEY_PREFIX],
+            reverse: false,
+        });
+        meta_iter.seek(meta_key(cursor).to_vec());
+        let next_meta = || meta_iter.next_key().map(|key| key.slice(1..));
+
+        let (mut plain, mut meta) = (next_plain(), next_meta());
+        let mut keys = Vec::new();
+        loop {
+            let key = match (&plain, &meta) {
+                (None, None) => return Ok((None, keys)),
+                (Some(plain), Some(meta)) if meta < plain => meta.clone(),
+                (Some(plain), _) => plain.clone(),
+                (None, Some(meta)) => meta.clone(),
+            };
+            if keys.len() == count {
+                return Ok((Some(key), keys));
+            }
+            // a name used by both is listed once
+            if plain.as_ref() == Some(&key) {
+                plain = next_plain();
+            }
+            if meta.as_ref() == Some(&key) {
+                meta = next_meta();
+            }
+            keys.push(key);
+        }
+    }
+
+    /// Whether key holds a plain string, reserved keys are never plain strings
+    pub fn plain_exists(&self, key: &[u8]) -> Result<bool> {
+        match is_internal_key(key) {
+            true => Ok(false),
+            false => self.engine.exists(Bytes::copy_from_slice(key)),
+        }
+    }
+
+    /// Delete key of any data type with its elements, return whether it existed
+    pub fn del(&self, key: &[u8]) -> Result<bool> {
+        let _guard = self.lock(key);
+        let metadata = match self.find_metadata(key)? {
+            Some(metadata) => metadata,
+            None => return Ok(false),
+        };
+        let batch = self.new_batch()?;
+        self.delete_elements(&batch, key, &metadata)?;
+        batch.delete(meta_key(key))?;
+        batch.commit()?;
+        Ok(true)
+    }
+
+    // held across read, modify and commit of a key
+    fn lock(&self, key: &[u8]) -> MutexGuard<'_, ()> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        self.locks[hasher.finish() as usize % LOCK_STRIPES].lock()
+    }
+
+    fn find_metadata(&self, key: &[u8]) -> Result<Option<Metadata>> {
+        match self.engine.get(meta_key(key)) {
+            Ok(value) => Metadata::decode(value).map(Some),
+            Err(Errors::KeyNotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    // metadata of key, none if it does not exist, err if key holds another
+    // type or a plain string
+    fn get_metadata(&self, key: &[u8], data_type: RedisDataType) -> Result<Option<Metadata>> {
+        match self.find_metadata(key)? {
+            Some(metadata) if metadata.data_type != data_type => Err(Errors::WrongType),
+            None if self.plain_exists(key)? => Err(Errors::WrongType),
+            metadata => Ok(metadata),
+        }
+    }
+
+    // batch following the sync policy of engine
+    fn new_batch(&self) -> Result<WriteBatch<'_>> {
+        self.engine.new_write_batch(WriteBatchOptions {
+            max_batch_size: usize::MAX,
+            sync: self.engine.sync_write(),
+        })
+    }
+
+    // write metadata with the batch, a key without elements is removed
+    fn put_metadata(&self, batch: &WriteBatch, key: &[u8], metadata: &Metadata) -> Result<()> {
+        match metadata.size {
+            0 => batch.delete(meta_key(key)),
+            _ => batch.put(meta_key(key), metadata.encode()),
+        }
+    }
+
+    // delete elements of a version of key with the batch, so compaction can
+    // reclaim them once the key is deleted or replaced
+    fn delete_elements(&self, batch: &WriteBatch, key: &[u8], metadata: &Metadata) -> Result<()> {
+        let iter = self.engine.iter(IteratorOptions {
+            prefix: element_key_prefix(key, metadata.version).to_vec(),
+            reverse: false,
+        });
+        while let Some(element_key) = iter.next_key() {
+            batch.delete(element_key)?;
+        }
+        Ok(())
+    }
+
+    // values of element keys starting with prefix, in key order
+    fn scan_elements(&self, prefix: &[u8]) -> Vec<(Bytes, Bytes)> {
+        let iter = self.engine.iter(IteratorOptions {
+            prefix: prefix.to_vec(),
+            reverse: false,
+        });
+        let mut elements = Vec::new();
+        while let Some((key, value)) = iter.next() {
+            elements.push((key.slice(prefix.len()..), value));
+        }
+        elements
+    }
+}
+
+// positions from `start` to `stop` inclusive of a sequence of `len` elements,
+// negative indexes count from the end, none if the range is empty
+fn normalize_range(start: i64, stop: i64, len: u64) -> Option<(u64, u64)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (start + len).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        stop + len
+    } else {
+        stop.min(len - 1)
+    };
+    if start > stop || start >= len {
+        return None;
+    }
+    Some((start as u64, stop as u64))
+}
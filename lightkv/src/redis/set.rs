@@ -0,0 +1,142 @@
+use crate::redis::meta::{element_key_prefix, Metadata};
+use crate::redis::{RedisDataStructure, RedisDataType};
+use crate::Result;
+use bytes::{BufMut, Bytes};
+use std::collections::HashSet;
+
+// members are stored as keys with empty values
+fn member_key(key: &[u8], version: u64, member: &[u8]) -> Bytes {
+    let mut buf = element_key_prefix(key, version);
+    buf.put_slice(member);
+    buf.freeze()
+}
+
+impl RedisDataStructure {
+    /// Add members to set, return the number of members newly added
+    pub fn sadd(&self, key: &[u8], members: &[Bytes]) -> Result<usize> {
+        let _guard = self.lock(key);
+        let mut metadata = self
+            .get_metadata(key, RedisDataType::Set)?
+            .unwrap_or_else(|| Metadata::new(RedisDataType::Set));
+
+        let batch = self.new_batch()?;
+        let mut added = HashSet::new();
+        for member in members.iter() {
+            let member_key = member_key(key, metadata.version, member);
+            if !self.engine.exists(member_key.clone())? && added.insert(member_key.clone()) {
+                batch.put(member_key, Bytes::new())?;
+            }
+        }
+        if added.is_empty() {
+            return Ok(0);
+        }
+        metadata.size += added.len() as u64;
+        self.put_metadata(&batch, key, &metadata)?;
+        batch.commit()?;
+        Ok(added.len())
+    }
+
+    /// Remove members from set, return the number of members removed,
+    /// set is removed with its last member
+    pub fn srem(&self, key: &[u8], members: &[Bytes]) -> Result<usize> {
+        let _guard = self.lock(key);
+        let mut metadata = match self.get_metadata(key, RedisDataType::Set)? {
+            Some(metadata) => metadata,
+            None => return Ok(0),
+        };
+
+        let batch = self.new_batch()?;
+        let mut removed = HashSet::new();
+        for member in members.iter() {
+            let member_key = member_key(key, metadata.version, member);
+            if self.engine.exists(member_key.clone())? && removed.insert(member_key.clone()) {
+                batch.delete(member_key)?;
+            }
+        }
+        if removed.is_empty() {
+            return Ok(0);
+        }
+        metadata.size -= removed.len() as u64;
+        self.put_metadata(&batch, key, &metadata)?;
+        batch.commit()?;
+        Ok(removed.len())
+    }
+
+    /// All members of set in member order
+    pub fn smembers(&self, key: &[u8]) -> Result<Vec<Bytes>> {
+        let metadata = match self.get_metadata(key, RedisDataType::Set)? {
+            Some(metadata) => metadata,
+            None => return Ok(Vec::new()),
+        };
+        let prefix = element_key_prefix(key, metadata.version);
+        Ok(self
+            .scan_elements(&prefix)
+            .into_iter()
+            .map(|(member, _)| member)
+            .collect())
+    }
+
+    pub fn sismember(&self, key: &[u8], member: &[u8]) -> Result<bool> {
+        match self.get_metadata(key, RedisDataType::Set)? {
+            Some(metadata) => self
+                .engine
+                .exists(member_key(key, metadata.version, member)),
+            None => Ok(false),
+        }
+    }
+
+    /// Number of members of set
+    pub fn scard(&self, key: &[u8]) -> Result<u64> {
+        let metadata = self.get_metadata(key, RedisDataType::Set)?;
+        Ok(metadata.map_or(0, |metadata| metadata.size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::engine_tests::{create_options, remove_db};
+    use crate::engine::Engine;
+    use crate::errors::Errors;
+    use crate::redis::{RedisDataStructure, RedisDataType};
+    use bytes::Bytes;
+    use std::sync::Arc;
+
+    fn members(members: &[&str]) -> Vec<Bytes> {
+        members
+            .iter()
+            .map(|member| Bytes::from(member.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_set() {
+        let options = create_options("redis-set");
+        let redis = RedisDataStructure::new(Arc::new(Engine::open(options.clone()).unwrap()));
+
+        assert_eq!(redis.sadd(b"set", &members(&["b", "a", "b"])).unwrap(), 2);
+        assert_eq!(redis.sadd(b"set", &members(&["a", "c"])).unwrap(), 1);
+        assert_eq!(redis.sadd(b"set", &members(&["c"])).unwrap(), 0);
+        assert_eq!(redis.scard(b"set").unwrap(), 3);
+        assert_eq!(redis.key_type(b"set").unwrap(), Some(RedisDataType::Set));
+        assert_eq!(redis.smembers(b"set").unwrap(), members(&["a", "b", "c"]));
+        assert!(redis.sismember(b"set", b"a").unwrap());
+        assert!(!redis.sismember(b"set", b"d").unwrap());
+        assert!(!redis.sismember(b"missing", b"a").unwrap());
+        assert_eq!(redis.srem(b"set", &members(&["a", "a", "d"])).unwrap(), 1);
+        assert_eq!(redis.smembers(b"set").unwrap(), members(&["b", "c"]));
+
+        redis.rpush(b"list", &members(&["a"])).unwrap();
+        assert_eq!(
+            redis.sadd(b"list", &members(&["a"])),
+            Err(Errors::WrongType)
+        );
+
+        // set disappears with its last member
+        assert_eq!(redis.srem(b"set", &members(&["b", "c"])).unwrap(), 2);
+        assert_eq!(redis.key_type(b"set").unwrap(), None);
+        assert!(redis.smembers(b"set").unwrap().is_empty());
+
+        drop(redis);
+        remove_db(&options);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::errors::Errors;
+use crate::redis::meta::{is_internal_key, meta_key};
+use crate::redis::RedisDataStructure;
+use crate::Result;
+use bytes::Bytes;
+
+// plain string commands never overwrite or read internal keys of data types and tables
+fn string_key(key: &[u8]) -> Result<Bytes> {
+    match is_internal_key(key) {
+        true => Err(Errors::ReservedKey),
+        false => Ok(Bytes::copy_from_slice(key)),
+    }
+}
+
+// plain strings share names with data types, string commands hold the lock
+// of key so a name never holds both a string and a data type
+impl RedisDataStructure {
+    /// Value of a plain string key, none if it does not exist
+    pub fn get(&self, key: &[u8]) -> Result<Option<Bytes>> {
+        let string_key = string_key(key)?;
+        self.check_no_data_type(key)?;
+        match self.engine.get(string_key) {
+            Ok(value) => Ok(Some(value)),
+            Err(Errors::KeyNotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Set key to a plain string, a data type of key is deleted with its
+    /// elements in the same batch
+    pub fn set(&self, key: &[u8], value: Bytes) -> Result<()> {
+        let string_key = string_key(key)?;
+        let _guard = self.lock(key);
+        self.replace_with_string(key, string_key, value)
+    }
+
+    /// Set key only if it does not exist, return whether it is written
+    pub fn set_nx(&self, key: &[u8], value: Bytes) -> Result<bool> {
+        let string_key = string_key(key)?;
+        let _guard = self.lock(key);
+        if self.find_metadata(key)?.is_some() {
+            return Ok(false);
+        }
+        self.engine.put_if_absent(string_key, value)
+    }
+
+    /// Set key only if it exists, return whether it is written
+    pub fn set_xx(&self, key: &[u8], value: Bytes) -> Result<bool> {
+        let string_key = string_key(key)?;
+        let _guard = self.lock(key);
+        if self.find_metadata(key)?.is_some() {
+            self.replace_with_string(key, string_key, value)?;
+            return Ok(true);
+        }
+        loop {
+            let current = match self.engine.get(string_key.clone()) {
+                Ok(current) => current,
+                Err(Errors::KeyNotFound) => return Ok(false),
+                Err(e) => return Err(e),
+            };
+            if self
+                .engine
+                .compare_and_swap(string_key.clone(), Some(current), Some(value.clone()))?
+            {
+                return Ok(true);
+            }
+        }
+    }
+
+    /// Add `delta` to the decimal integer held by a plain string key
+    pub fn incr_by(&self, key: &[u8], delta: i64) -> Result<i64> {
+        let string_key = string_key(key)?;
+        let _guard = self.lock(key);
+        self.check_no_data_type(key)?;
+        self.engine.incr_by(string_key, delta)
+    }
+
+    /// Append `value` to a plain string key, return the length of the new value
+    pub fn append(&self, key: &[u8], value: Bytes) -> Result<usize> {
+        let string_key = string_key(key)?;
+        let _guard = self.lock(key);
+        self.check_no_data_type(key)?;
+        self.engine.append(string_key, value)
+    }
+
+    fn check_no_data_type(&self, key: &[u8]) -> Result<()> {
+        match self.find_metadata(key)? {
+            Some(_) => Err(Errors::WrongType),
+            None => Ok(()),
+        }
+    }
+
+    fn replace_with_string(&self, key: &[u8], string_key: Bytes, value: Bytes) -> Result<()> {
+        let metadata = match self.find_metadata(key)? {
+            Some(metadata) => metadata,
+            None => return self.engine.put(string_key, value),
+        };
+        let batch = self.new_batch()?;
+        self.delete_elements(&batch, key, &metadata)?;
+        batch.delete(meta_key(key))?;
+        batch.put(string_key, value)?;
+        batch.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::engine_tests::{create_options, remove_db};
+    use crate::engine::Engine;
+    use crate::errors::Errors;
+    use crate::redis::{RedisDataStructure, RedisDataType};
+    use bytes::Bytes;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_string() {
+        let options = create_options("redis-string");
+        let redis = RedisDataStructure::new(Arc::new(Engine::open(options.clone()).unwrap()));
+
+        redis.set(b"name", Bytes::from("alice")).unwrap();
+        assert_eq!(redis.get(b"name").unwrap(), Some(Bytes::from("alice")));
+        assert!(!redis.set_nx(b"name", Bytes::from("bob")).unwrap());
+        assert!(redis.set_xx(b"name", Bytes::from("bob")).unwrap());
+        assert!(!redis.set_xx(b"missing", Bytes::from("bob")).unwrap());
+        assert_eq!(redis.get(b"missing").unwrap(), None);
+        assert_eq!(redis.set(b"\xfekey", Bytes::from("v")).err(), Some(Errors::ReservedKey));
+        assert_eq!(redis.incr_by(b"count", 2).unwrap(), 2);
+        assert_eq!(redis.append(b"name", Bytes::from("!")).unwrap(), 4);
+
+        // a name holds either a plain string or a data type
+        assert_eq!(
+            redis.hset(b"name", &[(Bytes::from("f"), Bytes::from("v"))]).err(),
+            Some(Errors::WrongType)
+        );
+        assert_eq!(redis.hlen(b"name").err(), Some(Errors::WrongType));
+        assert_eq!(redis.key_type(b"name").unwrap(), None);
+        redis.sadd(b"set", &[Bytes::from("a"), Bytes::from("b")]).unwrap();
+        assert_eq!(redis.get(b"set").err(), Some(Errors::WrongType));
+        assert_eq!(redis.incr_by(b"set", 1).err(), Some(Errors::WrongType));
+        assert_eq!(redis.append(b"set", Bytes::from("x")).err(), Some(Errors::WrongType));
+        assert!(!redis.set_nx(b"set", Bytes::from("x")).unwrap());
+
+        // a string replaces a data type with its elements
+        let keys = redis.engine().list_keys().unwrap().len();
+        assert!(redis.set_xx(b"set", Bytes::from("x")).unwrap());
+        assert_eq!(redis.engine().list_keys().unwrap().len(), keys - 2);
+        assert_eq!(redis.key_type(b"set").unwrap(), None);
+        assert_eq!(redis.get(b"set").unwrap(), Some(Bytes::from("x")));
+        redis.engine().remove(Bytes::from("set")).unwrap();
+        redis.lpush(b"set", &[Bytes::from("a")]).unwrap();
+        redis.set(b"set", Bytes::from("y")).unwrap();
+        assert_eq!(redis.key_type(b"set").unwrap(), None);
+        assert_eq!(redis.llen(b"set").err(), Some(Errors::WrongType));
+        assert_eq!(redis.get(b"set").unwrap(), Some(Bytes::from("y")));
+        redis.engine().remove(Bytes::from("set")).unwrap();
+        redis.zadd(b"set", &[(1.0, Bytes::from("a"))]).unwrap();
+        assert_eq!(redis.key_type(b"set").unwrap(), Some(RedisDataType::ZSet));
+
+        // plain strings and data types are scanned together in key order
+        assert_eq!(
+            redis.scan(b"", 2).unwrap(),
+            (
+                Some(Bytes::from("set")),
+                vec![Bytes::from("count"), Bytes::from("name")]
+            )
+        );
+        assert_eq!(redis.scan(b"o", 2).unwrap(), (None, vec![Bytes::from("set")]));
+        assert_eq!(redis.keys().unwrap().len(), 3);
+
+        drop(redis);
+        remove_db(&options);
+    }
+}
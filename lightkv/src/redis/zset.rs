@@ -0,0 +1,362 @@
+use crate::errors::Errors;
+use crate::options::IteratorOptions;
+use crate::redis::meta::{element_key_prefix, Metadata};
+use crate::redis::{normalize_range, RedisDataStructure, RedisDataType};
+use crate::Result;
+use bytes::{BufMut, Bytes, BytesMut};
+use std::collections::{HashMap, HashSet};
+use std::ops::Bound;
+
+// each member has a key holding its score, and a key made of the encoded
+// score and the member so members are iterated in score order
+const MEMBER_TAG: u8 = b'm';
+const SCORE_TAG: u8 = b's';
+
+fn member_key(key: &[u8], version: u64, member: &[u8]) -> Bytes {
+    let mut buf = element_key_prefix(key, version);
+    buf.put_u8(MEMBER_TAG);
+    buf.put_slice(member);
+    buf.freeze()
+}
+
+fn score_key_prefix(key: &[u8], version: u64) -> BytesMut {
+    let mut buf = element_key_prefix(key, version);
+    buf.put_u8(SCORE_TAG);
+    buf
+}
+
+fn score_key(key: &[u8], version: u64, score: f64, member: &[u8]) -> Bytes {
+    let mut buf = score_key_prefix(key, version);
+    buf.put_slice(&encode_score(score));
+    buf.put_slice(member);
+    buf.freeze()
+}
+
+// big endian bytes comparing in the same order as the scores, the sign bit is
+// flipped for positive scores and all bits are flipped for negative ones
+fn encode_score(score: f64) -> [u8; 8] {
+    let bits = score.to_bits();
+    let bits = match bits >> 63 {
+        0 => bits | (1 << 63),
+        _ => !bits,
+    };
+    bits.to_be_bytes()
+}
+
+fn decode_score(buf: &[u8]) -> Result<f64> {
+    let bits = u64::from_be_bytes(buf.try_into().map_err(|_| Errors::InvalidMetadata)?);
+    let bits = match bits >> 63 {
+        1 => bits & !(1 << 63),
+        _ => !bits,
+    };
+    Ok(f64::from_bits(bits))
+}
+
+impl RedisDataStructure {
+    /// Add members with scores or update scores of existing members, return the
+    /// number of members newly added, the last score of a repeated member wins
+    pub fn zadd(&self, key: &[u8], members: &[(f64, Bytes)]) -> Result<usize> {
+        let _guard = self.lock(key);
+        let mut metadata = self
+            .get_metadata(key, RedisDataType::ZSet)?
+            .unwrap_or_else(|| Metadata::new(RedisDataType::ZSet));
+
+        let scores: HashMap<&Bytes, f64> = members
+            .iter()
+            .map(|(score, member)| (member, *score))
+            .collect();
+        let batch = self.new_batch()?;
+        let mut added = 0;
+        for (member, score) in scores.into_iter() {
+            match self.find_score(key, metadata.version, member)? {
+                Some(old_score) if old_score == score => continue,
+                Some(old_score) => {
+                    batch.delete(score_key(key, metadata.version, old_score, member))?
+                }
+                None => added += 1,
+            }
+            batch.put(
+                member_key(key, metadata.version, member),
+                Bytes::copy_from_slice(&encode_score(score)),
+            )?;
+            batch.put(
+                score_key(key, metadata.version, score, member),
+                Bytes::new(),
+            )?;
+        }
+        metadata.size += added as u64;
+        self.put_metadata(&batch, key, &metadata)?;
+        batch.commit()?;
+        Ok(added)
+    }
+
+    /// Remove members, return the number of members removed,
+    /// sorted set is removed with its last member
+    pub fn zrem(&self, key: &[u8], members: &[Bytes]) -> Result<usize> {
+        let _guard = self.lock(key);
+        let mut metadata = match self.get_metadata(key, RedisDataType::ZSet)? {
+            Some(metadata) => metadata,
+            None => return Ok(0),
+        };
+
+        let batch = self.new_batch()?;
+        let mut removed = 0;
+        let mut seen = HashSet::new();
+        for member in members.iter() {
+            if !seen.insert(member) {
+                continue;
+            }
+            if let Some(score) = self.find_score(key, metadata.version, member)? {
+                batch.delete(member_key(key, metadata.version, member))?;
+                batch.delete(score_key(key, metadata.version, score, member))?;
+                removed += 1;
+            }
+        }
+        if removed == 0 {
+            return Ok(0);
+        }
+        metadata.size -= removed as u64;
+        self.put_metadata(&batch, key, &metadata)?;
+        batch.commit()?;
+        Ok(removed)
+    }
+
+    /// Score of member, none if sorted set or member does not exist
+    pub fn zscore(&self, key: &[u8], member: &[u8]) -> Result<Option<f64>> {
+        match self.get_metadata(key, RedisDataType::ZSet)? {
+            Some(metadata) => self.find_score(key, metadata.version, member),
+            None => Ok(None),
+        }
+    }
+
+    /// Number of members of sorted set
+    pub fn zcard(&self, key: &[u8]) -> Result<u64> {
+        let metadata = self.get_metadata(key, RedisDataType::ZSet)?;
+        Ok(metadata.map_or(0, |metadata| metadata.size))
+    }
+
+    /// Members with scores ranked from `start` to `stop` inclusive in score order,
+    /// negative ranks count from the highest score
+    pub fn zrange(&self, key: &[u8], start: i64, stop: i64) -> Result<Vec<(Bytes, f64)>> {
+        let metadata = match self.get_metadata(key, RedisDataType::ZSet)? {
+            Some(metadata) => metadata,
+            None => return Ok(Vec::new()),
+        };
+        let (start, stop) = match normalize_range(start, stop, metadata.size) {
+            Some(range) => range,
+            None => return Ok(Vec::new()),
+        };
+
+        let prefix = score_key_prefix(key, metadata.version);
+        let iter = self.engine.iter(IteratorOptions {
+            prefix: prefix.to_vec(),
+            reverse: false,
+        });
+        let mut members = Vec::new();
+        // only keys of the index are counted, no value is read
+        let mut rank = 0;
+        while let Some(score_key) = iter.next_key() {
+            if rank > stop {
+                break;
+            }
+            if rank >= start {
+                members.push(split_score_key(&score_key, prefix.len())?);
+            }
+            rank += 1;
+        }
+        Ok(members)
+    }
+
+    /// Members with scores within `min` and `max` in score order
+    pub fn zrange_by_score(
+        &self,
+        key: &[u8],
+        min: Bound<f64>,
+        max: Bound<f64>,
+    ) -> Result<Vec<(Bytes, f64)>> {
+        let metadata = match self.get_metadata(key, RedisDataType::ZSet)? {
+            Some(metadata) => metadata,
+            None => return Ok(Vec::new()),
+        };
+
+        let prefix = score_key_prefix(key, metadata.version);
+        let iter = self.engine.iter(IteratorOptions {
+            prefix: prefix.to_vec(),
+            reverse: false,
+        });
+        // start from the lowest score key not below min
+        if let Bound::Included(min) | Bound::Excluded(min) = min {
+            let mut start = prefix.clone();
+            start.put_slice(&encode_score(min));
+            iter.seek(start.to_vec());
+        }
+        let mut members = Vec::new();
+        while let Some((score_key, _)) = iter.next() {
+            let (member, score) = split_score_key(&score_key, prefix.len())?;
+            if matches!(min, Bound::Excluded(min) if score == min) {
+                continue;
+            }
+            match max {
+                Bound::Included(max) if score > max => break,
+                Bound::Excluded(max) if score >= max => break,
+                _ => members.push((member, score)),
+            }
+        }
+        Ok(members)
+    }
+
+    /// Rank of member in score order starting from 0, none if it does not exist
+    pub fn zrank(&self, key: &[u8], member: &[u8]) -> Result<Option<u64>> {
+        let metadata = match self.get_metadata(key, RedisDataType::ZSet)? {
+            Some(metadata) => metadata,
+            None => return Ok(None),
+        };
+        let score = match self.find_score(key, metadata.version, member)? {
+            Some(score) => score,
+            None => return Ok(None),
+        };
+
+        let target = score_key(key, metadata.version, score, member);
+        let iter = self.engine.iter(IteratorOptions {
+            prefix: score_key_prefix(key, metadata.version).to_vec(),
+            reverse: false,
+        });
+        // only keys of the index are counted, no value is read
+        let mut rank = 0;
+        while let Some(score_key) = iter.next_key() {
+            if score_key == target {
+                return Ok(Some(rank));
+            }
+            rank += 1;
+        }
+        Ok(None)
+    }
+
+    fn find_score(&self, key: &[u8], version: u64, member: &[u8]) -> Result<Option<f64>> {
+        match self.engine.get(member_key(key, version, member)) {
+            Ok(value) => decode_score(&value).map(Some),
+            Err(Errors::KeyNotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+// member and score of a score key, the score follows the prefix
+fn split_score_key(score_key: &Bytes, prefix_len: usize) -> Result<(Bytes, f64)> {
+    if score_key.len() < prefix_len + 8 {
+        return Err(Errors::InvalidMetadata);
+    }
+    let score = decode_score(&score_key[prefix_len..prefix_len + 8])?;
+    Ok((score_key.slice(prefix_len + 8..), score))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::engine_tests::{create_options, remove_db};
+    use crate::engine::Engine;
+    use crate::errors::Errors;
+    use crate::redis::zset::{decode_score, encode_score};
+    use crate::redis::{RedisDataStructure, RedisDataType};
+    use bytes::Bytes;
+    use std::ops::Bound;
+    use std::sync::Arc;
+
+    fn member(member: &str, score: f64) -> (Bytes, f64) {
+        (Bytes::from(member.to_string()), score)
+    }
+
+    #[test]
+    fn test_encode_score() {
+        let scores = [
+            f64::NEG_INFINITY,
+            -1e10,
+            -1.5,
+            -0.0,
+            0.0,
+            1e-10,
+            1.0,
+            2.5,
+            1e10,
+            f64::INFINITY,
+        ];
+        for window in scores.windows(2) {
+            assert!(encode_score(window[0]) < encode_score(window[1]));
+        }
+        for score in scores {
+            assert_eq!(decode_score(&encode_score(score)).unwrap(), score);
+        }
+    }
+
+    #[test]
+    fn test_zset() {
+        let options = create_options("redis-zset");
+        let redis = RedisDataStructure::new(Arc::new(Engine::open(options.clone()).unwrap()));
+
+        let members = [
+            (3.0, Bytes::from("c")),
+            (1.0, Bytes::from("a")),
+            (-2.0, Bytes::from("z")),
+            (9.0, Bytes::from("a")),
+            (2.0, Bytes::from("a")),
+        ];
+        assert_eq!(redis.zadd(b"zset", &members).unwrap(), 3);
+        assert_eq!(
+            redis
+                .zadd(b"zset", &[(2.0, Bytes::from("b")), (3.0, Bytes::from("z"))])
+                .unwrap(),
+            1
+        );
+        assert_eq!(redis.zcard(b"zset").unwrap(), 4);
+        assert_eq!(redis.key_type(b"zset").unwrap(), Some(RedisDataType::ZSet));
+        assert_eq!(redis.zscore(b"zset", b"a").unwrap(), Some(2.0));
+        assert_eq!(redis.zscore(b"zset", b"z").unwrap(), Some(3.0));
+        assert_eq!(redis.zscore(b"zset", b"missing").unwrap(), None);
+
+        // members of equal score are ordered by member
+        let all = vec![
+            member("a", 2.0),
+            member("b", 2.0),
+            member("c", 3.0),
+            member("z", 3.0),
+        ];
+        assert_eq!(redis.zrange(b"zset", 0, -1).unwrap(), all);
+        assert_eq!(redis.zrange(b"zset", 1, 2).unwrap(), all[1..3]);
+        assert_eq!(redis.zrange(b"zset", -1, 100).unwrap(), all[3..]);
+        assert!(redis.zrange(b"zset", 2, 1).unwrap().is_empty());
+        assert_eq!(redis.zrank(b"zset", b"c").unwrap(), Some(2));
+        assert_eq!(redis.zrank(b"zset", b"missing").unwrap(), None);
+
+        let by_score = |min, max| redis.zrange_by_score(b"zset", min, max).unwrap();
+        assert_eq!(by_score(Bound::Unbounded, Bound::Unbounded), all);
+        assert_eq!(
+            by_score(Bound::Included(2.0), Bound::Included(2.0)),
+            all[..2]
+        );
+        assert_eq!(by_score(Bound::Excluded(2.0), Bound::Unbounded), all[2..]);
+        assert_eq!(by_score(Bound::Included(2.5), Bound::Excluded(3.0)), vec![]);
+        assert_eq!(by_score(Bound::Unbounded, Bound::Excluded(3.0)), all[..2]);
+
+        assert_eq!(
+            redis
+                .zrem(
+                    b"zset",
+                    &[Bytes::from("a"), Bytes::from("a"), Bytes::from("x")]
+                )
+                .unwrap(),
+            1
+        );
+        assert_eq!(redis.zrange(b"zset", 0, 0).unwrap(), vec![member("b", 2.0)]);
+        assert_eq!(
+            redis.sadd(b"zset", &[Bytes::from("a")]),
+            Err(Errors::WrongType)
+        );
+
+        // sorted set disappears with its last member
+        let rest = [Bytes::from("b"), Bytes::from("c"), Bytes::from("z")];
+        assert_eq!(redis.zrem(b"zset", &rest).unwrap(), 3);
+        assert_eq!(redis.key_type(b"zset").unwrap(), None);
+
+        drop(redis);
+        remove_db(&options);
+    }
+}
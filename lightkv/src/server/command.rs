@@ -0,0 +1,549 @@
+use crate::errors::Errors;
+use crate::redis::{BitOp, RedisDataStructure};
+use crate::server::glob::glob_match;
+use crate::server::resp::Frame;
+use bytes::Bytes;
+use std::ops::Bound;
+
+const DEFAULT_SCAN_COUNT: usize = 10;
+
+/// Run a command against engine and its data types and build its reply
+pub(crate) fn execute(redis: &RedisDataStructure, args: &[Bytes]) -> Frame {
+    let name = String::from_utf8_lossy(&args[0]).to_ascii_lowercase();
+    let result = match name.as_str() {
+        "ping" => ping(args),
+        "echo" => arity(args, 2, 2).map(|_| Frame::Bulk(args[1].clone())),
+        "get" => get(redis, args),
+        "set" => set(redis, args),
+        "incr" => arity(args, 2, 2).and_then(|_| incr_by(redis, &args[1], 1)),
+        "incrby" => arity(args, 3, 3).and_then(|_| incr_by(redis, &args[1], parse_integer(&args[2])?)),
+        "decr" => arity(args, 2, 2).and_then(|_| incr_by(redis, &args[1], -1)),
+        "decrby" => decr_by(redis, args),
+        "append" => append(redis, args),
+        "del" => del(redis, args),
+        "exists" => exists(redis, args),
+        "type" => key_type(redis, args),
+        "keys" => keys(redis, args),
+        "scan" => scan(redis, args),
+        "hset" => hset(redis, args),
+        "hget" => hget(redis, args),
+        "hdel" => hdel(redis, args),
+        "hgetall" => hgetall(redis, args),
+        "hlen" => hlen(redis, args),
+        "hscan" => hscan(redis, args),
+        "lpush" => push(redis, args, true),
+        "rpush" => push(redis, args, false),
+        "lpop" => pop(redis, args, true),
+        "rpop" => pop(redis, args, false),
+        "lrange" => lrange(redis, args),
+        "llen" => llen(redis, args),
+        "lindex" => lindex(redis, args),
+        "sadd" => sadd(redis, args),
+        "srem" => srem(redis, args),
+        "smembers" => smembers(redis, args),
+        "sismember" => sismember(redis, args),
+        "scard" => scard(redis, args),
+        "zadd" => zadd(redis, args),
+        "zrem" => zrem(redis, args),
+        "zscore" => zscore(redis, args),
+        "zcard" => zcard(redis, args),
+        "zrange" => zrange(redis, args),
+        "zrangebyscore" => zrange_by_score(redis, args),
+        "zrank" => zrank(redis, args),
+        "setbit" => setbit(redis, args),
+        "getbit" => getbit(redis, args),
+        "bitcount" => bitcount(redis, args),
+        "bitop" => bitop(redis, args),
+        "pfadd" => pfadd(redis, args),
+        "pfcount" => pfcount(redis, args),
+        "pfmerge" => pfmerge(redis, args),
+        _ => Err(CommandError::Unknown),
+    };
+    match result {
+        Ok(frame) => frame,
+        Err(CommandError::Unknown) => Frame::Error(format!(
+            "ERR unknown command '{}'",
+            String::from_utf8_lossy(&args[0])
+        )),
+        Err(CommandError::Arity) => Frame::Error(format!(
+            "ERR wrong number of arguments for '{}' command",
+            name
+        )),
+        Err(CommandError::Syntax) => Frame::Error("ERR syntax error".to_string()),
+        Err(CommandError::NotInteger) => {
+            Frame::Error("ERR value is not an integer or out of range".to_string())
+        }
+        Err(CommandError::NotFloat) => Frame::Error("ERR value is not a valid float".to_string()),
+        Err(CommandError::Invalid(message)) => Frame::Error(format!("ERR {}", message)),
+        Err(CommandError::Engine(Errors::WrongType)) => Frame::Error(
+            "WRONGTYPE Operation against a key holding the wrong kind of value".to_string(),
+        ),
+        Err(CommandError::Engine(e)) => Frame::Error(format!("ERR {}", e)),
+    }
+}
+
+enum CommandError {
+    Unknown,
+    Arity,
+    Syntax,
+    NotInteger,
+    NotFloat,
+    // invalid argument described by the message
+    Invalid(&'static str),
+    Engine(Errors),
+}
+
+impl From<Errors> for CommandError {
+    fn from(e: Errors) -> Self {
+        CommandError::Engine(e)
+    }
+}
+
+type CommandResult = std::result::Result<Frame, CommandError>;
+
+// check number of arguments including command name, max of 0 means unlimited
+fn arity(args: &[Bytes], min: usize, max: usize) -> std::result::Result<(), CommandError> {
+    if args.len() < min || (max > 0 && args.len() > max) {
+        return Err(CommandError::Arity);
+    }
+    Ok(())
+}
+
+fn parse_integer(arg: &Bytes) -> std::result::Result<i64, CommandError> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|arg| arg.parse::<i64>().ok())
+        .ok_or(CommandError::NotInteger)
+}
+
+// scores may be `inf` or `-inf` but never NaN
+fn parse_float(arg: &Bytes) -> std::result::Result<f64, CommandError> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|arg| arg.parse::<f64>().ok())
+        .filter(|value| !value.is_nan())
+        .ok_or(CommandError::NotFloat)
+}
+
+fn ping(args: &[Bytes]) -> CommandResult {
+    arity(args, 1, 2)?;
+    match args.get(1) {
+        Some(message) => Ok(Frame::Bulk(message.clone())),
+        None => Ok(Frame::Simple("PONG".to_string())),
+    }
+}
+
+fn get(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 2, 2)?;
+    Ok(redis.get(&args[1])?.map_or(Frame::Null, Frame::Bulk))
+}
+
+// NX writes only a missing key, XX only an existing one,
+// a conditional write not performed replies null, a data type of key is replaced
+fn set(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 3, 0)?;
+    if args.len() > 4 {
+        return Err(CommandError::Syntax);
+    }
+    let condition = args
+        .get(3)
+        .map(|arg| String::from_utf8_lossy(arg).to_ascii_lowercase());
+    let (key, value) = (&args[1], args[2].clone());
+    let written = match condition.as_deref() {
+        None => {
+            redis.set(key, value)?;
+            true
+        }
+        Some("nx") => redis.set_nx(key, value)?,
+        Some("xx") => redis.set_xx(key, value)?,
+        Some(_) => return Err(CommandError::Syntax),
+    };
+    match written {
+        true => Ok(Frame::Simple("OK".to_string())),
+        false => Ok(Frame::Null),
+    }
+}
+
+// counters are plain values holding decimal integers, updated by merge operands
+fn incr_by(redis: &RedisDataStructure, key: &Bytes, delta: i64) -> CommandResult {
+    Ok(Frame::Integer(redis.incr_by(key, delta)?))
+}
+
+fn decr_by(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 3, 3)?;
+    let delta = parse_integer(&args[2])?
+        .checked_neg()
+        .ok_or(Errors::IntegerOverflow)?;
+    incr_by(redis, &args[1], delta)
+}
+
+fn append(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 3, 3)?;
+    let len = redis.append(&args[1], args[2].clone())?;
+    Ok(Frame::Integer(len as i64))
+}
+
+// a key holds either a plain string or a data type
+fn del(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 2, 0)?;
+    let mut count = 0;
+    for key in args[1..].iter() {
+        if redis.plain_exists(key)? {
+            redis.engine().remove(key.clone())?;
+            count += 1;
+        } else if redis.del(key)? {
+            count += 1;
+        }
+    }
+    Ok(Frame::Integer(count))
+}
+
+fn exists(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 2, 0)?;
+    let mut count = 0;
+    for key in args[1..].iter() {
+        if redis.plain_exists(key)? || redis.key_type(key)?.is_some() {
+            count += 1;
+        }
+    }
+    Ok(Frame::Integer(count))
+}
+
+fn key_type(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 2, 2)?;
+    let name = match redis.key_type(&args[1])? {
+        Some(data_type) => data_type.name(),
+        None if redis.plain_exists(&args[1])? => "string",
+        None => "none",
+    };
+    Ok(Frame::Simple(name.to_string()))
+}
+
+fn keys(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 2, 2)?;
+    let keys = redis
+        .keys()?
+        .into_iter()
+        .filter(|key| glob_match(&args[1], key))
+        .map(Frame::Bulk)
+        .collect();
+    Ok(Frame::Array(keys))
+}
+
+// cursor is the hex encoded key or field a page starts from, 0 starts the iteration
+fn parse_cursor(arg: &Bytes) -> std::result::Result<Vec<u8>, CommandError> {
+    match arg.as_ref() {
+        b"0" => Ok(Vec::new()),
+        cursor => decode_hex(cursor).ok_or(CommandError::Invalid("invalid cursor")),
+    }
+}
+
+fn parse_scan_options(args: &[Bytes]) -> std::result::Result<(Option<Bytes>, usize), CommandError> {
+    let mut pattern = None;
+    let mut count = DEFAULT_SCAN_COUNT;
+    for option in args.chunks(2) {
+        if option.len() != 2 {
+            return Err(CommandError::Syntax);
+        }
+        match String::from_utf8_lossy(&option[0]).to_ascii_lowercase().as_str() {
+            "match" => pattern = Some(option[1].clone()),
+            "count" => match parse_integer(&option[1])? {
+                count_arg if count_arg > 0 => count = count_arg as usize,
+                _ => return Err(CommandError::Syntax),
+            },
+            _ => return Err(CommandError::Syntax),
+        }
+    }
+    Ok((pattern, count))
+}
+
+// cursor is the hex encoded key the page starts from, 0 starts and ends the iteration
+fn scan(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 2, 0)?;
+    let cursor = parse_cursor(&args[1])?;
+    let (pattern, count) = parse_scan_options(&args[2..])?;
+    let (next_key, keys) = redis.scan(&cursor, count)?;
+    let next_cursor = next_key.map_or_else(|| "0".to_string(), |key| encode_hex(&key));
+    let matched = keys
+        .into_iter()
+        .filter(|key| pattern.as_ref().is_none_or(|pattern| glob_match(pattern, key)))
+        .map(Frame::Bulk)
+        .collect();
+    Ok(Frame::Array(vec![
+        Frame::Bulk(Bytes::from(next_cursor)),
+        Frame::Array(matched),
+    ]))
+}
+
+fn hset(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 4, 0)?;
+    if !args.len().is_multiple_of(2) {
+        return Err(CommandError::Arity);
+    }
+    let fields: Vec<(Bytes, Bytes)> = args[2..]
+        .chunks(2)
+        .map(|pair| (pair[0].clone(), pair[1].clone()))
+        .collect();
+    Ok(Frame::Integer(redis.hset(&args[1], &fields)? as i64))
+}
+
+fn hget(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 3, 3)?;
+    match redis.hget(&args[1], &args[2])? {
+        Some(value) => Ok(Frame::Bulk(value)),
+        None => Ok(Frame::Null),
+    }
+}
+
+fn hdel(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 3, 0)?;
+    Ok(Frame::Integer(redis.hdel(&args[1], &args[2..])? as i64))
+}
+
+fn hgetall(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 2, 2)?;
+    let fields = redis
+        .hgetall(&args[1])?
+        .into_iter()
+        .map(|(field, value)| (Frame::Bulk(field), Frame::Bulk(value)))
+        .collect();
+    Ok(Frame::Map(fields))
+}
+
+fn hlen(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 2, 2)?;
+    Ok(Frame::Integer(redis.hlen(&args[1])? as i64))
+}
+
+// cursor is the hex encoded field the page starts from, 0 starts and ends the iteration
+fn hscan(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 3, 0)?;
+    let cursor = parse_cursor(&args[2])?;
+    let (pattern, count) = parse_scan_options(&args[3..])?;
+    let (next_field, fields) = redis.hscan(&args[1], &cursor, count)?;
+    let next_cursor = next_field.map_or_else(|| "0".to_string(), |field| encode_hex(&field));
+    let matched = fields
+        .into_iter()
+        .filter(|(field, _)| pattern.as_ref().is_none_or(|pattern| glob_match(pattern, field)))
+        .flat_map(|(field, value)| [Frame::Bulk(field), Frame::Bulk(value)])
+        .collect();
+    Ok(Frame::Array(vec![
+        Frame::Bulk(Bytes::from(next_cursor)),
+        Frame::Array(matched),
+    ]))
+}
+
+fn encode_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_hex(data: &[u8]) -> Option<Vec<u8>> {
+    if !data.len().is_multiple_of(2) {
+        return None;
+    }
+    data.chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+fn push(redis: &RedisDataStructure, args: &[Bytes], left: bool) -> CommandResult {
+    arity(args, 3, 0)?;
+    let len = match left {
+        true => redis.lpush(&args[1], &args[2..])?,
+        false => redis.rpush(&args[1], &args[2..])?,
+    };
+    Ok(Frame::Integer(len as i64))
+}
+
+fn pop(redis: &RedisDataStructure, args: &[Bytes], left: bool) -> CommandResult {
+    arity(args, 2, 2)?;
+    let value = match left {
+        true => redis.lpop(&args[1])?,
+        false => redis.rpop(&args[1])?,
+    };
+    Ok(value.map_or(Frame::Null, Frame::Bulk))
+}
+
+fn lrange(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 4, 4)?;
+    let values = redis.lrange(&args[1], parse_integer(&args[2])?, parse_integer(&args[3])?)?;
+    Ok(Frame::Array(values.into_iter().map(Frame::Bulk).collect()))
+}
+
+fn llen(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 2, 2)?;
+    Ok(Frame::Integer(redis.llen(&args[1])? as i64))
+}
+
+fn lindex(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 3, 3)?;
+    let value = redis.lindex(&args[1], parse_integer(&args[2])?)?;
+    Ok(value.map_or(Frame::Null, Frame::Bulk))
+}
+
+fn sadd(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 3, 0)?;
+    Ok(Frame::Integer(redis.sadd(&args[1], &args[2..])? as i64))
+}
+
+fn srem(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 3, 0)?;
+    Ok(Frame::Integer(redis.srem(&args[1], &args[2..])? as i64))
+}
+
+fn smembers(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 2, 2)?;
+    let members = redis.smembers(&args[1])?;
+    Ok(Frame::Set(members.into_iter().map(Frame::Bulk).collect()))
+}
+
+fn sismember(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 3, 3)?;
+    Ok(Frame::Integer(redis.sismember(&args[1], &args[2])? as i64))
+}
+
+fn scard(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 2, 2)?;
+    Ok(Frame::Integer(redis.scard(&args[1])? as i64))
+}
+
+fn zadd(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 4, 0)?;
+    if !args.len().is_multiple_of(2) {
+        return Err(CommandError::Syntax);
+    }
+    let members = args[2..]
+        .chunks(2)
+        .map(|pair| Ok((parse_float(&pair[0])?, pair[1].clone())))
+        .collect::<std::result::Result<Vec<_>, CommandError>>()?;
+    Ok(Frame::Integer(redis.zadd(&args[1], &members)? as i64))
+}
+
+fn zrem(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 3, 0)?;
+    Ok(Frame::Integer(redis.zrem(&args[1], &args[2..])? as i64))
+}
+
+fn zscore(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 3, 3)?;
+    Ok(redis.zscore(&args[1], &args[2])?.map_or(Frame::Null, Frame::Double))
+}
+
+fn zcard(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 2, 2)?;
+    Ok(Frame::Integer(redis.zcard(&args[1])? as i64))
+}
+
+// whether the optional argument after the range is WITHSCORES
+fn with_scores(args: &[Bytes]) -> std::result::Result<bool, CommandError> {
+    match args.get(4) {
+        Some(option) if option.eq_ignore_ascii_case(b"withscores") => Ok(true),
+        Some(_) => Err(CommandError::Syntax),
+        None => Ok(false),
+    }
+}
+
+fn members_reply(members: Vec<(Bytes, f64)>, with_scores: bool) -> Frame {
+    let members = members
+        .into_iter()
+        .flat_map(|(member, score)| {
+            let score = with_scores.then_some(Frame::Double(score));
+            std::iter::once(Frame::Bulk(member)).chain(score)
+        })
+        .collect();
+    Frame::Array(members)
+}
+
+fn zrange(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 4, 5)?;
+    let with_scores = with_scores(args)?;
+    let members = redis.zrange(&args[1], parse_integer(&args[2])?, parse_integer(&args[3])?)?;
+    Ok(members_reply(members, with_scores))
+}
+
+// score bound is inclusive unless prefixed by `(`
+fn parse_score_bound(arg: &Bytes) -> std::result::Result<Bound<f64>, CommandError> {
+    match arg.strip_prefix(b"(") {
+        Some(score) => Ok(Bound::Excluded(parse_float(&Bytes::copy_from_slice(score))?)),
+        None => Ok(Bound::Included(parse_float(arg)?)),
+    }
+}
+
+fn zrange_by_score(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 4, 5)?;
+    let with_scores = with_scores(args)?;
+    let (min, max) = (parse_score_bound(&args[2])?, parse_score_bound(&args[3])?);
+    let members = redis.zrange_by_score(&args[1], min, max)?;
+    Ok(members_reply(members, with_scores))
+}
+
+fn zrank(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 3, 3)?;
+    let rank = redis.zrank(&args[1], &args[2])?;
+    Ok(rank.map_or(Frame::Null, |rank| Frame::Integer(rank as i64)))
+}
+
+// offsets are limited to 2^32 bits like redis strings
+fn parse_bit_offset(arg: &Bytes) -> std::result::Result<u64, CommandError> {
+    match parse_integer(arg) {
+        Ok(offset) if (0..1 << 32).contains(&offset) => Ok(offset as u64),
+        _ => Err(CommandError::Invalid("bit offset is not an integer or out of range")),
+    }
+}
+
+fn setbit(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 4, 4)?;
+    let offset = parse_bit_offset(&args[2])?;
+    let value = match &args[3][..] {
+        b"0" => false,
+        b"1" => true,
+        _ => return Err(CommandError::Invalid("bit is not an integer or out of range")),
+    };
+    Ok(Frame::Integer(redis.setbit(&args[1], offset, value)? as i64))
+}
+
+fn getbit(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 3, 3)?;
+    let offset = parse_bit_offset(&args[2])?;
+    Ok(Frame::Integer(redis.getbit(&args[1], offset)? as i64))
+}
+
+// BITCOUNT key [start end], positions are bytes
+fn bitcount(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 2, 4)?;
+    let (start, stop) = match args.len() {
+        2 => (0, -1),
+        4 => (parse_integer(&args[2])?, parse_integer(&args[3])?),
+        _ => return Err(CommandError::Syntax),
+    };
+    Ok(Frame::Integer(redis.bitcount(&args[1], start, stop)? as i64))
+}
+
+fn bitop(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 4, 0)?;
+    let op = match String::from_utf8_lossy(&args[1]).to_ascii_lowercase().as_str() {
+        "and" => BitOp::And,
+        "or" => BitOp::Or,
+        "xor" => BitOp::Xor,
+        "not" if args.len() == 4 => BitOp::Not,
+        "not" => return Err(CommandError::Invalid("BITOP NOT must be called with a single source key.")),
+        _ => return Err(CommandError::Syntax),
+    };
+    Ok(Frame::Integer(redis.bitop(op, &args[2], &args[3..])? as i64))
+}
+
+fn pfadd(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 2, 0)?;
+    Ok(Frame::Integer(redis.pfadd(&args[1], &args[2..])? as i64))
+}
+
+fn pfcount(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 2, 0)?;
+    Ok(Frame::Integer(redis.pfcount(&args[1..])? as i64))
+}
+
+fn pfmerge(redis: &RedisDataStructure, args: &[Bytes]) -> CommandResult {
+    arity(args, 2, 0)?;
+    redis.pfmerge(&args[1], &args[2..])?;
+    Ok(Frame::Simple("OK".to_string()))
+}
@@ -0,0 +1,299 @@
+use crate::redis::RedisDataStructure;
+use crate::server::command;
+use crate::server::pubsub::PubSub;
+use crate::server::resp::{parse_command, Frame, ProtocolVersion};
+use bytes::{Bytes, BytesMut};
+use log::warn;
+use parking_lot::Mutex;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::watch;
+
+// commands RESP2 clients may send while subscribed
+const SUBSCRIBED_COMMANDS: [&str; 4] = ["subscribe", "unsubscribe", "ping", "quit"];
+
+/// Protocol state of a client, shared by every stream of a QUIC connection
+pub(crate) struct Session {
+    id: u64,
+    version: Mutex<ProtocolVersion>,
+}
+
+impl Session {
+    pub(crate) fn new(id: u64) -> Self {
+        Self {
+            id,
+            version: Mutex::new(ProtocolVersion::Resp2),
+        }
+    }
+}
+
+/// State of a client connection, or of one stream of a QUIC connection,
+/// subscriptions belong to the stream their pushes are sent on
+pub(crate) struct Connection {
+    // identifies subscriptions of this stream
+    id: u64,
+    session: Arc<Session>,
+    redis: Arc<RedisDataStructure>,
+    pubsub: Arc<PubSub>,
+    subscriptions: BTreeSet<Bytes>,
+    push_sender: UnboundedSender<Frame>,
+    push_receiver: UnboundedReceiver<Frame>,
+    closed: watch::Receiver<bool>,
+    max_request_size: usize,
+}
+
+impl Connection {
+    pub(crate) fn new(
+        id: u64,
+        session: Arc<Session>,
+        redis: Arc<RedisDataStructure>,
+        pubsub: Arc<PubSub>,
+        closed: watch::Receiver<bool>,
+        max_request_size: usize,
+    ) -> Self {
+        let (push_sender, push_receiver) = mpsc::unbounded_channel();
+        Self {
+            id,
+            session,
+            redis,
+            pubsub,
+            subscriptions: BTreeSet::new(),
+            push_sender,
+            push_receiver,
+            closed,
+            max_request_size,
+        }
+    }
+
+    /// Serve commands until client closes the stream or sends QUIT
+    pub(crate) async fn run<S>(&mut self, mut stream: S) -> std::io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let res = self.serve(&mut stream).await;
+        for channel in std::mem::take(&mut self.subscriptions) {
+            self.pubsub.unsubscribe(&channel, self.id);
+        }
+        res
+    }
+
+    async fn serve<S>(&mut self, stream: &mut S) -> std::io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut read_buf = BytesMut::with_capacity(16 * 1024);
+        let mut write_buf = BytesMut::with_capacity(16 * 1024);
+        loop {
+            tokio::select! {
+                read = stream.read_buf(&mut read_buf) => {
+                    // finish our side too, QUIC streams are otherwise left open
+                    if read? == 0 {
+                        return stream.shutdown().await;
+                    }
+                }
+                // the borrowed value is not Send, drop it inside the branch future
+                _ = async { self.closed.wait_for(|closed| *closed).await.is_ok() } => {
+                    return stream.shutdown().await;
+                }
+                Some(push) = self.push_receiver.recv() => {
+                    push.encode(&mut write_buf, self.version());
+                    stream.write_all(&write_buf).await?;
+                    write_buf.clear();
+                    continue;
+                }
+            }
+
+            // commands before QUIT or invalid data are still served
+            let mut commands = Vec::new();
+            let mut last_reply = None;
+            loop {
+                match parse_command(&mut read_buf) {
+                    Ok(Some(args)) if args.is_empty() => continue,
+                    Ok(Some(args)) if args[0].eq_ignore_ascii_case(b"quit") => {
+                        last_reply = Some(Frame::Simple("OK".to_string()));
+                        break;
+                    }
+                    Ok(Some(args)) => commands.push(args),
+                    Ok(None) if read_buf.len() > self.max_request_size => {
+                        last_reply = Some(Frame::Error("ERR request exceeds max size".to_string()));
+                        break;
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        warn!("parse command err: {}", e);
+                        last_reply = Some(Frame::Error("ERR protocol error".to_string()));
+                        break;
+                    }
+                }
+            }
+
+            for reply in self.execute(commands).await.iter() {
+                reply.encode(&mut write_buf, self.version());
+            }
+            if let Some(last_reply) = last_reply {
+                last_reply.encode(&mut write_buf, self.version());
+                stream.write_all(&write_buf).await?;
+                return stream.shutdown().await;
+            }
+            stream.write_all(&write_buf).await?;
+            write_buf.clear();
+        }
+    }
+
+    fn version(&self) -> ProtocolVersion {
+        *self.session.version.lock()
+    }
+
+    // run commands in order, consecutive engine commands are run together off the async runtime
+    async fn execute(&mut self, commands: Vec<Vec<Bytes>>) -> Vec<Frame> {
+        let mut replies = Vec::with_capacity(commands.len());
+        let mut engine_commands = Vec::new();
+        for args in commands.into_iter() {
+            let name = String::from_utf8_lossy(&args[0]).to_ascii_lowercase();
+            if self.version() == ProtocolVersion::Resp2
+                && !self.subscriptions.is_empty()
+                && !SUBSCRIBED_COMMANDS.contains(&name.as_str())
+            {
+                replies.extend(self.execute_engine(std::mem::take(&mut engine_commands)).await);
+                replies.push(Frame::Error(format!(
+                    "ERR Can't execute '{}': only SUBSCRIBE / UNSUBSCRIBE / PING / QUIT are allowed in this context",
+                    name
+                )));
+                continue;
+            }
+            let reply = match name.as_str() {
+                "hello" => self.hello(&args),
+                "subscribe" => self.subscribe(&args),
+                "unsubscribe" => self.unsubscribe(&args),
+                "publish" => self.publish(&args),
+                _ => {
+                    engine_commands.push(args);
+                    continue;
+                }
+            };
+            replies.extend(self.execute_engine(std::mem::take(&mut engine_commands)).await);
+            replies.extend(reply);
+        }
+        replies.extend(self.execute_engine(engine_commands).await);
+        replies
+    }
+
+    async fn execute_engine(&self, commands: Vec<Vec<Bytes>>) -> Vec<Frame> {
+        if commands.is_empty() {
+            return Vec::new();
+        }
+        let count = commands.len();
+        let redis = self.redis.clone();
+        let res = tokio::task::spawn_blocking(move || {
+            commands
+                .iter()
+                .map(|args| command::execute(&redis, args))
+                .collect()
+        })
+        .await;
+        res.unwrap_or_else(|_| vec![Frame::Error("ERR internal error".to_string()); count])
+    }
+
+    // HELLO [protover [SETNAME name]], switch protocol and describe the server
+    fn hello(&mut self, args: &[Bytes]) -> Vec<Frame> {
+        let mut version = self.version();
+        if let Some(protover) = args.get(1) {
+            version = match &protover[..] {
+                b"2" => ProtocolVersion::Resp2,
+                b"3" => ProtocolVersion::Resp3,
+                _ if std::str::from_utf8(protover).is_ok_and(|v| v.parse::<i64>().is_ok()) => {
+                    return vec![Frame::Error(
+                        "NOPROTO unsupported protocol version".to_string(),
+                    )];
+                }
+                _ => {
+                    return vec![Frame::Error(
+                        "ERR Protocol version is not an integer or out of range".to_string(),
+                    )];
+                }
+            };
+        }
+        for option in args.get(2..).unwrap_or_default().chunks(2) {
+            if option.len() != 2 || !option[0].eq_ignore_ascii_case(b"setname") {
+                return vec![Frame::Error("ERR syntax error".to_string())];
+            }
+        }
+        *self.session.version.lock() = version;
+
+        let proto = match version {
+            ProtocolVersion::Resp2 => 2,
+            ProtocolVersion::Resp3 => 3,
+        };
+        let bulk = |value: &str| Frame::Bulk(Bytes::copy_from_slice(value.as_bytes()));
+        vec![Frame::Map(vec![
+            (bulk("server"), bulk("lightkv")),
+            (bulk("version"), bulk(env!("CARGO_PKG_VERSION"))),
+            (bulk("proto"), Frame::Integer(proto)),
+            (bulk("id"), Frame::Integer(self.session.id as i64)),
+            (bulk("mode"), bulk("standalone")),
+            (bulk("role"), bulk("master")),
+            (bulk("modules"), Frame::Array(vec![])),
+        ])]
+    }
+
+    fn subscribe(&mut self, args: &[Bytes]) -> Vec<Frame> {
+        if args.len() < 2 {
+            return vec![arity_error("subscribe")];
+        }
+        args[1..]
+            .iter()
+            .map(|channel| {
+                if self.subscriptions.insert(channel.clone()) {
+                    self.pubsub
+                        .subscribe(channel.clone(), self.id, self.push_sender.clone());
+                }
+                self.subscription_reply("subscribe", Frame::Bulk(channel.clone()))
+            })
+            .collect()
+    }
+
+    // without channels all subscriptions are removed
+    fn unsubscribe(&mut self, args: &[Bytes]) -> Vec<Frame> {
+        let channels: Vec<Bytes> = match args.len() {
+            1 => self.subscriptions.iter().cloned().collect(),
+            _ => args[1..].to_vec(),
+        };
+        if channels.is_empty() {
+            return vec![self.subscription_reply("unsubscribe", Frame::Null)];
+        }
+        channels
+            .into_iter()
+            .map(|channel| {
+                if self.subscriptions.remove(&channel) {
+                    self.pubsub.unsubscribe(&channel, self.id);
+                }
+                self.subscription_reply("unsubscribe", Frame::Bulk(channel))
+            })
+            .collect()
+    }
+
+    fn publish(&self, args: &[Bytes]) -> Vec<Frame> {
+        if args.len() != 3 {
+            return vec![arity_error("publish")];
+        }
+        let receivers = self.pubsub.publish(&args[1], args[2].clone());
+        vec![Frame::Integer(receivers as i64)]
+    }
+
+    fn subscription_reply(&self, kind: &str, channel: Frame) -> Frame {
+        Frame::Push(vec![
+            Frame::Bulk(Bytes::copy_from_slice(kind.as_bytes())),
+            channel,
+            Frame::Integer(self.subscriptions.len() as i64),
+        ])
+    }
+}
+
+fn arity_error(name: &str) -> Frame {
+    Frame::Error(format!(
+        "ERR wrong number of arguments for '{}' command",
+        name
+    ))
+}
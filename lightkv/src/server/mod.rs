@@ -0,0 +1,288 @@
+//! Redis compatible server over an engine
+
+mod command;
+mod connection;
+mod glob;
+mod pubsub;
+mod quic;
+pub mod resp;
+
+use crate::engine::Engine;
+use crate::errors::Errors;
+use crate::metrics::PrometheusMetrics;
+use crate::options::{NetworkType, ServerConfig};
+use crate::redis::RedisDataStructure;
+use crate::server::connection::{Connection, Session};
+use crate::server::pubsub::PubSub;
+use crate::Result;
+use bytes::BytesMut;
+use log::{info, warn};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{watch, OwnedSemaphorePermit, Semaphore};
+
+/// Server speaking RESP protocol over TCP or QUIC
+pub struct Server {
+    state: Arc<ServerState>,
+    transport: Transport,
+    local_addr: SocketAddr,
+    // listener serving metrics over http, with metrics it renders
+    metrics: Option<(TcpListener, SocketAddr, Arc<PrometheusMetrics>)>,
+}
+
+enum Transport {
+    Tcp(TcpListener),
+    // QUIC endpoint with its certificate
+    Quic(quinn::Endpoint, Vec<u8>),
+}
+
+// state shared by all connections
+pub(crate) struct ServerState {
+    redis: Arc<RedisDataStructure>,
+    pubsub: Arc<PubSub>,
+    next_conn_id: AtomicU64,
+    // set once server is closed, connections stop serving
+    closed: watch::Sender<bool>,
+    // permits of client connections, none if unlimited
+    connection_permits: Option<Arc<Semaphore>>,
+    max_request_size: usize,
+}
+
+impl ServerState {
+    fn new_connection(&self) -> Connection {
+        let conn_id = self.next_conn_id.fetch_add(1, Ordering::SeqCst);
+        self.connection_of(conn_id, Arc::new(Session::new(conn_id)))
+    }
+
+    // protocol state of a QUIC connection, shared by its streams
+    fn new_session(&self) -> Arc<Session> {
+        Arc::new(Session::new(self.next_conn_id.fetch_add(1, Ordering::SeqCst)))
+    }
+
+    // serve one stream of a QUIC connection, every stream gets its own id for subscriptions
+    fn new_stream(&self, session: Arc<Session>) -> Connection {
+        let stream_id = self.next_conn_id.fetch_add(1, Ordering::SeqCst);
+        self.connection_of(stream_id, session)
+    }
+
+    fn connection_of(&self, id: u64, session: Arc<Session>) -> Connection {
+        Connection::new(
+            id,
+            session,
+            self.redis.clone(),
+            self.pubsub.clone(),
+            self.closed.subscribe(),
+            self.max_request_size,
+        )
+    }
+
+    // permit held by a connection while it is open, err if max connections are reached
+    fn acquire_connection_permit(&self) -> std::result::Result<Option<OwnedSemaphorePermit>, ()> {
+        match self.connection_permits.as_ref() {
+            Some(permits) => permits.clone().try_acquire_owned().map(Some).map_err(|_| ()),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Server {
+    /// Open engine of the config and bind listening addresses
+    pub async fn bind(config: &ServerConfig) -> Result<Self> {
+        let mut options = config.storage.to_options();
+        let metrics = match config.metrics_addr.as_ref() {
+            Some(addr) => {
+                let prometheus_metrics = Arc::new(PrometheusMetrics::new());
+                options.metrics = prometheus_metrics.clone();
+                let (listener, local_addr) = bind_addr(addr).await?;
+                Some((listener, local_addr, prometheus_metrics))
+            }
+            None => None,
+        };
+
+        let (transport, local_addr) = match config.general_config.network {
+            NetworkType::Tcp => {
+                let (listener, local_addr) = bind_addr(&config.general_config.addr).await?;
+                (Transport::Tcp(listener), local_addr)
+            }
+            NetworkType::Quic => {
+                let addr = resolve_addr(&config.general_config.addr).await?;
+                let (endpoint, certificate) =
+                    quic::bind_endpoint(addr, config.tls_cert.as_deref(), config.tls_key.as_deref())?;
+                let local_addr = endpoint.local_addr().map_err(|_| Errors::BindAddressError)?;
+                (Transport::Quic(endpoint, certificate.to_vec()), local_addr)
+            }
+        };
+
+        let engine = Arc::new(Engine::open(options)?);
+        engine.start_auto_compact();
+        Ok(Self {
+            state: Arc::new(ServerState {
+                redis: Arc::new(RedisDataStructure::new(engine)),
+                pubsub: Arc::new(PubSub::default()),
+                next_conn_id: AtomicU64::new(1),
+                closed: watch::channel(false).0,
+                connection_permits: match config.limits.max_connections {
+                    0 => None,
+                    max_connections => Some(Arc::new(Semaphore::new(max_connections))),
+                },
+                max_request_size: config.limits.max_request_size,
+            }),
+            transport,
+            local_addr,
+            metrics,
+        })
+    }
+
+    /// Address serving RESP connections
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// DER encoded certificate of QUIC transport, clients can trust it
+    /// when the server uses a generated self-signed certificate
+    pub fn certificate(&self) -> Option<&[u8]> {
+        match &self.transport {
+            Transport::Tcp(_) => None,
+            Transport::Quic(_, certificate) => Some(certificate),
+        }
+    }
+
+    /// Address serving metrics at `/metrics`, none if disabled
+    pub fn metrics_addr(&self) -> Option<SocketAddr> {
+        self.metrics.as_ref().map(|(_, addr, _)| *addr)
+    }
+
+    /// Accept connections until the returned future is dropped
+    pub async fn serve(&self) -> Result<()> {
+        tokio::select! {
+            res = self.serve_resp() => res,
+            res = self.serve_metrics() => res,
+        }
+    }
+
+    /// Close client connections, then flush and close engine,
+    /// the future of `serve` should be dropped before
+    pub fn close(&self) -> Result<()> {
+        self.state.closed.send_replace(true);
+        if let Transport::Quic(endpoint, _) = &self.transport {
+            endpoint.close(0u32.into(), b"server closed");
+        }
+        self.state.redis.engine().close()
+    }
+
+    async fn serve_resp(&self) -> Result<()> {
+        let listener = match &self.transport {
+            Transport::Tcp(listener) => listener,
+            Transport::Quic(endpoint, _) => return quic::serve(endpoint, self.state.clone()).await,
+        };
+        loop {
+            let (stream, peer_addr) = match listener.accept().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    warn!("accept connection err: {}", e);
+                    continue;
+                }
+            };
+            let _ = stream.set_nodelay(true);
+            let permit = match self.state.acquire_connection_permit() {
+                Ok(permit) => permit,
+                Err(_) => {
+                    tokio::spawn(reject_connection(stream));
+                    continue;
+                }
+            };
+            let mut conn = self.state.new_connection();
+            tokio::spawn(async move {
+                let _permit = permit;
+                if let Err(e) = conn.run(stream).await {
+                    info!("connection {} closed: {}", peer_addr, e);
+                }
+            });
+        }
+    }
+
+    async fn serve_metrics(&self) -> Result<()> {
+        let (listener, _, metrics) = match self.metrics.as_ref() {
+            Some(metrics) => metrics,
+            None => return std::future::pending().await,
+        };
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    warn!("accept metrics connection err: {}", e);
+                    continue;
+                }
+            };
+            let metrics = metrics.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_metrics_request(metrics, stream).await {
+                    info!("metrics request err: {}", e);
+                }
+            });
+        }
+    }
+}
+
+async fn reject_connection(mut stream: TcpStream) {
+    let _ = stream
+        .write_all(b"-ERR max number of clients reached\r\n")
+        .await;
+    let _ = stream.shutdown().await;
+}
+
+async fn resolve_addr(addr: &str) -> Result<SocketAddr> {
+    match tokio::net::lookup_host(addr).await.map(|mut addrs| addrs.next()) {
+        Ok(Some(addr)) => Ok(addr),
+        _ => {
+            warn!("resolve address {} err", addr);
+            Err(Errors::BindAddressError)
+        }
+    }
+}
+
+async fn bind_addr(addr: &str) -> Result<(TcpListener, SocketAddr)> {
+    let listener = match TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("bind address {} err: {}", addr, e);
+            return Err(Errors::BindAddressError);
+        }
+    };
+    match listener.local_addr() {
+        Ok(local_addr) => Ok((listener, local_addr)),
+        Err(_) => Err(Errors::BindAddressError),
+    }
+}
+
+// serve `GET /metrics` in prometheus text format, one request per connection
+async fn handle_metrics_request(
+    metrics: Arc<PrometheusMetrics>,
+    mut stream: TcpStream,
+) -> std::io::Result<()> {
+    let mut buf = BytesMut::with_capacity(1024);
+    while !buf.windows(4).any(|window| window == b"\r\n\r\n") {
+        if buf.len() > 8 * 1024 || stream.read_buf(&mut buf).await? == 0 {
+            return Ok(());
+        }
+    }
+    let request_line = String::from_utf8_lossy(&buf[..buf.iter().position(|c| *c == b'\r').unwrap_or(0)])
+        .to_string();
+    let mut parts = request_line.split(' ');
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let body = metrics.render();
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        }
+        _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+    };
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
@@ -0,0 +1,46 @@
+use crate::server::resp::Frame;
+use bytes::Bytes;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Channels subscribed by connections, messages are delivered as push frames
+#[derive(Default)]
+pub(crate) struct PubSub {
+    // subscribers of each channel by connection id
+    channels: Mutex<HashMap<Bytes, HashMap<u64, UnboundedSender<Frame>>>>,
+}
+
+impl PubSub {
+    pub(crate) fn subscribe(&self, channel: Bytes, conn_id: u64, sender: UnboundedSender<Frame>) {
+        self.channels.lock().entry(channel).or_default().insert(conn_id, sender);
+    }
+
+    pub(crate) fn unsubscribe(&self, channel: &Bytes, conn_id: u64) {
+        let mut channels = self.channels.lock();
+        if let Some(subscribers) = channels.get_mut(channel) {
+            subscribers.remove(&conn_id);
+            if subscribers.is_empty() {
+                channels.remove(channel);
+            }
+        }
+    }
+
+    /// Send message to subscribers of channel, return how many received it
+    pub(crate) fn publish(&self, channel: &Bytes, message: Bytes) -> usize {
+        let channels = self.channels.lock();
+        let subscribers = match channels.get(channel) {
+            Some(subscribers) => subscribers,
+            None => return 0,
+        };
+        let frame = Frame::Push(vec![
+            Frame::Bulk(Bytes::from("message")),
+            Frame::Bulk(channel.clone()),
+            Frame::Bulk(message),
+        ]);
+        subscribers
+            .values()
+            .filter(|sender| sender.send(frame.clone()).is_ok())
+            .count()
+    }
+}
@@ -0,0 +1,119 @@
+use crate::errors::Errors;
+use crate::server::ServerState;
+use crate::Result;
+use log::{info, warn};
+use quinn::rustls::pki_types::pem::PemObject;
+use quinn::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use quinn::Endpoint;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Bind a QUIC endpoint, a self-signed certificate for `localhost` is generated
+/// if no certificate is configured, return the endpoint with its certificate
+pub(crate) fn bind_endpoint(
+    addr: SocketAddr,
+    cert_path: Option<&Path>,
+    key_path: Option<&Path>,
+) -> Result<(Endpoint, CertificateDer<'static>)> {
+    let (cert_chain, key) = match (cert_path, key_path) {
+        (Some(cert_path), Some(key_path)) => load_certificate(cert_path, key_path)?,
+        (None, None) => self_signed_certificate()?,
+        _ => {
+            warn!("tls certificate and key must be configured together");
+            return Err(Errors::TlsConfigError);
+        }
+    };
+    let certificate = cert_chain[0].clone();
+    let server_config = match quinn::ServerConfig::with_single_cert(cert_chain, key) {
+        Ok(server_config) => server_config,
+        Err(e) => {
+            warn!("build quic server config err: {}", e);
+            return Err(Errors::TlsConfigError);
+        }
+    };
+    match Endpoint::server(server_config, addr) {
+        Ok(endpoint) => Ok((endpoint, certificate)),
+        Err(e) => {
+            warn!("bind quic address {} err: {}", addr, e);
+            Err(Errors::BindAddressError)
+        }
+    }
+}
+
+/// Accept QUIC connections, each bidirectional stream is served like a TCP connection
+/// so a slow request does not block others of the same connection,
+/// protocol version switched by HELLO is kept for the whole connection
+pub(crate) async fn serve(endpoint: &Endpoint, state: Arc<ServerState>) -> Result<()> {
+    while let Some(incoming) = endpoint.accept().await {
+        let permit = match state.acquire_connection_permit() {
+            Ok(permit) => permit,
+            Err(_) => {
+                incoming.refuse();
+                continue;
+            }
+        };
+        let state = state.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            let connection = match incoming.await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    info!("quic handshake err: {}", e);
+                    return;
+                }
+            };
+            let peer_addr = connection.remote_address();
+            // HELLO on any stream applies to later streams of the connection
+            let session = state.new_session();
+            loop {
+                let (send, recv) = match connection.accept_bi().await {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        info!("quic connection {} closed: {}", peer_addr, e);
+                        return;
+                    }
+                };
+                let mut conn = state.new_stream(session.clone());
+                tokio::spawn(async move {
+                    if let Err(e) = conn.run(tokio::io::join(recv, send)).await {
+                        info!("quic stream of {} closed: {}", peer_addr, e);
+                    }
+                });
+            }
+        });
+    }
+    Ok(())
+}
+
+fn load_certificate(
+    cert_path: &Path,
+    key_path: &Path,
+) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)> {
+    let cert_chain = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<std::result::Result<Vec<_>, _>>());
+    let key = PrivateKeyDer::from_pem_file(key_path);
+    match (cert_chain, key) {
+        (Ok(cert_chain), Ok(key)) if !cert_chain.is_empty() => Ok((cert_chain, key)),
+        (cert_chain, key) => {
+            warn!(
+                "load tls certificate err: {:?} {:?}",
+                cert_chain.err(),
+                key.err()
+            );
+            Err(Errors::TlsConfigError)
+        }
+    }
+}
+
+fn self_signed_certificate() -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)> {
+    let certified_key = match rcgen::generate_simple_self_signed(vec!["localhost".to_string()]) {
+        Ok(certified_key) => certified_key,
+        Err(e) => {
+            warn!("generate self-signed certificate err: {}", e);
+            return Err(Errors::TlsConfigError);
+        }
+    };
+    let key = PrivatePkcs8KeyDer::from(certified_key.key_pair.serialize_der());
+    Ok((vec![certified_key.cert.der().clone()], key.into()))
+}
@@ -0,0 +1,377 @@
+use crate::errors::Errors;
+use crate::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+const CRLF: &[u8] = b"\r\n";
+// lengths beyond these are rejected before allocating
+const MAX_BULK_SIZE: i64 = 512 * 1024 * 1024;
+const MAX_ARRAY_SIZE: i64 = 1024 * 1024;
+const MAX_INLINE_SIZE: usize = 64 * 1024;
+// aggregates nested deeper than this are rejected before recursing
+const MAX_NESTING_DEPTH: usize = 32;
+// smallest encoded frame, e.g. `_\r\n`, bounds item capacity by the bytes left
+const MIN_FRAME_SIZE: usize = 3;
+
+/// Protocol version of a connection, switched by `HELLO`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProtocolVersion {
+    Resp2,
+    Resp3,
+}
+
+/// A value of the RESP protocol, RESP3 types are downgraded when encoded as RESP2
+#[derive(Debug, Clone, PartialEq)]
+pub enum Frame {
+    Simple(String),
+    Error(String),
+    Integer(i64),
+    Bulk(Bytes),
+    Null,
+    Array(Vec<Frame>),
+    Map(Vec<(Frame, Frame)>),
+    Set(Vec<Frame>),
+    Double(f64),
+    Boolean(bool),
+    Push(Vec<Frame>),
+}
+
+impl Frame {
+    pub fn encode(&self, buf: &mut BytesMut, version: ProtocolVersion) {
+        let resp3 = version == ProtocolVersion::Resp3;
+        match self {
+            Frame::Simple(value) => put_line(buf, b'+', value.as_bytes()),
+            Frame::Error(value) => put_line(buf, b'-', value.as_bytes()),
+            Frame::Integer(value) => put_line(buf, b':', value.to_string().as_bytes()),
+            Frame::Bulk(value) => {
+                put_line(buf, b'$', value.len().to_string().as_bytes());
+                buf.put_slice(value);
+                buf.put_slice(CRLF);
+            }
+            Frame::Null if resp3 => buf.put_slice(b"_\r\n"),
+            Frame::Null => buf.put_slice(b"$-1\r\n"),
+            Frame::Array(items) => put_items(buf, b'*', items, version),
+            Frame::Map(entries) => {
+                match resp3 {
+                    true => put_line(buf, b'%', entries.len().to_string().as_bytes()),
+                    false => put_line(buf, b'*', (entries.len() * 2).to_string().as_bytes()),
+                }
+                for (key, value) in entries.iter() {
+                    key.encode(buf, version);
+                    value.encode(buf, version);
+                }
+            }
+            Frame::Set(items) => put_items(buf, if resp3 { b'~' } else { b'*' }, items, version),
+            Frame::Double(value) => {
+                let value = format_double(*value);
+                match resp3 {
+                    true => put_line(buf, b',', value.as_bytes()),
+                    false => Frame::Bulk(Bytes::from(value)).encode(buf, version),
+                }
+            }
+            Frame::Boolean(value) => match resp3 {
+                true => put_line(buf, b'#', if *value { b"t" } else { b"f" }),
+                false => Frame::Integer(*value as i64).encode(buf, version),
+            },
+            Frame::Push(items) => put_items(buf, if resp3 { b'>' } else { b'*' }, items, version),
+        }
+    }
+
+    /// Parse a frame from the front of `buf`, none if it is not complete yet
+    pub fn parse(buf: &mut BytesMut) -> Result<Option<Frame>> {
+        match parse_frame(buf, 0, 0)? {
+            Some((frame, next)) => {
+                buf.advance(next);
+                Ok(Some(frame))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Parse a command from the front of `buf`, either a flat array of bulk strings
+/// or an inline command separated by spaces, none if it is not complete yet
+pub fn parse_command(buf: &mut BytesMut) -> Result<Option<Vec<Bytes>>> {
+    if buf.is_empty() {
+        return Ok(None);
+    }
+    if buf[0] != b'*' {
+        let end = match buf.iter().position(|c| *c == b'\n') {
+            Some(end) => end,
+            None if buf.len() > MAX_INLINE_SIZE => return Err(Errors::ProtocolError),
+            None => return Ok(None),
+        };
+        let args = buf[..end]
+            .split(|c| c.is_ascii_whitespace())
+            .filter(|arg| !arg.is_empty())
+            .map(Bytes::copy_from_slice)
+            .collect();
+        buf.advance(end + 1);
+        return Ok(Some(args));
+    }
+
+    let (line, mut next) = match read_line(buf, 1) {
+        Some(line) => line,
+        None => return Ok(None),
+    };
+    let len = parse_integer(line)?;
+    if !(0..=MAX_ARRAY_SIZE).contains(&len) {
+        return Err(Errors::ProtocolError);
+    }
+    let mut args = Vec::with_capacity((len as usize).min((buf.len() - next) / MIN_FRAME_SIZE));
+    for _ in 0..len {
+        if next >= buf.len() {
+            return Ok(None);
+        }
+        // any other frame type, including null bulk strings, is not an argument
+        if buf[next] != b'$' {
+            return Err(Errors::ProtocolError);
+        }
+        match parse_frame(buf, next, 0)? {
+            Some((Frame::Bulk(arg), arg_next)) => {
+                args.push(arg);
+                next = arg_next;
+            }
+            Some(_) => return Err(Errors::ProtocolError),
+            None => return Ok(None),
+        }
+    }
+    buf.advance(next);
+    Ok(Some(args))
+}
+
+fn put_line(buf: &mut BytesMut, prefix: u8, line: &[u8]) {
+    buf.put_u8(prefix);
+    buf.put_slice(line);
+    buf.put_slice(CRLF);
+}
+
+fn put_items(buf: &mut BytesMut, prefix: u8, items: &[Frame], version: ProtocolVersion) {
+    put_line(buf, prefix, items.len().to_string().as_bytes());
+    for item in items.iter() {
+        item.encode(buf, version);
+    }
+}
+
+fn format_double(value: f64) -> String {
+    match value {
+        f64::INFINITY => "inf".to_string(),
+        f64::NEG_INFINITY => "-inf".to_string(),
+        _ if value.is_nan() => "nan".to_string(),
+        _ => value.to_string(),
+    }
+}
+
+// parse a frame starting at `pos` nested in `depth` aggregates,
+// return it with the position after it
+fn parse_frame(buf: &[u8], pos: usize, depth: usize) -> Result<Option<(Frame, usize)>> {
+    if pos >= buf.len() {
+        return Ok(None);
+    }
+    let (line, next) = match read_line(buf, pos + 1) {
+        Some(line) => line,
+        None => return Ok(None),
+    };
+    let frame = match buf[pos] {
+        b'+' => Frame::Simple(parse_string(line)?),
+        b'-' => Frame::Error(parse_string(line)?),
+        b':' => Frame::Integer(parse_integer(line)?),
+        b'$' => {
+            let len = parse_integer(line)?;
+            if len == -1 {
+                return Ok(Some((Frame::Null, next)));
+            }
+            if !(0..=MAX_BULK_SIZE).contains(&len) {
+                return Err(Errors::ProtocolError);
+            }
+            let end = next + len as usize;
+            if buf.len() < end + CRLF.len() {
+                return Ok(None);
+            }
+            if &buf[end..end + CRLF.len()] != CRLF {
+                return Err(Errors::ProtocolError);
+            }
+            return Ok(Some((Frame::Bulk(Bytes::copy_from_slice(&buf[next..end])), end + CRLF.len())));
+        }
+        b'_' if line.is_empty() => Frame::Null,
+        b',' => Frame::Double(parse_double(line)?),
+        b'#' => match line {
+            b"t" => Frame::Boolean(true),
+            b"f" => Frame::Boolean(false),
+            _ => return Err(Errors::ProtocolError),
+        },
+        b'*' | b'~' | b'>' | b'%' => {
+            let len = parse_integer(line)?;
+            if len == -1 && buf[pos] == b'*' {
+                return Ok(Some((Frame::Null, next)));
+            }
+            if !(0..=MAX_ARRAY_SIZE).contains(&len) || depth >= MAX_NESTING_DEPTH {
+                return Err(Errors::ProtocolError);
+            }
+            // a map holds a key and a value for each entry
+            let count = if buf[pos] == b'%' { len * 2 } else { len };
+            let (mut items, next) = match parse_items(buf, next, count as usize, depth + 1)? {
+                Some(items) => items,
+                None => return Ok(None),
+            };
+            let frame = match buf[pos] {
+                b'*' => Frame::Array(items),
+                b'~' => Frame::Set(items),
+                b'>' => Frame::Push(items),
+                _ => {
+                    let mut entries = Vec::with_capacity(items.len() / 2);
+                    while let (Some(value), Some(key)) = (items.pop(), items.pop()) {
+                        entries.push((key, value));
+                    }
+                    entries.reverse();
+                    Frame::Map(entries)
+                }
+            };
+            return Ok(Some((frame, next)));
+        }
+        _ => return Err(Errors::ProtocolError),
+    };
+    Ok(Some((frame, next)))
+}
+
+fn parse_items(buf: &[u8], mut next: usize, count: usize, depth: usize) -> Result<Option<(Vec<Frame>, usize)>> {
+    // a count larger than the buffer can hold is not allocated up front
+    let mut items = Vec::with_capacity(count.min(buf.len().saturating_sub(next) / MIN_FRAME_SIZE));
+    for _ in 0..count {
+        match parse_frame(buf, next, depth)? {
+            Some((item, item_next)) => {
+                items.push(item);
+                next = item_next;
+            }
+            None => return Ok(None),
+        }
+    }
+    Ok(Some((items, next)))
+}
+
+// line from `pos` to the next CRLF, with the position after CRLF
+fn read_line(buf: &[u8], pos: usize) -> Option<(&[u8], usize)> {
+    buf.get(pos..)?
+        .windows(CRLF.len())
+        .position(|window| window == CRLF)
+        .map(|len| (&buf[pos..pos + len], pos + len + CRLF.len()))
+}
+
+fn parse_string(line: &[u8]) -> Result<String> {
+    String::from_utf8(line.to_vec()).map_err(|_| Errors::ProtocolError)
+}
+
+fn parse_integer(line: &[u8]) -> Result<i64> {
+    parse_string(line)?.parse::<i64>().map_err(|_| Errors::ProtocolError)
+}
+
+fn parse_double(line: &[u8]) -> Result<f64> {
+    parse_string(line)?.parse::<f64>().map_err(|_| Errors::ProtocolError)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::Errors;
+    use crate::server::resp::{parse_command, Frame, ProtocolVersion};
+    use bytes::{Bytes, BytesMut};
+
+    #[test]
+    fn test_encode_parse() {
+        let frame = Frame::Array(vec![
+            Frame::Simple("OK".to_string()),
+            Frame::Error("ERR bad".to_string()),
+            Frame::Integer(-42),
+            Frame::Bulk(Bytes::from("a\r\nb")),
+            Frame::Null,
+            Frame::Array(vec![]),
+        ]);
+        let mut buf = BytesMut::new();
+        frame.encode(&mut buf, ProtocolVersion::Resp2);
+        assert_eq!(&buf[..], b"*6\r\n+OK\r\n-ERR bad\r\n:-42\r\n$4\r\na\r\nb\r\n$-1\r\n*0\r\n");
+
+        // incomplete frames are kept in buffer
+        let mut partial = BytesMut::from(&buf[..buf.len() - 3]);
+        assert_eq!(Frame::parse(&mut partial), Ok(None));
+        assert_eq!(partial.len(), buf.len() - 3);
+
+        buf.extend_from_slice(b":1\r\n");
+        assert_eq!(Frame::parse(&mut buf), Ok(Some(frame)));
+        assert_eq!(Frame::parse(&mut buf), Ok(Some(Frame::Integer(1))));
+        assert!(buf.is_empty());
+
+        let mut buf = BytesMut::from("$3\r\nabcd\r\n");
+        assert_eq!(Frame::parse(&mut buf), Err(Errors::ProtocolError));
+    }
+
+    #[test]
+    fn test_encode_parse_resp3() {
+        let frame = Frame::Push(vec![
+            Frame::Map(vec![
+                (Frame::Bulk(Bytes::from("proto")), Frame::Integer(3)),
+                (Frame::Bulk(Bytes::from("flag")), Frame::Boolean(true)),
+            ]),
+            Frame::Set(vec![Frame::Bulk(Bytes::from("a"))]),
+            Frame::Double(1.5),
+            Frame::Double(f64::NEG_INFINITY),
+            Frame::Null,
+        ]);
+        let mut buf = BytesMut::new();
+        frame.encode(&mut buf, ProtocolVersion::Resp3);
+        assert_eq!(
+            &buf[..],
+            b">5\r\n%2\r\n$5\r\nproto\r\n:3\r\n$4\r\nflag\r\n#t\r\n~1\r\n$1\r\na\r\n,1.5\r\n,-inf\r\n_\r\n"
+        );
+        assert_eq!(Frame::parse(&mut buf), Ok(Some(frame.clone())));
+
+        // the same value downgraded for RESP2 clients
+        let mut buf = BytesMut::new();
+        frame.encode(&mut buf, ProtocolVersion::Resp2);
+        assert_eq!(
+            &buf[..],
+            b"*5\r\n*4\r\n$5\r\nproto\r\n:3\r\n$4\r\nflag\r\n:1\r\n*1\r\n$1\r\na\r\n$3\r\n1.5\r\n$4\r\n-inf\r\n$-1\r\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_command() {
+        let mut buf = BytesMut::from("*2\r\n$3\r\nGET\r\n$3\r\nfoo\r\nPING  hello\r\nEXISTS");
+        assert_eq!(
+            parse_command(&mut buf),
+            Ok(Some(vec![Bytes::from("GET"), Bytes::from("foo")]))
+        );
+        assert_eq!(
+            parse_command(&mut buf),
+            Ok(Some(vec![Bytes::from("PING"), Bytes::from("hello")]))
+        );
+        assert_eq!(parse_command(&mut buf), Ok(None));
+
+        let mut buf = BytesMut::from("*1\r\n:1\r\n");
+        assert_eq!(parse_command(&mut buf), Err(Errors::ProtocolError));
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        // deeply nested aggregates are rejected instead of overflowing the stack
+        let mut buf = BytesMut::from("*1\r\n".repeat(100_000).as_str());
+        assert_eq!(Frame::parse(&mut buf), Err(Errors::ProtocolError));
+        let mut buf = BytesMut::from("*1\r\n".repeat(100_000).as_str());
+        assert_eq!(parse_command(&mut buf), Err(Errors::ProtocolError));
+        let mut buf = BytesMut::from(format!("{}:1\r\n", "*1\r\n".repeat(8)).as_str());
+        assert!(Frame::parse(&mut buf).unwrap().is_some());
+
+        // commands are flat arrays of bulk strings
+        let mut buf = BytesMut::from("*2\r\n$3\r\nGET\r\n*1\r\n$3\r\nfoo\r\n");
+        assert_eq!(parse_command(&mut buf), Err(Errors::ProtocolError));
+        let mut buf = BytesMut::from("*1\r\n$-1\r\n");
+        assert_eq!(parse_command(&mut buf), Err(Errors::ProtocolError));
+        let mut buf = BytesMut::from("*-1\r\n");
+        assert_eq!(parse_command(&mut buf), Err(Errors::ProtocolError));
+
+        // large counts wait for more data
+        let mut buf = BytesMut::from("*1048576\r\n$3\r\nGET\r\n");
+        assert_eq!(parse_command(&mut buf), Ok(None));
+        let mut buf = BytesMut::from("%1048576\r\n");
+        assert_eq!(Frame::parse(&mut buf), Ok(None));
+        let mut buf = BytesMut::from("*1048577\r\n");
+        assert_eq!(parse_command(&mut buf), Err(Errors::ProtocolError));
+    }
+}
@@ -0,0 +1,196 @@
+use std::marker::PhantomData;
+use bytes::{BufMut, Bytes, BytesMut};
+use crate::engine::{Engine, Iterator as EngineIterator};
+use crate::errors::Errors;
+use crate::options::IteratorOptions;
+use crate::types::{LightKVKey, LightKVValue};
+use crate::Result;
+
+// metadata key of a table is this byte followed by table name
+pub(crate) const TABLE_META_PREFIX:u8=0xFC;
+// keys of a table are prefixed by this byte, length of table name and table name
+pub(crate) const TABLE_DATA_PREFIX:u8=0xFD;
+
+fn meta_key(name:&str)->Bytes{
+    let mut buf=BytesMut::with_capacity(name.len()+1);
+    buf.put_u8(TABLE_META_PREFIX);
+    buf.put_slice(name.as_bytes());
+    buf.freeze()
+}
+
+fn data_key_prefix(name:&str)->Vec<u8>{
+    let mut buf=BytesMut::new();
+    buf.put_u8(TABLE_DATA_PREFIX);
+    prost::encoding::encode_varint(name.len() as u64,&mut buf);
+    buf.put_slice(name.as_bytes());
+    buf.to_vec()
+}
+
+// type definition and name of key type, then of value type
+fn table_metadata<K:LightKVKey+?Sized,V:LightKVValue+?Sized>()->Bytes{
+    let mut buf=BytesMut::new();
+    for (definition,name) in [(K::type_definition(),K::type_name()),(V::type_definition(),V::type_name())] {
+        buf.put_u8(definition.to_byte());
+        prost::encoding::encode_varint(name.len() as u64,&mut buf);
+        buf.put_slice(name.as_bytes());
+    }
+    buf.freeze()
+}
+
+/// Table of typed keys and values, keys and values are encoded through
+/// `LightKVKey` and `LightKVValue`
+pub struct Table<'a,K:LightKVKey+?Sized,V:LightKVValue+?Sized>{
+    engine:&'a Engine,
+    name:String,
+    prefix:Vec<u8>,
+    _types:PhantomData<fn(&K,&V)>,
+}
+
+impl Engine {
+    /// Open table of `name`, it is created if it does not exist,
+    /// a table is only reopened with key and value types of the same names
+    pub fn open_table<K:LightKVKey+?Sized,V:LightKVValue+?Sized>(&self,name:&str)->Result<Table<'_,K,V>>{
+        let meta_key=meta_key(name);
+        let metadata=table_metadata::<K,V>();
+        let existing=match self.get(meta_key.clone()) {
+            Ok(existing)=>existing,
+            Err(Errors::KeyNotFound) if self.put_if_absent(meta_key.clone(),metadata.clone())?=>metadata.clone(),
+            // created by a concurrent open
+            Err(Errors::KeyNotFound)=>self.get(meta_key)?,
+            Err(e)=>return Err(e),
+        };
+        if existing!=metadata {
+            return Err(Errors::TableTypeMismatch);
+        }
+        Ok(Table{
+            engine:self,
+            name:name.to_string(),
+            prefix:data_key_prefix(name),
+            _types:PhantomData,
+        })
+    }
+}
+
+impl<'a,K:LightKVKey+?Sized,V:LightKVValue+?Sized> Table<'a,K,V> {
+    pub fn name(&self)->&str{
+        &self.name
+    }
+
+    pub fn insert(&self,key:&K,value:&V)->Result<()>{
+        self.engine.put(self.data_key(key)?,Bytes::copy_from_slice(&value.as_bytes()?))
+    }
+
+    pub fn get(&self,key:&K)->Result<Option<V::SelfType>>{
+        match self.engine.get(self.data_key(key)?) {
+            Ok(value)=>Ok(Some(V::from_bytes(&value)?)),
+            Err(Errors::KeyNotFound)=>Ok(None),
+            Err(e)=>Err(e),
+        }
+    }
+
+    pub fn contains_key(&self,key:&K)->Result<bool>{
+        self.engine.exists(self.data_key(key)?)
+    }
+
+    pub fn remove(&self,key:&K)->Result<()>{
+        self.engine.remove(self.data_key(key)?)
+    }
+
+    /// Entries of table in key order
+    pub fn iter(&self)->TableIterator<'a,K,V>{
+        TableIterator{
+            iter:self.engine.iter(IteratorOptions{prefix:self.prefix.clone(),reverse:false}),
+            prefix_len:self.prefix.len(),
+            _types:PhantomData,
+        }
+    }
+
+    fn data_key(&self,key:&K)->Result<Bytes>{
+        let key=key.as_bytes()?;
+        let mut buf=BytesMut::with_capacity(self.prefix.len()+key.len());
+        buf.put_slice(&self.prefix);
+        buf.put_slice(&key);
+        Ok(buf.freeze())
+    }
+}
+
+/// Iterator over decoded entries of a table
+pub struct TableIterator<'a,K:LightKVKey+?Sized,V:LightKVValue+?Sized>{
+    iter:EngineIterator<'a>,
+    prefix_len:usize,
+    _types:PhantomData<fn(&K,&V)>,
+}
+
+impl<K:LightKVKey+?Sized,V:LightKVValue+?Sized> Iterator for TableIterator<'_,K,V> {
+    type Item=Result<(K::SelfType,V::SelfType)>;
+
+    fn next(&mut self)->Option<Self::Item>{
+        let (key,value)=self.iter.next()?;
+        Some(K::from_bytes(&key[self.prefix_len..]).and_then(|key|Ok((key,V::from_bytes(&value)?))))
+    }
+}
+
+#[cfg(all(test, feature = "serde-value"))]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use crate::engine::engine_tests::{create_options, remove_db};
+    use crate::engine::Engine;
+    use crate::errors::Errors;
+    use crate::types::SerdeValue;
+
+    #[derive(Debug,PartialEq,Serialize,Deserialize)]
+    struct User{
+        name:String,
+        age:u32,
+    }
+
+    impl SerdeValue for User {
+        const TYPE_NAME:&'static str="user";
+    }
+
+    #[test]
+    fn test_table() {
+        let options=create_options("table");
+        let engine=Engine::open(options.clone()).unwrap();
+
+        let users=engine.open_table::<u64,User>("users").unwrap();
+        let alice=User{name:"alice".to_string(),age:30};
+        users.insert(&2,&alice).unwrap();
+        users.insert(&1,&User{name:"bob".to_string(),age:20}).unwrap();
+        assert_eq!(users.name(),"users");
+        assert_eq!(users.get(&2).unwrap(),Some(alice));
+        assert_eq!(users.get(&3).unwrap(),None);
+        assert!(users.contains_key(&1).unwrap());
+        let ids:Vec<u64>=users.iter().map(|entry|entry.unwrap().0).collect();
+        assert_eq!(ids,vec![1,2]);
+        users.remove(&1).unwrap();
+        assert!(!users.contains_key(&1).unwrap());
+
+        // tables with prefixed names never share keys
+        let names=engine.open_table::<str,[u8]>("user").unwrap();
+        names.insert("b",b"2").unwrap();
+        names.insert("a",b"1").unwrap();
+        let entries:Vec<(String,Vec<u8>)>=names.iter().map(|entry|entry.unwrap()).collect();
+        assert_eq!(entries,vec![("a".to_string(),b"1".to_vec()),("b".to_string(),b"2".to_vec())]);
+        assert_eq!(users.iter().count(),1);
+
+        let scores=engine.open_table::<(String,i32),i64>("scores").unwrap();
+        scores.insert(&("a".to_string(),-1),&-10).unwrap();
+        scores.insert(&("a".to_string(),1),&10).unwrap();
+        assert_eq!(scores.get(&("a".to_string(),-1)).unwrap(),Some(-10));
+        let keys:Vec<(String,i32)>=scores.iter().map(|entry|entry.unwrap().0).collect();
+        assert_eq!(keys,vec![("a".to_string(),-1),("a".to_string(),1)]);
+        drop(engine);
+
+        // owned and borrowed types share type names
+        let engine=Engine::open(options.clone()).unwrap();
+        let names=engine.open_table::<String,Vec<u8>>("user").unwrap();
+        assert_eq!(names.get(&"a".to_string()).unwrap(),Some(b"1".to_vec()));
+        assert_eq!(engine.open_table::<u32,User>("users").err(),Some(Errors::TableTypeMismatch));
+        assert_eq!(engine.open_table::<u64,String>("users").err(),Some(Errors::TableTypeMismatch));
+        let users=engine.open_table::<u64,User>("users").unwrap();
+        assert_eq!(users.get(&2).unwrap().unwrap().name,"alice");
+        drop(engine);
+        remove_db(&options);
+    }
+}
@@ -1,30 +1,60 @@
+use std::borrow::Cow;
 use std::fmt::Debug;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde-value")]
+use serde::de::DeserializeOwned;
+use crate::errors::Errors;
 
-#[derive(Copy, Clone)]
+/// Minimum level of emitted logs
+#[derive(Copy, Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all="lowercase")]
 pub enum LogLevel {
+    #[default]
     Info,
     Warn,
     Error
 }
 
-impl Default for LogLevel {
-    fn default() -> Self {LogLevel::Info}
+impl FromStr for LogLevel {
+    type Err=String;
+
+    fn from_str(s:&str)->Result<Self,Self::Err>{
+        match s.to_ascii_lowercase().as_str() {
+            "info"=>Ok(LogLevel::Info),
+            "warn"=>Ok(LogLevel::Warn),
+            "error"=>Ok(LogLevel::Error),
+            _=>Err(format!("unknown log level {}",s)),
+        }
+    }
+}
+
+impl LogLevel {
+    pub(crate) fn to_level_filter(self)->log::LevelFilter{
+        match self {
+            LogLevel::Info=>log::LevelFilter::Info,
+            LogLevel::Warn=>log::LevelFilter::Warn,
+            LogLevel::Error=>log::LevelFilter::Error,
+        }
+    }
 }
 
-#[derive(Debug)]
-enum TypeDefinition{
+/// Whether encoding of a table type is defined by lightkv or by application
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum TypeDefinition{
     Internal,
     UserCustomize,
 }
 
 impl TypeDefinition {
-    fn to_byte(&self)->u8{
+    pub(crate) fn to_byte(self)->u8{
         match self {
             TypeDefinition::Internal=>1,
             TypeDefinition::UserCustomize=>2,
         }
     }
 
+    #[allow(dead_code)]
     fn from_byte(value:u8)->Self{
         match value {
             1=>TypeDefinition::Internal,
@@ -34,21 +64,252 @@ impl TypeDefinition {
     }
 }
 
-
-enum TypeName{
-
-}
-
+/// Value of a typed table opened by `Engine::open_table`
 pub trait LightKVValue:Debug{
+    /// Owned type decoded from bytes
     type SelfType:Debug;
 
-    fn from_bytes(data:&[u8])->Self::SelfType;
+    fn from_bytes(data:&[u8])->Result<Self::SelfType,Errors>;
 
-    fn as_bytes(&self)->&[u8];
+    fn as_bytes(&self)->Result<Cow<'_,[u8]>,Errors>;
 
+    /// Name recorded in table metadata, a table is only reopened with types of the same names
     fn type_name()->String;
+
+    fn type_definition()->TypeDefinition{
+        TypeDefinition::UserCustomize
+    }
 }
 
+/// Key of a typed table, keys are iterated in the order of their encoded bytes
 pub trait LightKVKey:LightKVValue{
 
 }
+
+/// Value encoded by serde through bincode, implement it to store a serde type in tables
+#[cfg(feature = "serde-value")]
+pub trait SerdeValue:Serialize+DeserializeOwned+Debug{
+    /// Name recorded in table metadata, keep it when the type is renamed or moved
+    const TYPE_NAME:&'static str;
+}
+
+#[cfg(feature = "serde-value")]
+impl<T:SerdeValue> LightKVValue for T {
+    type SelfType=T;
+
+    fn from_bytes(data:&[u8])->Result<T,Errors>{
+        bincode::deserialize(data).map_err(|_|Errors::DecodeTableValueError)
+    }
+
+    fn as_bytes(&self)->Result<Cow<'_,[u8]>,Errors>{
+        bincode::serialize(self).map(Cow::Owned).map_err(|_|Errors::EncodeTableValueError)
+    }
+
+    fn type_name()->String{
+        T::TYPE_NAME.to_string()
+    }
+}
+
+// integers are big endian with sign bit flipped, so bytes sort in numeric order
+macro_rules! impl_integer {
+    ($($t:ty),+) => {
+        $(
+            impl LightKVValue for $t {
+                type SelfType=$t;
+
+                fn from_bytes(data:&[u8])->Result<$t,Errors>{
+                    let data=data.try_into().map_err(|_|Errors::DecodeTableValueError)?;
+                    Ok(<$t>::from_be_bytes(data)^<$t>::MIN)
+                }
+
+                fn as_bytes(&self)->Result<Cow<'_,[u8]>,Errors>{
+                    Ok(Cow::Owned((*self^<$t>::MIN).to_be_bytes().to_vec()))
+                }
+
+                fn type_name()->String{
+                    stringify!($t).to_string()
+                }
+
+                fn type_definition()->TypeDefinition{
+                    TypeDefinition::Internal
+                }
+            }
+
+            impl LightKVKey for $t {}
+        )+
+    };
+}
+
+impl_integer!(u8,u16,u32,u64,u128,i8,i16,i32,i64,i128);
+
+// owned and borrowed forms share encoding and type name
+macro_rules! impl_bytes {
+    ($t:ty,$owned:ty,$name:expr,$from_bytes:expr,$as_bytes:expr) => {
+        impl LightKVValue for $t {
+            type SelfType=$owned;
+
+            fn from_bytes(data:&[u8])->Result<$owned,Errors>{
+                $from_bytes(data)
+            }
+
+            fn as_bytes(&self)->Result<Cow<'_,[u8]>,Errors>{
+                Ok(Cow::Borrowed($as_bytes(self)))
+            }
+
+            fn type_name()->String{
+                $name.to_string()
+            }
+
+            fn type_definition()->TypeDefinition{
+                TypeDefinition::Internal
+            }
+        }
+
+        impl LightKVKey for $t {}
+    };
+}
+
+fn string_from_bytes(data:&[u8])->Result<String,Errors>{
+    String::from_utf8(data.to_vec()).map_err(|_|Errors::DecodeTableValueError)
+}
+
+fn vec_from_bytes(data:&[u8])->Result<Vec<u8>,Errors>{
+    Ok(data.to_vec())
+}
+
+impl_bytes!(str,String,"string",string_from_bytes,str::as_bytes);
+impl_bytes!(String,String,"string",string_from_bytes,String::as_bytes);
+impl_bytes!([u8],Vec<u8>,"bytes",vec_from_bytes,<[u8]>::as_ref);
+impl_bytes!(Vec<u8>,Vec<u8>,"bytes",vec_from_bytes,Vec::as_slice);
+
+// split an element prefixed by its u32 length from the rest of a tuple
+fn split_element(data:&[u8])->Result<(&[u8],&[u8]),Errors>{
+    if data.len()<4 {
+        return Err(Errors::DecodeTableValueError);
+    }
+    let (len,data)=data.split_at(4);
+    let len=u32::from_be_bytes(len.try_into().unwrap()) as usize;
+    if data.len()<len {
+        return Err(Errors::DecodeTableValueError);
+    }
+    Ok(data.split_at(len))
+}
+
+// every element except the last is prefixed by its length,
+// tuples of fixed size elements sort in element order
+macro_rules! impl_tuple {
+    ($($name:ident $index:tt),+;$last:ident $last_index:tt) => {
+        impl<$($name:LightKVValue,)+ $last:LightKVValue> LightKVValue for ($($name,)+ $last) {
+            type SelfType=($($name::SelfType,)+ $last::SelfType);
+
+            fn from_bytes(data:&[u8])->Result<Self::SelfType,Errors>{
+                let mut data=data;
+                Ok(($({
+                    let (element,rest)=split_element(data)?;
+                    data=rest;
+                    $name::from_bytes(element)?
+                },)+ $last::from_bytes(data)?))
+            }
+
+            fn as_bytes(&self)->Result<Cow<'_,[u8]>,Errors>{
+                let mut buf=Vec::new();
+                $(
+                    let element=self.$index.as_bytes()?;
+                    buf.extend_from_slice(&(element.len() as u32).to_be_bytes());
+                    buf.extend_from_slice(&element);
+                )+
+                buf.extend_from_slice(&self.$last_index.as_bytes()?);
+                Ok(Cow::Owned(buf))
+            }
+
+            fn type_name()->String{
+                let names=[$($name::type_name(),)+ $last::type_name()];
+                format!("({})",names.join(","))
+            }
+
+            fn type_definition()->TypeDefinition{
+                let definitions=[$($name::type_definition(),)+ $last::type_definition()];
+                match definitions.iter().all(|definition|*definition==TypeDefinition::Internal) {
+                    true=>TypeDefinition::Internal,
+                    false=>TypeDefinition::UserCustomize,
+                }
+            }
+        }
+
+        impl<$($name:LightKVKey,)+ $last:LightKVKey> LightKVKey for ($($name,)+ $last) {}
+    };
+}
+
+impl_tuple!(A 0;B 1);
+impl_tuple!(A 0,B 1;C 2);
+impl_tuple!(A 0,B 1,C 2;D 3);
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{LightKVValue, TypeDefinition};
+
+    #[test]
+    fn test_integer_order() {
+        let values=[i64::MIN,-2,-1,0,1,i64::MAX];
+        for pair in values.windows(2) {
+            assert!(pair[0].as_bytes().unwrap()<pair[1].as_bytes().unwrap());
+        }
+        for value in values {
+            assert_eq!(i64::from_bytes(&value.as_bytes().unwrap()).unwrap(),value);
+        }
+        assert!(u16::from_bytes(&[1]).is_err());
+        assert_eq!(u8::type_name(),"u8");
+    }
+
+    #[test]
+    fn test_tuple() {
+        let value=("key".to_string(),7u32,b"bytes".to_vec());
+        assert_eq!(<(String,u32,Vec<u8>)>::from_bytes(&value.as_bytes().unwrap()).unwrap(),value);
+        assert_eq!(<(String,u32,Vec<u8>)>::type_name(),"(string,u32,bytes)");
+        assert_eq!(<(String,u32)>::type_definition(),TypeDefinition::Internal);
+        assert!(<(u32,u32)>::from_bytes(&[0,0]).is_err());
+        assert!((1u32,2u32).as_bytes().unwrap()<(2u32,1u32).as_bytes().unwrap());
+    }
+
+    #[cfg(feature = "serde-value")]
+    #[test]
+    fn test_serde_value() {
+        use crate::errors::Errors;
+        use crate::types::SerdeValue;
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug,PartialEq,Serialize,Deserialize)]
+        struct User{
+            name:String,
+            age:u32,
+        }
+
+        impl SerdeValue for User {
+            const TYPE_NAME:&'static str="user";
+        }
+
+        let user=User{name:"a".to_string(),age:3};
+        assert_eq!(User::from_bytes(&user.as_bytes().unwrap()).unwrap(),user);
+        assert!(User::from_bytes(&[1]).is_err());
+        assert_eq!(<User as LightKVValue>::type_name(),"user");
+        assert_eq!(<(u64,User)>::type_name(),"(u64,user)");
+        assert_eq!(<(String,User)>::type_definition(),TypeDefinition::UserCustomize);
+
+        // failed encoding is an error instead of a panic
+        #[derive(Debug,Deserialize)]
+        struct Broken;
+
+        impl Serialize for Broken {
+            fn serialize<S:serde::Serializer>(&self,_:S)->Result<S::Ok,S::Error>{
+                Err(serde::ser::Error::custom("broken"))
+            }
+        }
+
+        impl SerdeValue for Broken {
+            const TYPE_NAME:&'static str="broken";
+        }
+
+        assert_eq!(Broken.as_bytes().err(),Some(Errors::EncodeTableValueError));
+        assert_eq!(("a".to_string(),Broken).as_bytes().err(),Some(Errors::EncodeTableValueError));
+    }
+}
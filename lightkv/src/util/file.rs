@@ -0,0 +1,107 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+
+// free space of the file system holding the directory
+pub(crate) fn available_disk_size(dir_path: &Path) -> u64 {
+    match fs2::available_space(dir_path) {
+        Ok(size) => size,
+        Err(e) => {
+            log::error!("get available disk space err: {}", e);
+            0
+        }
+    }
+}
+
+// total size of files directly under the directory
+pub(crate) fn dir_disk_size(dir_path: &Path) -> u64 {
+    let entries = match fs::read_dir(dir_path) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+// hard link an immutable file, fall back to copy across file systems,
+// copied file keeps modified time so `is_same_file` recognizes it
+pub(crate) fn link_or_copy(src: &Path, dst: &Path) -> io::Result<()> {
+    if dst.exists() {
+        fs::remove_file(dst)?;
+    }
+    if fs::hard_link(src, dst).is_ok() {
+        return Ok(());
+    }
+    fs::copy(src, dst)?;
+    let dst_file = File::options().write(true).open(dst)?;
+    dst_file.set_modified(fs::metadata(src)?.modified()?)?;
+    dst_file.sync_all()
+}
+
+// whether `dst` was linked or copied from `src` and not changed since
+pub(crate) fn is_same_file(src: &Path, dst: &Path) -> bool {
+    match (fs::metadata(src), fs::metadata(dst)) {
+        (Ok(src), Ok(dst)) => match (src.modified(), dst.modified()) {
+            (Ok(src_modified), Ok(dst_modified)) => src.len() == dst.len() && src_modified == dst_modified,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+// copy the first `len` bytes of a file which may still be appended,
+// an appended source no longer matches the copy by size
+pub(crate) fn copy_file_prefix(src: &Path, dst: &Path, len: u64) -> io::Result<()> {
+    if dst.exists() {
+        fs::remove_file(dst)?;
+    }
+    let src_file = File::open(src)?;
+    let modified = src_file.metadata()?.modified()?;
+    let mut dst_file = File::create(dst)?;
+    io::copy(&mut src_file.take(len), &mut dst_file)?;
+    dst_file.set_modified(modified)?;
+    dst_file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::file::{available_disk_size, copy_file_prefix, dir_disk_size, is_same_file, link_or_copy};
+
+    #[test]
+    fn test_dir_disk_size() {
+        let dir_path = std::env::temp_dir().join("lightkv-dir-disk-size");
+        let _ = std::fs::remove_dir_all(&dir_path);
+        std::fs::create_dir_all(&dir_path).unwrap();
+        assert_eq!(dir_disk_size(&dir_path), 0);
+
+        std::fs::write(dir_path.join("a"), vec![0u8; 100]).unwrap();
+        std::fs::write(dir_path.join("b"), vec![0u8; 28]).unwrap();
+        assert_eq!(dir_disk_size(&dir_path), 128);
+        assert!(available_disk_size(&dir_path) > 0);
+        std::fs::remove_dir_all(&dir_path).unwrap();
+    }
+
+    #[test]
+    fn test_copy_file() {
+        let dir_path = std::env::temp_dir().join("lightkv-copy-file");
+        let _ = std::fs::remove_dir_all(&dir_path);
+        std::fs::create_dir_all(&dir_path).unwrap();
+        std::fs::write(dir_path.join("src"), b"hello lightkv").unwrap();
+
+        link_or_copy(&dir_path.join("src"), &dir_path.join("link")).unwrap();
+        assert_eq!(std::fs::read(dir_path.join("link")).unwrap(), b"hello lightkv");
+        assert!(is_same_file(&dir_path.join("src"), &dir_path.join("link")));
+        copy_file_prefix(&dir_path.join("src"), &dir_path.join("prefix"), 5).unwrap();
+        assert_eq!(std::fs::read(dir_path.join("prefix")).unwrap(), b"hello");
+        // existing destination is replaced
+        copy_file_prefix(&dir_path.join("src"), &dir_path.join("link"), 2).unwrap();
+        assert_eq!(std::fs::read(dir_path.join("link")).unwrap(), b"he");
+        assert!(!is_same_file(&dir_path.join("src"), &dir_path.join("link")));
+        assert_eq!(std::fs::read(dir_path.join("src")).unwrap(), b"hello lightkv");
+        std::fs::remove_dir_all(&dir_path).unwrap();
+    }
+}
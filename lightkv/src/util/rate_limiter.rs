@@ -0,0 +1,54 @@
+use std::time::{Duration, Instant};
+
+/// RateLimiter throttles a single io stream to the configured bytes per second
+pub(crate) struct RateLimiter {
+    // 0 means unlimited
+    bytes_per_sec: u64,
+    start: Instant,
+    consumed: u64,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec,
+            start: Instant::now(),
+            consumed: 0,
+        }
+    }
+
+    // account `size` bytes, sleep if the stream runs ahead of the limit
+    pub(crate) fn consume(&mut self, size: u64) {
+        if self.bytes_per_sec == 0 {
+            return;
+        }
+        self.consumed += size;
+        let expected = Duration::from_secs_f64(self.consumed as f64 / self.bytes_per_sec as f64);
+        let elapsed = self.start.elapsed();
+        if expected > elapsed {
+            std::thread::sleep(expected - elapsed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::rate_limiter::RateLimiter;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_rate_limiter() {
+        let start = Instant::now();
+        let mut unlimited = RateLimiter::new(0);
+        unlimited.consume(1024 * 1024 * 1024);
+        assert!(start.elapsed() < Duration::from_millis(50));
+
+        // 10KB at 100KB/s takes about 100ms
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new(100 * 1024);
+        for _ in 0..10 {
+            limiter.consume(1024);
+        }
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+}
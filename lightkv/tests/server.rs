@@ -0,0 +1,495 @@
+use lightkv::options::{GeneralConfig, LimitsConfig, NetworkType, ServerConfig, StorageConfig};
+use lightkv::server::Server;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+async fn start_server(name: &str, network: NetworkType) -> (Arc<Server>, PathBuf) {
+    start_server_with_limits(name, network, LimitsConfig::default()).await
+}
+
+async fn start_server_with_limits(name: &str, network: NetworkType, limits: LimitsConfig) -> (Arc<Server>, PathBuf) {
+    let path = std::env::temp_dir().join(format!("lightkv-server-{}", name));
+    let _ = std::fs::remove_dir_all(&path);
+    let config = ServerConfig {
+        general_config: GeneralConfig {
+            addr: "127.0.0.1:0".to_string(),
+            network,
+        },
+        storage: StorageConfig {
+            path: path.clone(),
+            ..Default::default()
+        },
+        metrics_addr: Some("127.0.0.1:0".to_string()),
+        limits,
+        ..Default::default()
+    };
+    let server = Arc::new(Server::bind(&config).await.unwrap());
+    let serving = server.clone();
+    tokio::spawn(async move { serving.serve().await });
+    (server, path)
+}
+
+// send raw request bytes and compare the exact reply
+async fn assert_reply(stream: &mut TcpStream, request: &str, reply: &str) {
+    stream.write_all(request.as_bytes()).await.unwrap();
+    assert_received(stream, reply).await;
+}
+
+async fn assert_received(stream: &mut TcpStream, reply: &str) {
+    let mut buf = vec![0u8; reply.len()];
+    tokio::time::timeout(Duration::from_secs(10), stream.read_exact(&mut buf))
+        .await
+        .unwrap_or_else(|_| panic!("wait {:?} timeout, got {:?}", reply, String::from_utf8_lossy(&buf)))
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&buf), reply);
+}
+
+async fn http_get(addr: SocketAddr, path: &str) -> String {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path);
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_resp_commands() {
+    let (server, path) = start_server("commands", NetworkType::Tcp).await;
+    let mut stream = TcpStream::connect(server.local_addr()).await.unwrap();
+
+    assert_reply(&mut stream, "*1\r\n$4\r\nPING\r\n", "+PONG\r\n").await;
+    assert_reply(&mut stream, "*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n", "+OK\r\n").await;
+    assert_reply(&mut stream, "*2\r\n$3\r\nGET\r\n$3\r\nfoo\r\n", "$3\r\nbar\r\n").await;
+    assert_reply(&mut stream, "*2\r\n$3\r\nGET\r\n$7\r\nmissing\r\n", "$-1\r\n").await;
+    assert_reply(&mut stream, "*3\r\n$6\r\nEXISTS\r\n$3\r\nfoo\r\n$7\r\nmissing\r\n", ":1\r\n").await;
+    assert_reply(&mut stream, "*1\r\n$3\r\nGET\r\n", "-ERR wrong number of arguments for 'get' command\r\n").await;
+    assert_reply(&mut stream, "*1\r\n$3\r\nFOO\r\n", "-ERR unknown command 'FOO'\r\n").await;
+
+    // pipelined and inline commands
+    assert_reply(
+        &mut stream,
+        "*3\r\n$3\r\nSET\r\n$5\r\nuser1\r\n$1\r\na\r\n*3\r\n$3\r\nSET\r\n$5\r\nuser2\r\n$1\r\nb\r\nSET item c\r\n",
+        "+OK\r\n+OK\r\n+OK\r\n",
+    )
+    .await;
+    assert_reply(&mut stream, "KEYS user*\r\n", "*2\r\n$5\r\nuser1\r\n$5\r\nuser2\r\n").await;
+    assert_reply(
+        &mut stream,
+        "SCAN 0 COUNT 3\r\n",
+        "*2\r\n$10\r\n7573657232\r\n*3\r\n$3\r\nfoo\r\n$4\r\nitem\r\n$5\r\nuser1\r\n",
+    )
+    .await;
+    // keys written or deleted before the cursor never shift the next page
+    assert_reply(&mut stream, "SET bar d\r\n", "+OK\r\n").await;
+    assert_reply(&mut stream, "DEL bar item\r\n", ":2\r\n").await;
+    assert_reply(&mut stream, "SCAN 7573657232\r\n", "*2\r\n$1\r\n0\r\n*1\r\n$5\r\nuser2\r\n").await;
+    assert_reply(&mut stream, "SCAN 7573657232 MATCH foo\r\n", "*2\r\n$1\r\n0\r\n*0\r\n").await;
+    assert_reply(&mut stream, "SCAN 757\r\n", "-ERR invalid cursor\r\n").await;
+    assert_reply(&mut stream, "SET item c\r\n", "+OK\r\n").await;
+    assert_reply(&mut stream, "DEL foo user1 missing\r\n", ":2\r\n").await;
+    assert_reply(&mut stream, "KEYS *\r\n", "*2\r\n$4\r\nitem\r\n$5\r\nuser2\r\n").await;
+
+    // metrics of served commands
+    let response = http_get(server.metrics_addr().unwrap(), "/metrics").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("lightkv_put_seconds_count 6\n"));
+    assert!(http_get(server.metrics_addr().unwrap(), "/").await.starts_with("HTTP/1.1 404"));
+
+    // invalid data closes connection after an error reply
+    assert_reply(&mut stream, "*1\r\n:1\r\n", "-ERR protocol error\r\n").await;
+    assert_eq!(stream.read(&mut [0u8; 1]).await.unwrap(), 0);
+
+    server.close().unwrap();
+    std::fs::remove_dir_all(path).unwrap();
+}
+
+fn hello_reply(prefix: &str, len: usize, proto: usize, id: usize) -> String {
+    let version = env!("CARGO_PKG_VERSION");
+    format!(
+        "{}{}\r\n$6\r\nserver\r\n$7\r\nlightkv\r\n$7\r\nversion\r\n${}\r\n{}\r\n$5\r\nproto\r\n:{}\r\n$2\r\nid\r\n:{}\r\n\
+         $4\r\nmode\r\n$10\r\nstandalone\r\n$4\r\nrole\r\n$6\r\nmaster\r\n$7\r\nmodules\r\n*0\r\n",
+        prefix,
+        len,
+        version.len(),
+        version,
+        proto,
+        id
+    )
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_resp3_negotiation() {
+    let (server, path) = start_server("resp3", NetworkType::Tcp).await;
+    let mut resp2 = TcpStream::connect(server.local_addr()).await.unwrap();
+    assert_reply(&mut resp2, "PING\r\n", "+PONG\r\n").await;
+    let mut resp3 = TcpStream::connect(server.local_addr()).await.unwrap();
+
+    // RESP2 stays the default, HELLO switches per connection
+    assert_reply(&mut resp2, "HELLO\r\n", &hello_reply("*", 14, 2, 1)).await;
+    assert_reply(&mut resp3, "HELLO 3\r\n", &hello_reply("%", 7, 3, 2)).await;
+    assert_reply(&mut resp3, "HELLO 4\r\n", "-NOPROTO unsupported protocol version\r\n").await;
+    assert_reply(&mut resp2, "GET missing\r\n", "$-1\r\n").await;
+    assert_reply(&mut resp3, "GET missing\r\n", "_\r\n").await;
+
+    // subscription replies and messages are push frames in RESP3
+    assert_reply(&mut resp2, "SUBSCRIBE news\r\n", "*3\r\n$9\r\nsubscribe\r\n$4\r\nnews\r\n:1\r\n").await;
+    assert_reply(&mut resp3, "SUBSCRIBE news\r\n", ">3\r\n$9\r\nsubscribe\r\n$4\r\nnews\r\n:1\r\n").await;
+    assert_reply(
+        &mut resp2,
+        "GET foo\r\n",
+        "-ERR Can't execute 'get': only SUBSCRIBE / UNSUBSCRIBE / PING / QUIT are allowed in this context\r\n",
+    )
+    .await;
+    assert_reply(&mut resp3, "SET foo bar\r\n", "+OK\r\n").await;
+
+    let mut publisher = TcpStream::connect(server.local_addr()).await.unwrap();
+    assert_reply(&mut publisher, "PUBLISH news hi\r\n", ":2\r\n").await;
+    assert_received(&mut resp2, "*3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$2\r\nhi\r\n").await;
+    assert_received(&mut resp3, ">3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$2\r\nhi\r\n").await;
+
+    // closed connections no longer receive messages
+    assert_reply(&mut resp2, "UNSUBSCRIBE\r\n", "*3\r\n$11\r\nunsubscribe\r\n$4\r\nnews\r\n:0\r\n").await;
+    assert_reply(&mut resp2, "GET foo\r\n", "$3\r\nbar\r\n").await;
+    drop(resp3);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_reply(&mut publisher, "PUBLISH news hi\r\n", ":0\r\n").await;
+
+    server.close().unwrap();
+    std::fs::remove_dir_all(path).unwrap();
+}
+
+async fn quic_connect(server: &Server) -> quinn::Connection {
+    let mut roots = quinn::rustls::RootCertStore::empty();
+    roots.add(server.certificate().unwrap().to_vec().into()).unwrap();
+    let client_config = quinn::ClientConfig::with_root_certificates(Arc::new(roots)).unwrap();
+    let mut endpoint = quinn::Endpoint::client("127.0.0.1:0".parse().unwrap()).unwrap();
+    endpoint.set_default_client_config(client_config);
+    endpoint.connect(server.local_addr(), "localhost").unwrap().await.unwrap()
+}
+
+// each request runs on its own stream, the reply is complete once server finishes the stream
+async fn quic_request(conn: &quinn::Connection, request: &str) -> quinn::RecvStream {
+    let (mut send, recv) = conn.open_bi().await.unwrap();
+    send.write_all(request.as_bytes()).await.unwrap();
+    send.finish().unwrap();
+    recv
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_quic_transport() {
+    let (server, path) = start_server("quic", NetworkType::Quic).await;
+    let conn = quic_connect(&server).await;
+
+    let mut recv = quic_request(&conn, "SET foo bar\r\nGET foo\r\n").await;
+    assert_eq!(recv.read_to_end(1024).await.unwrap(), b"+OK\r\n$3\r\nbar\r\n");
+
+    let large_value = "v".repeat(4 * 1024 * 1024);
+    let request = format!("*3\r\n$3\r\nSET\r\n$5\r\nlarge\r\n${}\r\n{}\r\n", large_value.len(), large_value);
+    let mut recv = quic_request(&conn, &request).await;
+    assert_eq!(recv.read_to_end(1024).await.unwrap(), b"+OK\r\n");
+
+    // small reply is not blocked behind a large reply nobody reads yet
+    let mut large = quic_request(&conn, "GET large\r\n").await;
+    let mut small = quic_request(&conn, "GET foo\r\n").await;
+    let small_reply = tokio::time::timeout(Duration::from_secs(10), small.read_to_end(1024)).await;
+    assert_eq!(small_reply.unwrap().unwrap(), b"$3\r\nbar\r\n");
+    let large_reply = large.read_to_end(8 * 1024 * 1024).await.unwrap();
+    assert_eq!(large_reply.len(), large_value.len() + 12);
+
+    conn.close(0u32.into(), b"done");
+    server.close().unwrap();
+    std::fs::remove_dir_all(path).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_quic_protocol_version() {
+    let (server, path) = start_server("quic_protocol_version", NetworkType::Quic).await;
+    let conn = quic_connect(&server).await;
+
+    // HELLO on one stream switches later streams of the same connection
+    let mut recv = quic_request(&conn, "HELLO 3\r\n").await;
+    assert_eq!(recv.read_to_end(1024).await.unwrap(), hello_reply("%", 7, 3, 1).as_bytes());
+    let mut recv = quic_request(&conn, "GET missing\r\n").await;
+    assert_eq!(recv.read_to_end(1024).await.unwrap(), b"_\r\n");
+
+    // other connections keep RESP2
+    let other = quic_connect(&server).await;
+    let mut recv = quic_request(&other, "GET missing\r\n").await;
+    assert_eq!(recv.read_to_end(1024).await.unwrap(), b"$-1\r\n");
+
+    conn.close(0u32.into(), b"done");
+    other.close(0u32.into(), b"done");
+    server.close().unwrap();
+    std::fs::remove_dir_all(path).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_limits() {
+    let limits = LimitsConfig {
+        max_connections: 1,
+        max_request_size: 64,
+    };
+    let (server, path) = start_server_with_limits("limits", NetworkType::Tcp, limits).await;
+    let mut stream = TcpStream::connect(server.local_addr()).await.unwrap();
+    assert_reply(&mut stream, "PING\r\n", "+PONG\r\n").await;
+
+    // connections beyond the limit are rejected
+    let mut rejected = TcpStream::connect(server.local_addr()).await.unwrap();
+    assert_received(&mut rejected, "-ERR max number of clients reached\r\n").await;
+    assert_eq!(rejected.read(&mut [0u8; 1]).await.unwrap(), 0);
+
+    // pending request beyond the limit closes the connection
+    let request = format!(
+        "*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$1000\r\n{}",
+        "v".repeat(100)
+    );
+    assert_reply(&mut stream, &request, "-ERR request exceeds max size\r\n").await;
+    assert_eq!(stream.read(&mut [0u8; 1]).await.unwrap(), 0);
+
+    // permit is released once the connection is closed
+    let mut stream = loop {
+        let mut stream = TcpStream::connect(server.local_addr()).await.unwrap();
+        stream.write_all(b"PING\r\n").await.unwrap();
+        let mut buf = [0u8; 7];
+        stream.read_exact(&mut buf).await.unwrap();
+        if &buf == b"+PONG\r\n" {
+            break stream;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    };
+    assert_reply(&mut stream, "EXISTS foo\r\n", ":0\r\n").await;
+
+    server.close().unwrap();
+    std::fs::remove_dir_all(path).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_hash_commands() {
+    let (server, path) = start_server("hash", NetworkType::Tcp).await;
+    let mut stream = TcpStream::connect(server.local_addr()).await.unwrap();
+
+    assert_reply(&mut stream, "HSET user name alice age 20\r\n", ":2\r\n").await;
+    assert_reply(&mut stream, "HSET user age 21 city x\r\n", ":1\r\n").await;
+    assert_reply(&mut stream, "HSET user age\r\n", "-ERR wrong number of arguments for 'hset' command\r\n").await;
+    assert_reply(&mut stream, "HGET user age\r\n", "$2\r\n21\r\n").await;
+    assert_reply(&mut stream, "HGET user missing\r\n", "$-1\r\n").await;
+    assert_reply(&mut stream, "HLEN user\r\n", ":3\r\n").await;
+    assert_reply(
+        &mut stream,
+        "HGETALL user\r\n",
+        "*6\r\n$3\r\nage\r\n$2\r\n21\r\n$4\r\ncity\r\n$1\r\nx\r\n$4\r\nname\r\n$5\r\nalice\r\n",
+    )
+    .await;
+    assert_reply(&mut stream, "HSCAN user 0 COUNT 2\r\n", "*2\r\n$8\r\n6e616d65\r\n*4\r\n$3\r\nage\r\n$2\r\n21\r\n$4\r\ncity\r\n$1\r\nx\r\n").await;
+    assert_reply(&mut stream, "HSCAN user 6e616d65 COUNT 2\r\n", "*2\r\n$1\r\n0\r\n*2\r\n$4\r\nname\r\n$5\r\nalice\r\n").await;
+    assert_reply(&mut stream, "HSCAN user 6e6\r\n", "-ERR invalid cursor\r\n").await;
+    assert_reply(&mut stream, "HSCAN user 0 MATCH n*\r\n", "*2\r\n$1\r\n0\r\n*2\r\n$4\r\nname\r\n$5\r\nalice\r\n").await;
+    assert_reply(&mut stream, "HDEL user age missing\r\n", ":1\r\n").await;
+
+    // keys of data types are listed with plain keys, internal keys are not
+    assert_reply(&mut stream, "SET plain v\r\n", "+OK\r\n").await;
+    assert_reply(&mut stream, "KEYS *\r\n", "*2\r\n$5\r\nplain\r\n$4\r\nuser\r\n").await;
+    assert_reply(&mut stream, "SCAN 0 COUNT 1\r\n", "*2\r\n$8\r\n75736572\r\n*1\r\n$5\r\nplain\r\n").await;
+    assert_reply(&mut stream, "TYPE user\r\n", "+hash\r\n").await;
+    assert_reply(&mut stream, "TYPE plain\r\n", "+string\r\n").await;
+    assert_reply(&mut stream, "TYPE missing\r\n", "+none\r\n").await;
+    assert_reply(&mut stream, "EXISTS user plain missing\r\n", ":2\r\n").await;
+
+    // a name holds one type, SET replaces a data type
+    let wrong_type = "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n";
+    assert_reply(&mut stream, "GET user\r\n", wrong_type).await;
+    assert_reply(&mut stream, "INCR user\r\n", wrong_type).await;
+    assert_reply(&mut stream, "HSET plain f v\r\n", wrong_type).await;
+    assert_reply(&mut stream, "SADD plain m\r\n", wrong_type).await;
+    assert_reply(&mut stream, "HSET other f v\r\n", ":1\r\n").await;
+    assert_reply(&mut stream, "SET other v\r\n", "+OK\r\n").await;
+    assert_reply(&mut stream, "TYPE other\r\n", "+string\r\n").await;
+    assert_reply(&mut stream, "HLEN other\r\n", wrong_type).await;
+    assert_reply(&mut stream, "DEL other\r\n", ":1\r\n").await;
+    assert_reply(&mut stream, "EXISTS other\r\n", ":0\r\n").await;
+
+    // plain string commands never read or overwrite internal keys
+    let reserved = "-ERR key starts with a byte reserved for internal keys\r\n";
+    stream.write_all(b"*3\r\n$3\r\nSET\r\n$5\r\n\xfeuser\r\n$1\r\nv\r\n").await.unwrap();
+    assert_received(&mut stream, reserved).await;
+    stream.write_all(b"*2\r\n$3\r\nGET\r\n$5\r\n\xfeuser\r\n").await.unwrap();
+    assert_received(&mut stream, reserved).await;
+    stream.write_all(b"*2\r\n$3\r\nDEL\r\n$5\r\n\xfeuser\r\n").await.unwrap();
+    assert_received(&mut stream, ":0\r\n").await;
+    assert_reply(&mut stream, "HLEN user\r\n", ":2\r\n").await;
+    assert_reply(&mut stream, "DEL user plain\r\n", ":2\r\n").await;
+    assert_reply(&mut stream, "HGETALL user\r\n", "*0\r\n").await;
+
+    // hash is a map in RESP3
+    assert_reply(&mut stream, "HSET user name bob\r\n", ":1\r\n").await;
+    assert_reply(&mut stream, "HELLO 3\r\n", &hello_reply("%", 7, 3, 1)).await;
+    assert_reply(&mut stream, "HGETALL user\r\n", "%1\r\n$4\r\nname\r\n$3\r\nbob\r\n").await;
+
+    server.close().unwrap();
+    std::fs::remove_dir_all(path).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_list_commands() {
+    let (server, path) = start_server("list", NetworkType::Tcp).await;
+    let mut stream = TcpStream::connect(server.local_addr()).await.unwrap();
+
+    assert_reply(&mut stream, "RPUSH list c d\r\n", ":2\r\n").await;
+    assert_reply(&mut stream, "LPUSH list b a\r\n", ":4\r\n").await;
+    assert_reply(&mut stream, "LLEN list\r\n", ":4\r\n").await;
+    assert_reply(&mut stream, "LRANGE list 0 -1\r\n", "*4\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\nc\r\n$1\r\nd\r\n").await;
+    assert_reply(&mut stream, "LRANGE list -2 10\r\n", "*2\r\n$1\r\nc\r\n$1\r\nd\r\n").await;
+    assert_reply(&mut stream, "LRANGE list x 1\r\n", "-ERR value is not an integer or out of range\r\n").await;
+    assert_reply(&mut stream, "LINDEX list -1\r\n", "$1\r\nd\r\n").await;
+    assert_reply(&mut stream, "LINDEX list 4\r\n", "$-1\r\n").await;
+    assert_reply(&mut stream, "LPOP list\r\n", "$1\r\na\r\n").await;
+    assert_reply(&mut stream, "RPOP list\r\n", "$1\r\nd\r\n").await;
+    assert_reply(&mut stream, "RPOP missing\r\n", "$-1\r\n").await;
+    assert_reply(&mut stream, "TYPE list\r\n", "+list\r\n").await;
+
+    // commands of another type are rejected
+    assert_reply(
+        &mut stream,
+        "HGET list field\r\n",
+        "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n",
+    )
+    .await;
+    assert_reply(&mut stream, "DEL list\r\n", ":1\r\n").await;
+    assert_reply(&mut stream, "LLEN list\r\n", ":0\r\n").await;
+
+    server.close().unwrap();
+    std::fs::remove_dir_all(path).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_set_commands() {
+    let (server, path) = start_server("set", NetworkType::Tcp).await;
+    let mut stream = TcpStream::connect(server.local_addr()).await.unwrap();
+
+    assert_reply(&mut stream, "SADD set b a b\r\n", ":2\r\n").await;
+    assert_reply(&mut stream, "SADD set c\r\n", ":1\r\n").await;
+    assert_reply(&mut stream, "SCARD set\r\n", ":3\r\n").await;
+    assert_reply(&mut stream, "SISMEMBER set a\r\n", ":1\r\n").await;
+    assert_reply(&mut stream, "SISMEMBER set d\r\n", ":0\r\n").await;
+    assert_reply(&mut stream, "SREM set a d\r\n", ":1\r\n").await;
+    assert_reply(&mut stream, "SMEMBERS set\r\n", "*2\r\n$1\r\nb\r\n$1\r\nc\r\n").await;
+    assert_reply(&mut stream, "TYPE set\r\n", "+set\r\n").await;
+
+    assert_reply(&mut stream, "ZADD zset 2 b 1.5 a 3 c\r\n", ":3\r\n").await;
+    assert_reply(&mut stream, "ZADD zset 5 a\r\n", ":0\r\n").await;
+    assert_reply(&mut stream, "ZADD zset x a\r\n", "-ERR value is not a valid float\r\n").await;
+    assert_reply(&mut stream, "ZSCORE zset a\r\n", "$1\r\n5\r\n").await;
+    assert_reply(&mut stream, "ZSCORE zset missing\r\n", "$-1\r\n").await;
+    assert_reply(&mut stream, "ZCARD zset\r\n", ":3\r\n").await;
+    assert_reply(&mut stream, "ZRANGE zset 0 -1\r\n", "*3\r\n$1\r\nb\r\n$1\r\nc\r\n$1\r\na\r\n").await;
+    assert_reply(&mut stream, "ZRANGE zset 0 0 WITHSCORES\r\n", "*2\r\n$1\r\nb\r\n$1\r\n2\r\n").await;
+    assert_reply(&mut stream, "ZRANGEBYSCORE zset (2 +inf\r\n", "*2\r\n$1\r\nc\r\n$1\r\na\r\n").await;
+    assert_reply(&mut stream, "ZRANGEBYSCORE zset -inf 3 WITHSCORES\r\n", "*4\r\n$1\r\nb\r\n$1\r\n2\r\n$1\r\nc\r\n$1\r\n3\r\n").await;
+    assert_reply(&mut stream, "ZRANK zset a\r\n", ":2\r\n").await;
+    assert_reply(&mut stream, "ZRANK zset missing\r\n", "$-1\r\n").await;
+    assert_reply(&mut stream, "ZREM zset a missing\r\n", ":1\r\n").await;
+    assert_reply(&mut stream, "TYPE zset\r\n", "+zset\r\n").await;
+    assert_reply(
+        &mut stream,
+        "SADD zset a\r\n",
+        "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n",
+    )
+    .await;
+
+    // scores are doubles in RESP3
+    assert_reply(&mut stream, "HELLO 3\r\n", &hello_reply("%", 7, 3, 1)).await;
+    assert_reply(&mut stream, "ZSCORE zset b\r\n", ",2\r\n").await;
+    assert_reply(&mut stream, "SMEMBERS set\r\n", "~2\r\n$1\r\nb\r\n$1\r\nc\r\n").await;
+
+    server.close().unwrap();
+    std::fs::remove_dir_all(path).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_bitmap_and_hyperloglog_commands() {
+    let (server, path) = start_server("bitmap", NetworkType::Tcp).await;
+    let mut stream = TcpStream::connect(server.local_addr()).await.unwrap();
+
+    assert_reply(&mut stream, "SETBIT a 1 1\r\n", ":0\r\n").await;
+    assert_reply(&mut stream, "SETBIT a 1 1\r\n", ":1\r\n").await;
+    assert_reply(&mut stream, "SETBIT a 100000 1\r\n", ":0\r\n").await;
+    assert_reply(&mut stream, "SETBIT a 1 2\r\n", "-ERR bit is not an integer or out of range\r\n").await;
+    assert_reply(&mut stream, "SETBIT a -1 1\r\n", "-ERR bit offset is not an integer or out of range\r\n").await;
+    assert_reply(&mut stream, "GETBIT a 1\r\n", ":1\r\n").await;
+    assert_reply(&mut stream, "GETBIT a 2\r\n", ":0\r\n").await;
+    assert_reply(&mut stream, "BITCOUNT a\r\n", ":2\r\n").await;
+    assert_reply(&mut stream, "BITCOUNT a 0 0\r\n", ":1\r\n").await;
+    assert_reply(&mut stream, "BITCOUNT a 0\r\n", "-ERR syntax error\r\n").await;
+    assert_reply(&mut stream, "SETBIT b 0 1\r\n", ":0\r\n").await;
+    assert_reply(&mut stream, "BITOP OR dest a b\r\n", ":12501\r\n").await;
+    assert_reply(&mut stream, "BITCOUNT dest\r\n", ":3\r\n").await;
+    assert_reply(&mut stream, "BITOP NOT dest a b\r\n", "-ERR BITOP NOT must be called with a single source key.\r\n").await;
+    assert_reply(&mut stream, "BITOP NOT dest b\r\n", ":1\r\n").await;
+    assert_reply(&mut stream, "BITCOUNT dest\r\n", ":7\r\n").await;
+    assert_reply(&mut stream, "TYPE dest\r\n", "+string\r\n").await;
+
+    assert_reply(&mut stream, "PFADD hll a b c\r\n", ":1\r\n").await;
+    assert_reply(&mut stream, "PFADD hll a\r\n", ":0\r\n").await;
+    assert_reply(&mut stream, "PFADD other c d\r\n", ":1\r\n").await;
+    assert_reply(&mut stream, "PFCOUNT hll\r\n", ":3\r\n").await;
+    assert_reply(&mut stream, "PFCOUNT hll other missing\r\n", ":4\r\n").await;
+    assert_reply(&mut stream, "PFMERGE merged hll other\r\n", "+OK\r\n").await;
+    assert_reply(&mut stream, "PFCOUNT merged\r\n", ":4\r\n").await;
+    assert_reply(
+        &mut stream,
+        "SETBIT hll 0 1\r\n",
+        "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n",
+    )
+    .await;
+
+    server.close().unwrap();
+    std::fs::remove_dir_all(path).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_counter_commands() {
+    let (server, path) = start_server("counter", NetworkType::Tcp).await;
+    let mut stream = TcpStream::connect(server.local_addr()).await.unwrap();
+
+    assert_reply(&mut stream, "INCR counter\r\n", ":1\r\n").await;
+    assert_reply(&mut stream, "INCRBY counter 10\r\n", ":11\r\n").await;
+    assert_reply(&mut stream, "DECR counter\r\n", ":10\r\n").await;
+    assert_reply(&mut stream, "DECRBY counter 15\r\n", ":-5\r\n").await;
+    assert_reply(&mut stream, "GET counter\r\n", "$2\r\n-5\r\n").await;
+    assert_reply(&mut stream, "INCRBY counter abc\r\n", "-ERR value is not an integer or out of range\r\n").await;
+    assert_reply(&mut stream, "SET counter 9223372036854775807\r\n", "+OK\r\n").await;
+    assert_reply(&mut stream, "INCR counter\r\n", "-ERR increment or decrement would overflow\r\n").await;
+    assert_reply(&mut stream, "SET text abc\r\n", "+OK\r\n").await;
+    assert_reply(&mut stream, "INCR text\r\n", "-ERR value is not an integer or out of range\r\n").await;
+    assert_reply(&mut stream, "INCR\r\n", "-ERR wrong number of arguments for 'incr' command\r\n").await;
+
+    assert_reply(&mut stream, "APPEND text de\r\n", ":5\r\n").await;
+    assert_reply(&mut stream, "APPEND new value\r\n", ":5\r\n").await;
+    assert_reply(&mut stream, "GET text\r\n", "$5\r\nabcde\r\n").await;
+
+    server.close().unwrap();
+    std::fs::remove_dir_all(path).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_conditional_set() {
+    let (server, path) = start_server("conditional_set", NetworkType::Tcp).await;
+    let mut stream = TcpStream::connect(server.local_addr()).await.unwrap();
+
+    assert_reply(&mut stream, "SET lock a XX\r\n", "$-1\r\n").await;
+    assert_reply(&mut stream, "SET lock a NX\r\n", "+OK\r\n").await;
+    assert_reply(&mut stream, "SET lock b nx\r\n", "$-1\r\n").await;
+    assert_reply(&mut stream, "GET lock\r\n", "$1\r\na\r\n").await;
+    assert_reply(&mut stream, "SET lock c XX\r\n", "+OK\r\n").await;
+    assert_reply(&mut stream, "GET lock\r\n", "$1\r\nc\r\n").await;
+    assert_reply(&mut stream, "SET lock d EX\r\n", "-ERR syntax error\r\n").await;
+    assert_reply(&mut stream, "SET lock d NX XX\r\n", "-ERR syntax error\r\n").await;
+
+    server.close().unwrap();
+    std::fs::remove_dir_all(path).unwrap();
+}